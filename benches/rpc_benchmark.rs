@@ -4,8 +4,8 @@ use std::time::Duration;
 
 use async_timer_rs::hashed::Timeout;
 use async_timer_rs::Timer;
-use criterion::Criterion;
 use criterion::{criterion_group, criterion_main};
+use criterion::{Criterion, Throughput};
 
 // This is a struct that tells Criterion.rs to use the "futures" crate's current-thread executor
 use criterion::async_executor::FuturesExecutor;
@@ -13,13 +13,14 @@ use criterion::async_executor::FuturesExecutor;
 use futures::{
     channel::mpsc::{self, SendError, Sender},
     executor::ThreadPool,
+    future::try_join_all,
     stream::BoxStream,
     task::SpawnExt,
     StreamExt,
 };
 use jsonrpc_rs::channel::RPCData;
 use jsonrpc_rs::RPCError;
-use jsonrpc_rs::{channel::TransportChannel, Client, RPCResult, Server};
+use jsonrpc_rs::{channel::TransportChannel, Client, Codec, JsonCodec, RPCResult, Request, Server};
 use once_cell::sync::OnceCell;
 
 struct MPSCTransportChannel(BoxStream<'static, RPCResult<RPCData>>, Sender<RPCData>);
@@ -51,27 +52,46 @@ impl TransportChannel for MPSCTransportChannel {
     }
 }
 
-async fn prepare_bench() -> RPCResult<(Server, Client)> {
+/// Calls in flight at once in the batch throughput bench.
+const BATCH: usize = 100;
+
+/// Clients connected to one server in the multi-client throughput bench.
+const CLIENTS: usize = 8;
+
+/// Connect a new client to `server` over an in-memory transport.
+fn connect(server: &Server) -> Client {
     let (server_output, client_input) = mpsc::channel(20);
 
     let (client_output, server_input) = mpsc::channel(20);
 
-    let server_transport = MPSCTransportChannel(server_input.map(|c| Ok(c)).boxed(), server_output);
+    let server_transport = MPSCTransportChannel(server_input.map(Ok).boxed(), server_output);
+
+    let client_transport = MPSCTransportChannel(client_input.map(Ok).boxed(), client_output);
+
+    server.clone().accept(server_transport);
+
+    Client::new("Test", client_transport)
+}
 
-    let client_transport = MPSCTransportChannel(client_input.map(|c| Ok(c)).boxed(), client_output);
+async fn prepare_bench(coalesce: bool) -> RPCResult<(Server, Client)> {
+    let mut builder = Server::builder("");
 
-    let mut server = Server::default();
+    // Responses completed together are written as one batch frame.
+    if coalesce {
+        builder = builder.coalesce_responses(BATCH);
+    }
 
-    server
+    let server = builder
         .async_handle("echo", |msg: String| async { Ok(Some(msg)) })
         .handle("event", |msg: String| {
             log::debug!("{}", msg);
             Ok(None::<String>)
-        });
+        })
+        .build();
 
-    server.accept(server_transport);
+    let client = connect(&server);
 
-    Ok((server, Client::new("Test", client_transport)))
+    Ok((server, client))
 }
 
 async fn blocking_pingpong(mut client: Client) -> RPCResult<()> {
@@ -92,8 +112,27 @@ async fn timeout_pingpong(mut client: Client) -> RPCResult<()> {
     Ok(())
 }
 
+async fn batch_pingpong(client: Client) -> RPCResult<()> {
+    let echoes = try_join_all((0..BATCH).map(|_| {
+        let mut client = client.clone();
+
+        async move { client.call::<_, String>("echo", "world").await }
+    }))
+    .await?;
+
+    assert!(echoes.iter().all(|echo| echo == "world"));
+
+    Ok(())
+}
+
+async fn multi_client_pingpong(clients: Vec<Client>) -> RPCResult<()> {
+    try_join_all(clients.into_iter().map(batch_pingpong)).await?;
+
+    Ok(())
+}
+
 fn call_benchmark(c: &mut Criterion) {
-    let (_server, client) = async_std::task::block_on(async { prepare_bench().await.unwrap() });
+    let (server, client) = async_std::task::block_on(async { prepare_bench(false).await.unwrap() });
 
     // let mut group = c.benchmark_group("jsonrpc");
 
@@ -110,6 +149,49 @@ fn call_benchmark(c: &mut Criterion) {
     });
 
     // group.finish();
+
+    let (_coalescing_server, coalescing_client) =
+        async_std::task::block_on(async { prepare_bench(true).await.unwrap() });
+
+    c.bench_function("json encode", |b| {
+        b.iter(|| JsonCodec::encode(&Request::call(1, "echo", ["world"])).unwrap());
+    });
+
+    let large = "x".repeat(16 * 1024);
+
+    c.bench_function("json encode large", |b| {
+        b.iter(|| JsonCodec::encode(&Request::call(1, "echo", [&large])).unwrap());
+    });
+
+    let mut group = c.benchmark_group("batch");
+
+    group.throughput(Throughput::Elements(BATCH as u64));
+
+    group.bench_function("pingpong throughput", |b| {
+        b.to_async(FuturesExecutor)
+            .iter(|| batch_pingpong(client.clone()));
+    });
+
+    group.bench_function("coalesced pingpong throughput", |b| {
+        b.to_async(FuturesExecutor)
+            .iter(|| batch_pingpong(coalescing_client.clone()));
+    });
+
+    group.finish();
+
+    // Every client has its own session on one server.
+    let clients = (0..CLIENTS).map(|_| connect(&server)).collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("multi-client");
+
+    group.throughput(Throughput::Elements((CLIENTS * BATCH) as u64));
+
+    group.bench_function("pingpong throughput", |b| {
+        b.to_async(FuturesExecutor)
+            .iter(|| multi_client_pingpong(clients.clone()));
+    });
+
+    group.finish();
 }
 
 criterion_group!(benches, call_benchmark);
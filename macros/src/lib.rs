@@ -0,0 +1,106 @@
+//! Procedural macros of [jsonrpc-rs](https://docs.rs/jsonrpc-rs), enabled by its `macros` feature.
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput, ItemTrait};
+
+mod params;
+mod rpc;
+
+/// Derive a `Deserialize` implementation accepting method params either by name or by position.
+///
+/// The struct deserializes from an object keyed by field name, as `#[derive(Deserialize)]`
+/// does, and also from an array holding the fields in declaration order. Missing `Option`
+/// fields, or trailing array elements of `Option` fields, deserialize as `None`.
+///
+/// Field names honor `#[serde(rename = "...")]`, so they match a derived `Serialize`.
+///
+/// ```ignore
+/// #[derive(Serialize, RpcParams)]
+/// struct Transfer {
+///     from: String,
+///     to: String,
+///     amount: u64,
+/// }
+///
+/// // Both `{"from":"a","to":"b","amount":1}` and `["a","b",1]` are accepted.
+/// server.handle("transfer", |transfer: Transfer| Ok(Some(transfer.amount)));
+/// ```
+#[proc_macro_derive(RpcParams, attributes(serde))]
+pub fn derive_rpc_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    params::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Declare RPC methods as the async fns of a trait.
+///
+/// Every method takes `&self` and returns `RPCResult<T>`. The method name defaults to
+/// the fn name, `#[rpc(name = "...")]` on a fn overrides it. Arguments are passed as
+/// positional params.
+///
+/// The macro adds `Send + Sync + 'static` to the supertraits and a provided
+/// `register_rpc` method registering all methods of an implementation on a `Server`.
+/// It also generates the typed client `<Trait>Client` wrapping a `Client` with one
+/// method per RPC, and the `<trait>_methods` module of method name constants used by both.
+///
+/// ```ignore
+/// #[rpc]
+/// pub trait Calculator {
+///     async fn add(&self, a: i32, b: i32) -> RPCResult<i32>;
+/// }
+///
+/// struct Calc;
+///
+/// impl Calculator for Calc {
+///     async fn add(&self, a: i32, b: i32) -> RPCResult<i32> {
+///         Ok(a + b)
+///     }
+/// }
+///
+/// Calc.register_rpc(&mut server);
+///
+/// let sum = CalculatorClient::new(client).add(1, 2).await?;
+/// ```
+#[proc_macro_attribute]
+pub fn rpc(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new(proc_macro2::Span::call_site(), "#[rpc] takes no arguments")
+            .into_compile_error()
+            .into();
+    }
+
+    let item = parse_macro_input!(item as ItemTrait);
+
+    rpc::expand(item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generate a typed client from a list of async fn signatures.
+///
+/// The struct wraps a `Client` with one method per signature, serializing the
+/// arguments as positional params and deserializing the typed result. Signatures
+/// take no receiver, return `RPCResult<T>` and accept `#[rpc(name = "...")]` as in
+/// [`macro@rpc`]. Method names are defined once in the generated `<struct>_methods`
+/// module, e.g. `echo_client_methods::ECHO`, so servers can register handlers under
+/// the same constants.
+///
+/// ```ignore
+/// rpc_client! {
+///     pub struct EchoClient {
+///         async fn echo(msg: String) -> RPCResult<String>;
+///     }
+/// }
+///
+/// server.handle(echo_client_methods::ECHO, |(msg,): (String,)| Ok(Some(msg)));
+///
+/// let echo = EchoClient::new(client).echo("hello".to_owned()).await?;
+/// ```
+#[proc_macro]
+pub fn rpc_client(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as rpc::ClientInput);
+
+    rpc::expand_client(input).into()
+}
@@ -0,0 +1,192 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, Data, DeriveInput, Fields, GenericParam, LitStr, Result, Type};
+
+/// Named field of the derived struct.
+struct Field<'a> {
+    ident: &'a syn::Ident,
+    name: String,
+    ty: &'a Type,
+}
+
+pub(crate) fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = field.ident.as_ref().expect("named field");
+
+                    Ok(Field {
+                        ident,
+                        name: rename(field)?.unwrap_or_else(|| ident.to_string()),
+                        ty: &field.ty,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "RpcParams requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "RpcParams can only be derived for structs",
+            ))
+        }
+    };
+
+    let serde = quote!(::jsonrpc_rs::__private::serde);
+
+    let ident = &input.ident;
+
+    let expecting = format!("params of {} as an array or an object", ident);
+
+    let (struct_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut generics = input.generics.clone();
+
+    for param in generics.params.iter_mut() {
+        if let GenericParam::Type(param) = param {
+            param.bounds.push(parse_quote!(#serde::Deserialize<'de>));
+        }
+    }
+
+    generics.params.insert(0, parse_quote!('de));
+
+    let (impl_generics, _, _) = generics.split_for_impl();
+
+    let idents = fields.iter().map(|field| field.ident).collect::<Vec<_>>();
+
+    let vars = fields
+        .iter()
+        .map(|field| format_ident!("__{}", field.ident))
+        .collect::<Vec<_>>();
+
+    let names = fields
+        .iter()
+        .map(|field| LitStr::new(&field.name, field.ident.span()))
+        .collect::<Vec<_>>();
+
+    let types = fields.iter().map(|field| field.ty).collect::<Vec<_>>();
+
+    let indexes = 0..fields.len();
+
+    let len = fields.len();
+
+    Ok(quote! {
+        const _: () = {
+            struct __Visitor #struct_generics #where_clause {
+                marker: ::std::marker::PhantomData<#ident #ty_generics>,
+            }
+
+            /// Deserialize a field missing from the params, only `Option` fields may be missing.
+            fn __missing<'de, T, E>(name: &'static str) -> ::std::result::Result<T, E>
+            where
+                T: #serde::Deserialize<'de>,
+                E: #serde::de::Error,
+            {
+                T::deserialize(#serde::de::value::UnitDeserializer::<E>::new())
+                    .map_err(|_: E| E::missing_field(name))
+            }
+
+            impl #impl_generics #serde::de::Visitor<'de> for __Visitor #ty_generics #where_clause {
+                type Value = #ident #ty_generics;
+
+                fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                    formatter.write_str(#expecting)
+                }
+
+                fn visit_seq<A>(self, mut seq: A) -> ::std::result::Result<Self::Value, A::Error>
+                where
+                    A: #serde::de::SeqAccess<'de>,
+                {
+                    #(
+                        let #vars = match seq.next_element::<#types>()? {
+                            Some(value) => value,
+                            None => __missing::<#types, A::Error>(#names)
+                                .map_err(|_| #serde::de::Error::invalid_length(#indexes, &self))?,
+                        };
+                    )*
+
+                    if seq.next_element::<#serde::de::IgnoredAny>()?.is_some() {
+                        return Err(#serde::de::Error::invalid_length(#len + 1, &self));
+                    }
+
+                    Ok(#ident { #(#idents: #vars),* })
+                }
+
+                fn visit_map<A>(self, mut map: A) -> ::std::result::Result<Self::Value, A::Error>
+                where
+                    A: #serde::de::MapAccess<'de>,
+                {
+                    #(let mut #vars: ::std::option::Option<#types> = None;)*
+
+                    while let Some(key) = map.next_key::<::std::string::String>()? {
+                        match key.as_str() {
+                            #(
+                                #names => {
+                                    if #vars.is_some() {
+                                        return Err(#serde::de::Error::duplicate_field(#names));
+                                    }
+
+                                    #vars = Some(map.next_value()?);
+                                }
+                            )*
+                            _ => {
+                                map.next_value::<#serde::de::IgnoredAny>()?;
+                            }
+                        }
+                    }
+
+                    #(
+                        let #vars = match #vars {
+                            Some(value) => value,
+                            None => __missing::<#types, A::Error>(#names)?,
+                        };
+                    )*
+
+                    Ok(#ident { #(#idents: #vars),* })
+                }
+            }
+
+            impl #impl_generics #serde::Deserialize<'de> for #ident #ty_generics #where_clause {
+                fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+                where
+                    D: #serde::Deserializer<'de>,
+                {
+                    deserializer.deserialize_any(__Visitor {
+                        marker: ::std::marker::PhantomData,
+                    })
+                }
+            }
+        };
+    })
+}
+
+/// Returns the name set by `#[serde(rename = "...")]` on `field`.
+fn rename(field: &syn::Field) -> Result<Option<String>> {
+    let mut name = None;
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                name = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(name)
+}
@@ -0,0 +1,384 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_quote, Attribute, FnArg, Ident, ItemTrait, LitStr, Pat, Result, ReturnType, Signature,
+    Token, TraitItem, TraitItemFn, Type, Visibility,
+};
+
+/// RPC method declared by one async fn signature.
+pub(crate) struct Method {
+    ident: Ident,
+    name: LitStr,
+    args: Vec<(Ident, Type)>,
+    output: Type,
+}
+
+impl Method {
+    /// Name of the method name constant.
+    fn constant(&self) -> Ident {
+        format_ident!("{}", self.ident.to_string().to_uppercase())
+    }
+}
+
+/// Input of `rpc_client!`, a struct declaring async fn signatures without receiver.
+pub(crate) struct ClientInput {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    ident: Ident,
+    methods: Vec<Method>,
+}
+
+impl Parse for ClientInput {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+
+        input.parse::<Token![struct]>()?;
+
+        let ident = input.parse()?;
+
+        let content;
+
+        syn::braced!(content in input);
+
+        let mut methods = vec![];
+
+        while !content.is_empty() {
+            let mut attrs = content.call(Attribute::parse_outer)?;
+            let sig = content.parse::<Signature>()?;
+
+            content.parse::<Token![;]>()?;
+
+            methods.push(method(&mut attrs, &sig, false)?);
+        }
+
+        Ok(Self {
+            attrs,
+            vis,
+            ident,
+            methods,
+        })
+    }
+}
+
+pub(crate) fn expand_client(input: ClientInput) -> TokenStream {
+    let names = format_ident!("{}_methods", snake_case(&input.ident.to_string()));
+
+    let constants = constants(&input.vis, &input.ident, &names, &input.methods);
+
+    let client = client(
+        &input.attrs,
+        &input.vis,
+        &input.ident,
+        &names,
+        &input.methods,
+    );
+
+    quote! {
+        #constants
+
+        #client
+    }
+}
+
+pub(crate) fn expand(mut item: ItemTrait) -> Result<TokenStream> {
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            "#[rpc] traits can't be generic",
+        ));
+    }
+
+    let mut methods = vec![];
+
+    for trait_item in item.items.iter_mut() {
+        match trait_item {
+            TraitItem::Fn(f) => methods.push(trait_method(f)?),
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "#[rpc] traits may only declare async fns",
+                ))
+            }
+        }
+    }
+
+    item.supertraits.push(parse_quote!(::std::marker::Send));
+    item.supertraits.push(parse_quote!(::std::marker::Sync));
+    item.supertraits.push(parse_quote!('static));
+
+    let vis = &item.vis;
+    let ident = &item.ident;
+    let names = format_ident!("{}_methods", snake_case(&ident.to_string()));
+
+    let registers = methods.iter().map(|method| {
+        let ident = &method.ident;
+        let constant = method.constant();
+
+        let args = method.args.iter().map(|(arg, _)| arg).collect::<Vec<_>>();
+        let types = method.args.iter().map(|(_, ty)| ty).collect::<Vec<_>>();
+
+        if args.is_empty() {
+            quote! {
+                server.async_handle0(#names::#constant, {
+                    let service = service.clone();
+
+                    move || {
+                        let service = service.clone();
+
+                        async move { service.#ident().await.map(Some) }
+                    }
+                });
+            }
+        } else {
+            quote! {
+                server.async_handle(#names::#constant, {
+                    let service = service.clone();
+
+                    move |(#(#args,)*): (#(#types,)*)| {
+                        let service = service.clone();
+
+                        async move { service.#ident(#(#args),*).await.map(Some) }
+                    }
+                });
+            }
+        }
+    });
+
+    let register_doc = format!(
+        "Register the methods of this [`{}`] implementation on `server`.",
+        ident
+    );
+
+    item.items.push(parse_quote! {
+        #[doc = #register_doc]
+        fn register_rpc<K: ::jsonrpc_rs::Codec>(
+            self,
+            server: &mut ::jsonrpc_rs::Server<K>,
+        ) -> &mut ::jsonrpc_rs::Server<K>
+        where
+            Self: Sized,
+        {
+            let service = ::std::sync::Arc::new(self);
+
+            #(#registers)*
+
+            server
+        }
+    });
+
+    let constants = constants(vis, ident, &names, &methods);
+
+    let client_doc = format!("Typed client of the [`{}`] methods.", ident);
+
+    let client = client(
+        &[parse_quote!(#[doc = #client_doc])],
+        vis,
+        &format_ident!("{}Client", ident),
+        &names,
+        &methods,
+    );
+
+    Ok(quote! {
+        #item
+
+        #constants
+
+        #client
+    })
+}
+
+/// Generate the module of method name constants shared by server and client.
+fn constants(vis: &Visibility, ident: &Ident, names: &Ident, methods: &[Method]) -> TokenStream {
+    let doc = format!("Method names of [`{}`].", ident);
+
+    let constants = methods.iter().map(|method| {
+        let constant = method.constant();
+        let name = &method.name;
+
+        quote! {
+            pub const #constant: &str = #name;
+        }
+    });
+
+    quote! {
+        #[doc = #doc]
+        #vis mod #names {
+            #(#constants)*
+        }
+    }
+}
+
+/// Generate the typed client struct wrapping a `Client`.
+fn client(
+    attrs: &[Attribute],
+    vis: &Visibility,
+    ident: &Ident,
+    names: &Ident,
+    methods: &[Method],
+) -> TokenStream {
+    let calls = methods.iter().map(|method| {
+        let ident = &method.ident;
+        let constant = method.constant();
+        let output = &method.output;
+
+        let args = method.args.iter().map(|(arg, _)| arg).collect::<Vec<_>>();
+        let types = method.args.iter().map(|(_, ty)| ty);
+
+        let doc = format!("Call the `{}` method.", method.name.value());
+
+        quote! {
+            #[doc = #doc]
+            pub async fn #ident(&mut self, #(#args: #types),*) -> #output {
+                self.client.call(#names::#constant, (#(#args,)*)).await
+            }
+        }
+    });
+
+    quote! {
+        #(#attrs)*
+        #[derive(Clone)]
+        #vis struct #ident<K: ::jsonrpc_rs::Codec = ::jsonrpc_rs::JsonCodec> {
+            client: ::jsonrpc_rs::Client<K>,
+        }
+
+        impl<K: ::jsonrpc_rs::Codec> #ident<K> {
+            /// Call the methods through `client`.
+            pub fn new(client: ::jsonrpc_rs::Client<K>) -> Self {
+                Self { client }
+            }
+
+            /// Returns the underlying untyped client.
+            pub fn client(&mut self) -> &mut ::jsonrpc_rs::Client<K> {
+                &mut self.client
+            }
+
+            #(#calls)*
+        }
+    }
+}
+
+/// Parse one trait fn, rewriting it to return a `Send` future.
+fn trait_method(f: &mut TraitItemFn) -> Result<Method> {
+    let method = method(&mut f.attrs, &f.sig, true)?;
+
+    let output = &method.output;
+
+    f.sig.asyncness = None;
+    f.sig.output = parse_quote! {
+        -> impl ::std::future::Future<Output = #output> + ::std::marker::Send
+    };
+
+    if let Some(block) = f.default.as_mut() {
+        let stmts = &block.stmts;
+
+        *block = parse_quote!({ async move { #(#stmts)* } });
+    }
+
+    Ok(method)
+}
+
+/// Parse an async fn signature, taking `&self` if `receiver` is set.
+///
+/// `#[rpc(name = "...")]` attributes are removed from `attrs`.
+fn method(attrs: &mut Vec<Attribute>, sig: &Signature, receiver: bool) -> Result<Method> {
+    let mut name = LitStr::new(&sig.ident.to_string(), sig.ident.span());
+
+    let mut err = None;
+
+    attrs.retain(|attr| {
+        if !attr.path().is_ident("rpc") {
+            return true;
+        }
+
+        if let Err(e) = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = meta.value()?.parse()?;
+
+                Ok(())
+            } else {
+                Err(meta.error("unsupported #[rpc] method attribute"))
+            }
+        }) {
+            err = Some(e);
+        }
+
+        false
+    });
+
+    if let Some(err) = err {
+        return Err(err);
+    }
+
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            sig,
+            "RPC methods must be async fns",
+        ));
+    }
+
+    let mut inputs = sig.inputs.iter();
+
+    if receiver {
+        match inputs.next() {
+            Some(FnArg::Receiver(receiver))
+                if receiver.reference.is_some() && receiver.mutability.is_none() => {}
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    sig,
+                    "#[rpc] methods must take &self",
+                ))
+            }
+        }
+    }
+
+    let args = inputs
+        .map(|arg| match arg {
+            FnArg::Typed(arg) => match arg.pat.as_ref() {
+                Pat::Ident(pat) => Ok((pat.ident.clone(), arg.ty.as_ref().clone())),
+                pat => Err(syn::Error::new_spanned(
+                    pat,
+                    "RPC method arguments must be plain identifiers",
+                )),
+            },
+            FnArg::Receiver(receiver) => Err(syn::Error::new_spanned(receiver, "unexpected self")),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let output = match &sig.output {
+        ReturnType::Type(_, ty) => ty.as_ref().clone(),
+        ReturnType::Default => {
+            return Err(syn::Error::new_spanned(
+                sig,
+                "RPC methods must return RPCResult<T>",
+            ))
+        }
+    };
+
+    Ok(Method {
+        ident: sig.ident.clone(),
+        name,
+        args,
+        output,
+    })
+}
+
+/// Convert an UpperCamelCase type name into snake_case.
+fn snake_case(ident: &str) -> String {
+    let mut snake = String::new();
+
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+
+    snake
+}
@@ -1,32 +1,112 @@
+mod builder;
+pub use builder::*;
+mod config;
+pub use config::*;
 mod recv;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
-use async_timer_rs::{hashed::Timeout, Timer};
 use completeq_rs::{error::CompleteQError, oneshot::EventReceiver};
 use futures::{
     channel::mpsc::{self, Sender},
-    SinkExt,
+    future::AbortHandle,
+    FutureExt, SinkExt,
 };
 use recv::*;
 mod send;
 use send::*;
+mod keepalive;
+use keepalive::*;
+mod failover;
+pub use failover::*;
+mod notification;
+use notification::NotificationListeners;
+mod pool;
+pub use pool::*;
+mod reconnect;
+pub use reconnect::*;
+mod retry;
+pub use retry::*;
+mod stream;
+use stream::CallStreams;
+mod subscription;
+pub use subscription::Subscription;
+use subscription::Subscriptions;
+#[cfg(feature = "blocking")]
+mod blocking;
+#[cfg(feature = "tower")]
+mod service;
+#[cfg(feature = "blocking")]
+pub use blocking::*;
 mod user_event;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use user_event::*;
 
 use crate::{
     channel::{RPCData, TransportChannel},
-    map_error, RPCResult, Request,
+    executor::spawn,
+    map_error,
+    timer::{timeout, Timeout, Timer},
+    trace::{call_span, Instrument, Span},
+    Codec, ErrorCode, JsonCodec, Metrics, RPCError, RPCResult, Request, CANCEL_METHOD,
 };
 
 #[derive(Clone)]
-pub struct Client {
-    output_sender: Sender<RPCData>,
+pub struct Client<K: Codec = JsonCodec> {
+    output_sender: Sender<OutputItem>,
     completed_q: RPCCompletedQ,
+    backlog: Backlog,
+    subscriptions: Subscriptions,
+    streams: CallStreams,
+    listeners: NotificationListeners,
+    /// Shared by all clones, background tasks holding only a weak reference stop
+    /// once every clone was dropped.
+    handle: Arc<()>,
+    /// Background tasks of the connection, aborted by [`Client::close`].
+    tasks: Arc<Mutex<Vec<AbortHandle>>>,
+    timeout: Option<Duration>,
+    metrics: Option<Arc<dyn Metrics>>,
+    slow_call_threshold: Option<Duration>,
+    interceptors: Arc<Vec<Interceptor>>,
+    #[cfg(feature = "traceparent")]
+    trace_context: Option<TraceContext>,
+    codec: PhantomData<K>,
 }
 
 impl Client {
+    /// Create a client with the default configuration, see [`Client::builder`].
     pub fn new<C, S>(tag: S, channel: C) -> Self
+    where
+        C: TransportChannel,
+        S: AsRef<str>,
+    {
+        Self::builder(tag.as_ref()).build(channel)
+    }
+
+    /// Configure a new client, started by [`ClientBuilder::build`].
+    pub fn builder<S: Into<String>>(tag: S) -> ClientBuilder {
+        ClientBuilder::new(tag)
+    }
+
+    /// Create a client with custom [`ClientConfig`].
+    pub fn with_config<C, S>(tag: S, channel: C, config: ClientConfig) -> Self
+    where
+        C: TransportChannel,
+        S: AsRef<str>,
+    {
+        Self::with_codec(tag, channel, JsonCodec, config)
+    }
+}
+
+impl<K: Codec> Client<K> {
+    /// Create a client exchanging frames encoded by `codec`.
+    pub fn with_codec<C, S>(tag: S, channel: C, _codec: K, config: ClientConfig) -> Self
     where
         C: TransportChannel,
         S: AsRef<str>,
@@ -35,54 +115,211 @@ impl Client {
 
         let client_id = format!("{}_{}", tag.as_ref(), ID.fetch_add(1, Ordering::SeqCst));
 
-        let (output_sender, output_receiver) = mpsc::channel(100);
+        let (output_sender, output_receiver) = mpsc::channel(config.output_capacity());
+
+        let completed_q = RPCCompletedQ::new(
+            config.max_inflight,
+            config.inflight_overflow,
+            config.max_request_id.unwrap_or(usize::MAX),
+        );
+
+        let backlog = Backlog::new(config.high_watermark);
+
+        let subscriptions = Subscriptions::default();
+
+        let streams = CallStreams::default();
 
-        let completed_q = RPCCompletedQ::new();
+        let listeners = NotificationListeners::default();
 
         let (input, output) = channel.framed();
 
-        C::spawn(send_loop::<C, String>(
-            client_id.clone(),
-            output,
-            output_receiver,
-            completed_q.clone(),
-        ));
+        let executor = config.executor.clone();
 
-        C::spawn(recv_loop::<C, String>(
-            client_id,
-            input,
-            completed_q.clone(),
-        ));
+        let send_task = spawn::<C, _>(
+            executor.as_ref(),
+            send_loop::<C, String>(
+                client_id.clone(),
+                output,
+                output_receiver,
+                completed_q.clone(),
+                backlog.clone(),
+                config.metrics.clone(),
+            ),
+        );
 
-        Self {
+        let timeout = config.timeout;
+
+        let metrics = config.metrics.clone();
+
+        let slow_call_threshold = config.slow_call_threshold;
+
+        let interceptors = Arc::new(config.interceptors.clone());
+
+        #[cfg(feature = "traceparent")]
+        let trace_context = config.trace_context.clone();
+
+        let keepalive = config
+            .keepalive
+            .clone()
+            .map(|keepalive| (keepalive, config.ping_method().to_owned()));
+
+        let recv_task = spawn::<C, _>(
+            executor.as_ref(),
+            recv_loop::<C, K, String>(
+                client_id.clone(),
+                input,
+                completed_q.clone(),
+                Notifications {
+                    subscriptions: subscriptions.clone(),
+                    streams: streams.clone(),
+                    listeners: listeners.clone(),
+                },
+                config,
+                output_sender.clone(),
+            ),
+        );
+
+        let client = Self {
             output_sender,
             completed_q,
+            backlog,
+            subscriptions,
+            streams,
+            listeners,
+            handle: Arc::new(()),
+            tasks: Arc::new(Mutex::new(vec![send_task, recv_task])),
+            timeout,
+            metrics,
+            slow_call_threshold,
+            interceptors,
+            #[cfg(feature = "traceparent")]
+            trace_context,
+            codec: PhantomData,
+        };
+
+        if let Some((keepalive, method)) = keepalive {
+            let pinger = Self {
+                handle: Arc::new(()),
+                ..client.clone()
+            };
+
+            let keepalive_task = spawn::<C, _>(
+                executor.as_ref(),
+                keepalive_loop(
+                    client_id,
+                    pinger,
+                    Arc::downgrade(&client.handle),
+                    keepalive,
+                    method,
+                ),
+            );
+
+            client.tasks.lock().unwrap().push(keepalive_task);
         }
+
+        client
     }
 
-    pub async fn send<P>(&mut self, method: &str, params: P) -> RPCResult<Responser<Timeout>>
+    /// Returns true once the connection closed, every later call fails.
+    ///
+    /// Pending calls of a closed client fail with [`ErrorCode::ConnectionClosed`].
+    pub fn is_closed(&self) -> bool {
+        self.output_sender.is_closed()
+    }
+
+    /// Number of calls waiting for their response.
+    ///
+    /// Once [`ClientConfig::max_inflight`] calls are pending, new calls wait for one to
+    /// complete or fail, see [`ClientConfig::inflight_overflow`].
+    pub fn inflight_count(&self) -> usize {
+        self.completed_q.len()
+    }
+
+    /// Number of frames queued for sending, not yet taken by the transport.
+    ///
+    /// Calls fail while [`ClientConfig::high_watermark`] frames are queued.
+    pub fn queued_count(&self) -> usize {
+        self.backlog.len()
+    }
+
+    /// Register `f` as last interceptor of this client and the clones made from it
+    /// afterwards, see [`ClientConfig::interceptor`].
+    pub fn with_interceptor<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut Request<String, serde_json::Value>) -> RPCResult<()> + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.interceptors).push(Arc::new(f));
+
+        self
+    }
+
+    /// Encode `request`, passed through the interceptors first.
+    fn encode_request<P>(&self, request: Request<&str, P>) -> RPCResult<RPCData>
     where
         P: Serialize,
     {
-        let receiver = self.completed_q.wait_one();
+        if self.interceptors.is_empty() {
+            return K::encode(&request);
+        }
 
-        let request = Request {
-            id: Some(receiver.event_id()),
-            method,
-            params,
-            jsonrpc: crate::Version::default(),
+        let id = request.id;
+
+        let mut request = Request {
+            id,
+            jsonrpc: request.jsonrpc,
+            method: request.method.to_owned(),
+            params: serde_json::to_value(request.params)?,
+            #[cfg(feature = "traceparent")]
+            traceparent: request.traceparent.map(ToOwned::to_owned),
+            #[cfg(feature = "extensions")]
+            extensions: request.extensions,
         };
 
-        let data = serde_json::to_vec(&request).expect("Inner error, assembly json request");
+        for interceptor in self.interceptors.iter() {
+            interceptor(&mut request)?;
+        }
 
-        self.output_sender
-            .send(data.into())
-            .await
-            .map_err(map_error)?;
+        request.id = id;
 
-        Ok(Responser {
-            receiver: Some(receiver),
-        })
+        K::encode(&request)
+    }
+
+    /// The current trace context to send with a request.
+    #[cfg(feature = "traceparent")]
+    fn traceparent(&self) -> Option<String> {
+        self.trace_context.as_ref().and_then(|context| context())
+    }
+
+    /// Close the connection of this client and all its clones.
+    ///
+    /// Pending calls fail with [`ErrorCode::ConnectionClosed`], subscriptions, call
+    /// streams and notification streams end, and the background tasks are aborted at once, dropping the transport
+    /// along with the frames not sent yet.
+    pub fn close(&mut self) {
+        self.output_sender.close_channel();
+
+        self.completed_q.cancel_all();
+
+        self.subscriptions.close_all();
+
+        self.streams.close_all();
+
+        self.listeners.close_all();
+
+        self.tasks
+            .lock()
+            .unwrap()
+            .drain(..)
+            .for_each(|task| task.abort());
+    }
+
+    pub async fn send<P>(&mut self, method: &str, params: P) -> RPCResult<Responser<Timeout>>
+    where
+        P: Serialize,
+    {
+        let receiver = self.completed_q.wait_one(self.timeout).await?;
+
+        self.send_request(method, params, receiver).await
     }
 
     pub async fn call<P, R>(&mut self, method: &str, params: P) -> RPCResult<R>
@@ -93,6 +330,20 @@ impl Client {
         self.send(method, params).await?.recv().await
     }
 
+    /// [`Client::call`] whose error data is deserialized as a `D`, see [`RPCError::typed`].
+    pub async fn call_with_error_data<P, R, D>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> RPCResult<R, D>
+    where
+        P: Serialize,
+        for<'b> R: Deserialize<'b> + Send + 'static,
+        D: DeserializeOwned,
+    {
+        self.call(method, params).await.map_err(RPCError::typed)
+    }
+
     pub async fn send_with_timer<P, T>(
         &mut self,
         method: &str,
@@ -103,25 +354,85 @@ impl Client {
         P: Serialize,
         T: Timer + Unpin + 'static,
     {
-        let receiver = self.completed_q.wait_one_with_timer(timer);
+        let receiver = self.completed_q.wait_one_with_timer(timer).await?;
+
+        self.send_request(method, params, receiver).await
+    }
+
+    async fn send_request<P, T>(
+        &mut self,
+        method: &str,
+        params: P,
+        receiver: EventReceiver<RPCEvent, T>,
+    ) -> RPCResult<Responser<T>>
+    where
+        P: Serialize,
+        T: Timer,
+    {
+        let (responser, item) = self.request_item(method, params, receiver)?;
+
+        self.backlog.try_reserve()?;
+
+        let sent = self
+            .output_sender
+            .send(item)
+            .instrument(responser.span.clone())
+            .await;
+
+        if sent.is_err() {
+            self.backlog.release();
+        }
+
+        sent?;
+
+        Ok(responser)
+    }
+
+    /// Encode the call request, waiting for its response through `receiver`.
+    fn request_item<P, T>(
+        &self,
+        method: &str,
+        params: P,
+        receiver: EventReceiver<RPCEvent, T>,
+    ) -> RPCResult<(Responser<T>, OutputItem)>
+    where
+        P: Serialize,
+        T: Timer,
+    {
+        // Created first, so the pending call is cleaned up on any error below.
+        let id = receiver.event_id();
 
+        let responser = Responser {
+            id,
+            receiver: Some(receiver),
+            completed_q: self.completed_q.clone(),
+            span: call_span(method, id),
+            sent: (self.metrics.is_some() || self.slow_call_threshold.is_some()).then(|| {
+                if let Some(metrics) = &self.metrics {
+                    metrics.on_request(method);
+                }
+
+                (method.to_owned(), Instant::now())
+            }),
+            metrics: self.metrics.clone(),
+            slow_call_threshold: self.slow_call_threshold,
+        };
+
+        let request = Request::call(responser.id, method, params);
+
+        #[cfg(feature = "traceparent")]
+        let traceparent = self.traceparent();
+        #[cfg(feature = "traceparent")]
         let request = Request {
-            id: Some(receiver.event_id()),
-            method,
-            params,
-            jsonrpc: crate::Version::default(),
+            traceparent: traceparent.as_deref(),
+            ..request
         };
 
-        let data = serde_json::to_vec(&request).expect("Inner error, assembly json request");
+        let data = self.encode_request(request)?;
 
-        self.output_sender
-            .send(data.into())
-            .await
-            .map_err(map_error)?;
+        let id = Some(responser.id);
 
-        Ok(Responser {
-            receiver: Some(receiver),
-        })
+        Ok((responser, (id, data)))
     }
 
     pub async fn call_with_timer<P, T, R>(
@@ -141,30 +452,92 @@ impl Client {
             .await
     }
 
+    /// Call `method`, failing with [`ErrorCode::Timeout`] if no response arrived before `deadline`.
+    ///
+    /// Sharing one deadline across several calls bounds their total latency.
+    pub async fn call_with_deadline<P, R>(
+        &mut self,
+        method: &str,
+        params: P,
+        deadline: Instant,
+    ) -> RPCResult<R>
+    where
+        P: Serialize,
+        for<'b> R: Deserialize<'b> + Send + 'static,
+    {
+        let now = Instant::now();
+
+        if deadline <= now {
+            return Err(RPCError::new(
+                ErrorCode::Timeout,
+                format!("Method {} deadline exceeded", method),
+                None,
+            ));
+        }
+
+        self.call_with_timer(method, params, timeout(deadline - now))
+            .await
+    }
+
     pub async fn notification<P>(&mut self, method: &str, params: P) -> RPCResult<()>
     where
         P: Serialize,
     {
+        let request = Request::notification(method, params);
+
+        #[cfg(feature = "traceparent")]
+        let traceparent = self.traceparent();
+        #[cfg(feature = "traceparent")]
         let request = Request {
-            method,
-            params,
-            id: None,
-            jsonrpc: crate::Version::default(),
+            traceparent: traceparent.as_deref(),
+            ..request
         };
 
-        let data = serde_json::to_vec(&request)?;
+        let data = self.encode_request(request)?;
 
-        self.output_sender
-            .send(data.into())
-            .await
-            .map_err(map_error)?;
+        self.backlog.try_reserve()?;
+
+        if let Err(err) = self.output_sender.send((None, data)).await {
+            self.backlog.release();
+
+            return Err(err.into());
+        }
 
         Ok(())
     }
+
+    /// Ask the server to cancel the call `id`, see [`Context::cancellation`](crate::Context::cancellation).
+    ///
+    /// Cancellation is cooperative: the call still completes with whatever its handler
+    /// returns once it observed the cancellation.
+    pub async fn cancel(&mut self, id: usize) -> RPCResult<()> {
+        self.notification(CANCEL_METHOD, [id]).await
+    }
 }
 
 pub struct Responser<T: Timer> {
+    id: usize,
     receiver: Option<EventReceiver<RPCEvent, T>>,
+    completed_q: RPCCompletedQ,
+    span: Span,
+    /// Method name and instant the call was sent, only taken with metrics or
+    /// a slow call threshold set.
+    sent: Option<(String, Instant)>,
+    metrics: Option<Arc<dyn Metrics>>,
+    slow_call_threshold: Option<Duration>,
+}
+
+impl<T: Timer> Responser<T> {
+    /// Request id of the call, e.g. for [`Client::cancel`].
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<T: Timer> Drop for Responser<T> {
+    fn drop(&mut self) {
+        self.completed_q.remove(self.id);
+    }
 }
 
 impl<T: Timer> Responser<T>
@@ -175,15 +548,67 @@ where
     where
         for<'b> R: Deserialize<'b> + Send + 'static,
     {
-        let value = self
-            .receiver
-            .take()
-            .unwrap()
-            .await
-            .success()
-            .map_err(map_error)?
-            .ok_or(CompleteQError::PipeBroken)??;
+        let span = self.span.clone();
+
+        let result = self.receive().instrument(span).await;
+
+        self.finish(result)
+    }
+
+    /// Returns the response if it already arrived, without waiting for it.
+    ///
+    /// `Ok(None)` means the call is still pending, it can be checked again later or
+    /// awaited by [`Responser::recv`]. Once the response was returned, further
+    /// receives fail.
+    pub fn try_recv<R>(&mut self) -> RPCResult<Option<R>>
+    where
+        for<'b> R: Deserialize<'b> + Send + 'static,
+    {
+        let span = self.span.clone();
+
+        match span.in_scope(|| self.receive().now_or_never()) {
+            Some(result) => self.finish(result).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Report the completed call and decode its result.
+    fn finish<R>(&self, result: RPCResult<serde_json::Value>) -> RPCResult<R>
+    where
+        for<'b> R: Deserialize<'b> + Send + 'static,
+    {
+        if let Some((method, sent)) = &self.sent {
+            let elapsed = sent.elapsed();
+
+            if let Some(metrics) = &self.metrics {
+                metrics.on_response(method, elapsed, result.is_err());
+            }
+
+            if self
+                .slow_call_threshold
+                .is_some_and(|threshold| elapsed > threshold)
+            {
+                log::warn!(
+                    "rpc call {} id {} slow, took {:?}",
+                    method,
+                    self.id,
+                    elapsed
+                );
+            }
+        }
+
+        serde_json::from_value(result?).map_err(map_error)
+    }
+
+    /// Wait for the response, the receiver is kept until it completed so
+    /// abandoning this future doesn't lose the response.
+    async fn receive(&mut self) -> RPCResult<serde_json::Value> {
+        let receiver = self.receiver.as_mut().ok_or(CompleteQError::PipeBroken)?;
+
+        let result = receiver.await;
+
+        self.receiver = None;
 
-        serde_json::from_value(value.clone()).map_err(map_error)
+        result.success()?.ok_or(CompleteQError::PipeBroken)?
     }
 }
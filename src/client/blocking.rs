@@ -0,0 +1,84 @@
+use futures::executor::LocalPool;
+use serde::{Deserialize, Serialize};
+
+use crate::{channel::TransportChannel, Codec, JsonCodec, RPCResult};
+
+use super::{Client, ClientConfig};
+
+/// [`Client`] for synchronous code, blocking the calling thread until a call completed.
+///
+/// Calls are driven by an executor owned by the client, while the transport's I/O tasks
+/// run wherever [`TransportChannel::spawn`] puts them, so any transport works. The
+/// blocking methods panic if called from within an async executor.
+pub struct BlockingClient<K: Codec = JsonCodec> {
+    client: Client<K>,
+    pool: LocalPool,
+}
+
+impl BlockingClient {
+    /// Create a client with the default configuration, see [`Client::new`].
+    pub fn new<C, S>(tag: S, channel: C) -> Self
+    where
+        C: TransportChannel,
+        S: AsRef<str>,
+    {
+        Client::new(tag, channel).into()
+    }
+
+    /// Create a client with custom [`ClientConfig`], see [`Client::with_config`].
+    pub fn with_config<C, S>(tag: S, channel: C, config: ClientConfig) -> Self
+    where
+        C: TransportChannel,
+        S: AsRef<str>,
+    {
+        Client::with_config(tag, channel, config).into()
+    }
+}
+
+impl<K: Codec> BlockingClient<K> {
+    /// Create a client exchanging frames encoded by `codec`, see [`Client::with_codec`].
+    pub fn with_codec<C, S>(tag: S, channel: C, codec: K, config: ClientConfig) -> Self
+    where
+        C: TransportChannel,
+        S: AsRef<str>,
+    {
+        Client::with_codec(tag, channel, codec, config).into()
+    }
+
+    /// Blocking [`Client::call`].
+    pub fn call_blocking<P, R>(&mut self, method: &str, params: P) -> RPCResult<R>
+    where
+        P: Serialize,
+        for<'b> R: Deserialize<'b> + Send + 'static,
+    {
+        self.pool.run_until(self.client.call(method, params))
+    }
+
+    /// Blocking [`Client::notification`].
+    pub fn notify_blocking<P>(&mut self, method: &str, params: P) -> RPCResult<()>
+    where
+        P: Serialize,
+    {
+        self.pool
+            .run_until(self.client.notification(method, params))
+    }
+
+    /// The wrapped async client.
+    pub fn client(&self) -> &Client<K> {
+        &self.client
+    }
+
+    /// Returns the wrapped async client.
+    pub fn into_inner(self) -> Client<K> {
+        self.client
+    }
+}
+
+impl<K: Codec> From<Client<K>> for BlockingClient<K> {
+    fn from(client: Client<K>) -> Self {
+        Self {
+            client,
+            pool: LocalPool::new(),
+        }
+    }
+}
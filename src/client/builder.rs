@@ -0,0 +1,171 @@
+use std::{marker::PhantomData, time::Duration};
+
+use crate::{channel::TransportChannel, Codec, Executor, JsonCodec, Metrics, RPCResult, Request};
+
+use super::{Client, ClientConfig, InflightOverflow};
+
+/// [`Client`] builder, see [`Client::builder`].
+///
+/// Accumulates the [`ClientConfig`] options and the codec of a client,
+/// which [`ClientBuilder::build`] starts on a transport.
+pub struct ClientBuilder<K: Codec = JsonCodec> {
+    tag: String,
+    config: ClientConfig,
+    codec: PhantomData<K>,
+}
+
+impl ClientBuilder {
+    /// Create a builder of a client tagged `tag` in logs, with the default configuration.
+    pub fn new<S: Into<String>>(tag: S) -> Self {
+        Self {
+            tag: tag.into(),
+            config: ClientConfig::default(),
+            codec: PhantomData,
+        }
+    }
+}
+
+impl<K: Codec> ClientBuilder<K> {
+    /// Exchange frames encoded by `codec`.
+    pub fn codec<C: Codec>(self, _codec: C) -> ClientBuilder<C> {
+        ClientBuilder {
+            tag: self.tag,
+            config: self.config,
+            codec: PhantomData,
+        }
+    }
+
+    /// Replace all options set so far by `config`.
+    pub fn config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+
+        self
+    }
+
+    /// See [`ClientConfig::strict`].
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.config = self.config.strict(strict);
+
+        self
+    }
+
+    /// See [`ClientConfig::lenient_version`].
+    pub fn lenient_version(mut self, lenient: bool) -> Self {
+        self.config = self.config.lenient_version(lenient);
+
+        self
+    }
+
+    /// See [`ClientConfig::on_orphan_response`].
+    pub fn on_orphan_response<F>(mut self, f: F) -> Self
+    where
+        F: Fn(usize, RPCResult<serde_json::Value>) + Send + Sync + 'static,
+    {
+        self.config = self.config.on_orphan_response(f);
+
+        self
+    }
+
+    /// See [`ClientConfig::keepalive`].
+    pub fn keepalive(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.config = self.config.keepalive(interval, timeout);
+
+        self
+    }
+
+    /// See [`ClientConfig::keepalive_method`].
+    pub fn keepalive_method<S: Into<String>>(mut self, method: S) -> Self {
+        self.config = self.config.keepalive_method(method);
+
+        self
+    }
+
+    /// See [`ClientConfig::max_inflight`].
+    pub fn max_inflight(mut self, max: usize) -> Self {
+        self.config = self.config.max_inflight(max);
+
+        self
+    }
+
+    /// See [`ClientConfig::max_request_id`].
+    pub fn max_request_id(mut self, max: usize) -> Self {
+        self.config = self.config.max_request_id(max);
+
+        self
+    }
+
+    /// See [`ClientConfig::inflight_overflow`].
+    pub fn inflight_overflow(mut self, overflow: InflightOverflow) -> Self {
+        self.config = self.config.inflight_overflow(overflow);
+
+        self
+    }
+
+    /// See [`ClientConfig::high_watermark`].
+    pub fn high_watermark(mut self, max: usize) -> Self {
+        self.config = self.config.high_watermark(max);
+
+        self
+    }
+
+    /// See [`ClientConfig::timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config = self.config.timeout(timeout);
+
+        self
+    }
+
+    /// See [`ClientConfig::capacity`].
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.config = self.config.capacity(capacity);
+
+        self
+    }
+
+    /// See [`ClientConfig::metrics`].
+    pub fn metrics<M: Metrics>(mut self, metrics: M) -> Self {
+        self.config = self.config.metrics(metrics);
+
+        self
+    }
+
+    /// See [`ClientConfig::slow_call_threshold`].
+    pub fn slow_call_threshold(mut self, threshold: Duration) -> Self {
+        self.config = self.config.slow_call_threshold(threshold);
+
+        self
+    }
+
+    /// See [`ClientConfig::executor`].
+    pub fn executor<E: Executor>(mut self, executor: E) -> Self {
+        self.config = self.config.executor(executor);
+
+        self
+    }
+
+    /// See [`ClientConfig::interceptor`].
+    pub fn interceptor<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut Request<String, serde_json::Value>) -> RPCResult<()> + Send + Sync + 'static,
+    {
+        self.config = self.config.interceptor(f);
+
+        self
+    }
+
+    /// See [`ClientConfig::trace_context`].
+    #[cfg(feature = "traceparent")]
+    pub fn trace_context<F>(mut self, f: F) -> Self
+    where
+        F: Fn() -> Option<String> + Send + Sync + 'static,
+    {
+        self.config = self.config.trace_context(f);
+
+        self
+    }
+
+    /// Start the client on `channel`.
+    pub fn build<C: TransportChannel>(self, channel: C) -> Client<K> {
+        Client::with_codec(self.tag, channel, K::default(), self.config)
+    }
+}
@@ -0,0 +1,244 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{Executor, Metrics, RPCResult, Request, PING_METHOD};
+
+/// Error code of [`ErrorCode::TooManyInflight`](crate::ErrorCode::TooManyInflight), failing
+/// a call beyond [`ClientConfig::max_inflight`] with [`InflightOverflow::Reject`].
+pub const TOO_MANY_INFLIGHT: i64 = -32004;
+
+/// Error code of [`ErrorCode::Backpressure`](crate::ErrorCode::Backpressure), failing a call
+/// while [`ClientConfig::high_watermark`] frames are queued for sending.
+pub const BACKPRESSURE: i64 = -32005;
+
+/// How a new call behaves once [`ClientConfig::max_inflight`] calls are pending.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InflightOverflow {
+    /// Wait until a pending call completes.
+    #[default]
+    Wait,
+    /// Fail at once with an [`ErrorCode::TooManyInflight`](crate::ErrorCode::TooManyInflight).
+    Reject,
+}
+
+/// Callback receiving responses no pending call is waiting for,
+/// see [`ClientConfig::on_orphan_response`].
+pub type OrphanResponseHandler = Arc<dyn Fn(usize, RPCResult<serde_json::Value>) + Send + Sync>;
+
+/// Hook inspecting or rewriting every outgoing request before it is encoded,
+/// see [`ClientConfig::interceptor`].
+pub type Interceptor =
+    Arc<dyn Fn(&mut Request<String, serde_json::Value>) -> RPCResult<()> + Send + Sync>;
+
+/// Source of the current trace context, see [`ClientConfig::trace_context`].
+#[cfg(feature = "traceparent")]
+pub type TraceContext = Arc<dyn Fn() -> Option<String> + Send + Sync>;
+
+/// [`Client`](crate::Client) configuration, see [`Client::with_config`](crate::Client::with_config).
+#[derive(Clone, Default)]
+pub struct ClientConfig {
+    pub(crate) strict: bool,
+    pub(crate) lenient_version: bool,
+    pub(crate) on_orphan_response: Option<OrphanResponseHandler>,
+    pub(crate) keepalive: Option<Keepalive>,
+    pub(crate) keepalive_method: Option<String>,
+    pub(crate) max_inflight: Option<usize>,
+    pub(crate) max_request_id: Option<usize>,
+    pub(crate) inflight_overflow: InflightOverflow,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) capacity: Option<usize>,
+    pub(crate) high_watermark: Option<usize>,
+    pub(crate) metrics: Option<Arc<dyn Metrics>>,
+    pub(crate) slow_call_threshold: Option<Duration>,
+    pub(crate) executor: Option<Arc<dyn Executor>>,
+    pub(crate) interceptors: Vec<Interceptor>,
+    #[cfg(feature = "traceparent")]
+    pub(crate) trace_context: Option<TraceContext>,
+}
+
+/// Keepalive settings, see [`ClientConfig::keepalive`].
+#[derive(Clone)]
+pub(crate) struct Keepalive {
+    pub(crate) interval: Duration,
+    pub(crate) timeout: Duration,
+}
+
+impl ClientConfig {
+    /// Abort the connection when an incoming frame can't be parsed as a response.
+    ///
+    /// By default such frames are logged and skipped, only failing the call
+    /// whose id can still be recovered from the frame. In strict mode all pending
+    /// calls are canceled and the receive loop stops.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+
+        self
+    }
+
+    /// Accept responses omitting the `jsonrpc` member as version 2.0.
+    ///
+    /// By default such responses are rejected as malformed.
+    pub fn lenient_version(mut self, lenient: bool) -> Self {
+        self.lenient_version = lenient;
+
+        self
+    }
+
+    /// Set the callback receiving responses whose id matches no pending call,
+    /// e.g. duplicated responses, responses for timed out calls or bogus ids.
+    ///
+    /// The callback is invoked from the client's receive loop and should not block.
+    pub fn on_orphan_response<F>(mut self, f: F) -> Self
+    where
+        F: Fn(usize, RPCResult<serde_json::Value>) + Send + Sync + 'static,
+    {
+        self.on_orphan_response = Some(Arc::new(f));
+
+        self
+    }
+
+    /// Ping the server with a [`PING_METHOD`] request every `interval`.
+    ///
+    /// If the ping isn't answered within `timeout` the connection is considered dead:
+    /// the client is closed and pending calls fail with
+    /// [`ErrorCode::ConnectionClosed`](crate::ErrorCode::ConnectionClosed).
+    /// Any response, even an error, proves the server alive,
+    /// see [`Server::handle_ping`](crate::Server::handle_ping).
+    pub fn keepalive(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.keepalive = Some(Keepalive { interval, timeout });
+
+        self
+    }
+
+    /// Ping with `method` instead of [`PING_METHOD`], see [`ClientConfig::keepalive`].
+    pub fn keepalive_method<S: Into<String>>(mut self, method: S) -> Self {
+        self.keepalive_method = Some(method.into());
+
+        self
+    }
+
+    /// Limit the number of calls waiting for their response to `max`.
+    ///
+    /// Once the limit is reached new calls wait until a pending call completes,
+    /// bounding memory growth when the server is slow. Unlimited by default,
+    /// a `max` of zero is treated as one.
+    pub fn max_inflight(mut self, max: usize) -> Self {
+        self.max_inflight = Some(max.max(1));
+
+        self
+    }
+
+    /// Wrap request ids around to 1 after `max`, e.g. for peers which can't represent
+    /// large integers. Defaults to `usize::MAX`, a `max` of zero is treated as one.
+    ///
+    /// Ids of calls still pending are skipped, so an id is never shared by two calls.
+    /// Once all `max` ids are pending new calls fail with an
+    /// [`ErrorCode::RequestIdsExhausted`](crate::ErrorCode::RequestIdsExhausted).
+    pub fn max_request_id(mut self, max: usize) -> Self {
+        self.max_request_id = Some(max.max(1));
+
+        self
+    }
+
+    /// Set how new calls beyond [`ClientConfig::max_inflight`] are handled,
+    /// by default they wait, see [`InflightOverflow`].
+    pub fn inflight_overflow(mut self, overflow: InflightOverflow) -> Self {
+        self.inflight_overflow = overflow;
+
+        self
+    }
+
+    /// Fail calls without a response after `timeout` with [`ErrorCode::Timeout`](crate::ErrorCode::Timeout).
+    ///
+    /// Applies to [`Client::call`](crate::Client::call), [`Client::send`](crate::Client::send)
+    /// and every other call without its own timer, [`Client::call_with_timer`](crate::Client::call_with_timer)
+    /// overrides it per call. Calls wait for their response forever by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+
+        self
+    }
+
+    /// Buffer up to `capacity` outgoing frames before sending a request waits
+    /// for the transport. Defaults to 100, a `capacity` of zero is treated as one.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity.max(1));
+
+        self
+    }
+
+    /// Fail new calls and notifications at once with an
+    /// [`ErrorCode::Backpressure`](crate::ErrorCode::Backpressure)
+    /// while `max` frames are queued for sending, see [`Client::queued_count`](crate::Client::queued_count).
+    ///
+    /// Frames queue up when the transport is slower than the callers. Without a high
+    /// watermark callers wait once [`ClientConfig::capacity`] frames are queued, so
+    /// `max` should be below the capacity. A `max` of zero is treated as one.
+    pub fn high_watermark(mut self, max: usize) -> Self {
+        self.high_watermark = Some(max.max(1));
+
+        self
+    }
+
+    /// Report calls and their latency to `metrics`.
+    pub fn metrics<M: Metrics>(mut self, metrics: M) -> Self {
+        self.metrics = Some(Arc::new(metrics));
+
+        self
+    }
+
+    /// Log a warning with the method name and elapsed time for every call whose
+    /// response took longer than `threshold` to receive. Faster calls aren't logged.
+    pub fn slow_call_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_call_threshold = Some(threshold);
+
+        self
+    }
+
+    /// Run the background tasks of the client on `executor` instead of spawning
+    /// them by [`TransportChannel::spawn`](crate::channel::TransportChannel::spawn).
+    pub fn executor<E: Executor>(mut self, executor: E) -> Self {
+        self.executor = Some(Arc::new(executor));
+
+        self
+    }
+
+    /// Pass every outgoing call and notification through `f` before it is encoded,
+    /// e.g. to add auth params, inject a trace id or log requests.
+    ///
+    /// Interceptors run in registration order. An error fails the call without sending
+    /// it. The id of a call is owned by the client, changes to it are discarded.
+    /// Requests are converted to a [`serde_json::Value`] params first, which costs an
+    /// extra copy only once an interceptor is registered.
+    pub fn interceptor<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut Request<String, serde_json::Value>) -> RPCResult<()> + Send + Sync + 'static,
+    {
+        self.interceptors.push(Arc::new(f));
+
+        self
+    }
+
+    /// Send the [W3C `traceparent`](https://www.w3.org/TR/trace-context/#traceparent-header)
+    /// returned by `f` with every request, see [`Context::traceparent`](crate::Context::traceparent).
+    ///
+    /// `f` is called for each request and returns `None` outside of a trace, in which
+    /// case no `traceparent` is sent. It's typically backed by the current span of the
+    /// tracing backend, e.g. injected by opentelemetry's `TraceContextPropagator`.
+    #[cfg(feature = "traceparent")]
+    pub fn trace_context<F>(mut self, f: F) -> Self
+    where
+        F: Fn() -> Option<String> + Send + Sync + 'static,
+    {
+        self.trace_context = Some(Arc::new(f));
+
+        self
+    }
+
+    pub(crate) fn output_capacity(&self) -> usize {
+        self.capacity.unwrap_or(100)
+    }
+
+    pub(crate) fn ping_method(&self) -> &str {
+        self.keepalive_method.as_deref().unwrap_or(PING_METHOD)
+    }
+}
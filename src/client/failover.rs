@@ -0,0 +1,158 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Codec, ErrorCode, JsonCodec, RPCError, RPCResult};
+
+use super::Client;
+
+struct Endpoint<K: Codec> {
+    client: Client<K>,
+    /// Set once the endpoint failed, skipped until then.
+    down_until: Mutex<Option<Instant>>,
+}
+
+impl<K: Codec> Endpoint<K> {
+    fn is_down(&self, now: Instant) -> bool {
+        self.down_until
+            .lock()
+            .unwrap()
+            .is_some_and(|down_until| down_until > now)
+    }
+
+    fn mark(&self, down_until: Option<Instant>) {
+        *self.down_until.lock().unwrap() = down_until;
+    }
+}
+
+/// Client over several endpoints in order of preference, failing over to the next
+/// endpoint when a call fails with [`ErrorCode::ConnectionClosed`] or [`ErrorCode::Timeout`].
+///
+/// Only those raised locally fail over, the errors of a responding endpoint are returned
+/// as is, even those with the same codes.
+///
+/// A failed endpoint is skipped for a cooldown period, unless every endpoint is down.
+/// A call which timed out may have been executed by the failed endpoint, so it may be
+/// executed twice.
+#[derive(Clone)]
+pub struct FailoverClient<K: Codec = JsonCodec> {
+    endpoints: Arc<Vec<Endpoint<K>>>,
+    cooldown: Duration,
+}
+
+impl<K: Codec> FailoverClient<K> {
+    /// Create a client over `clients`, the first one being the primary endpoint.
+    pub fn new<I>(clients: I) -> Self
+    where
+        I: IntoIterator<Item = Client<K>>,
+    {
+        Self {
+            endpoints: Arc::new(
+                clients
+                    .into_iter()
+                    .map(|client| Endpoint {
+                        client,
+                        down_until: Default::default(),
+                    })
+                    .collect(),
+            ),
+            cooldown: Duration::from_secs(30),
+        }
+    }
+
+    /// Set how long a failed endpoint is skipped. Defaults to 30s.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+
+        self
+    }
+
+    /// Returns true if the endpoint at `index` is skipped after a failure.
+    pub fn is_down(&self, index: usize) -> bool {
+        self.endpoints
+            .get(index)
+            .is_some_and(|endpoint| endpoint.is_down(Instant::now()))
+    }
+
+    /// Endpoints in the order calls try them, healthy endpoints first.
+    fn endpoints(&self) -> impl Iterator<Item = &Endpoint<K>> {
+        let now = Instant::now();
+
+        let (up, down): (Vec<_>, Vec<_>) = self
+            .endpoints
+            .iter()
+            .partition(|endpoint| !endpoint.is_down(now));
+
+        up.into_iter().chain(down)
+    }
+
+    /// Call `method` on the first healthy endpoint, see [`Client::call`].
+    pub async fn call<P, R>(&self, method: &str, params: P) -> RPCResult<R>
+    where
+        P: Serialize,
+        for<'b> R: Deserialize<'b> + Send + 'static,
+    {
+        let mut last_err = None;
+
+        for endpoint in self.endpoints() {
+            match endpoint.client.clone().call(method, &params).await {
+                Err(err) if is_failover(&err) => {
+                    self.failed(endpoint, &err);
+
+                    last_err = Some(err);
+                }
+                result => {
+                    endpoint.mark(None);
+
+                    return result;
+                }
+            }
+        }
+
+        Err(last_err.unwrap_or_else(no_endpoint))
+    }
+
+    /// Send notification to the first healthy endpoint, see [`Client::notification`].
+    pub async fn notification<P>(&self, method: &str, params: P) -> RPCResult<()>
+    where
+        P: Serialize,
+    {
+        let mut last_err = None;
+
+        for endpoint in self.endpoints() {
+            match endpoint.client.clone().notification(method, &params).await {
+                Err(err) if is_failover(&err) => {
+                    self.failed(endpoint, &err);
+
+                    last_err = Some(err);
+                }
+                result => return result,
+            }
+        }
+
+        Err(last_err.unwrap_or_else(no_endpoint))
+    }
+
+    fn failed(&self, endpoint: &Endpoint<K>, err: &RPCError) {
+        log::warn!("rpc endpoint failed, failing over, {}", err);
+
+        endpoint.mark(Some(Instant::now() + self.cooldown));
+    }
+}
+
+/// Returns true for errors of the transport, which are local as peer errors never
+/// decode to these codes.
+fn is_failover(err: &RPCError) -> bool {
+    matches!(err.code, ErrorCode::ConnectionClosed | ErrorCode::Timeout)
+}
+
+fn no_endpoint() -> RPCError {
+    RPCError::new(
+        ErrorCode::ConnectionClosed,
+        "No endpoint configured".to_owned(),
+        None,
+    )
+}
@@ -0,0 +1,44 @@
+use std::sync::Weak;
+
+use crate::{timer::timeout, Codec, ErrorCode, RPCResult};
+
+use super::{Client, Keepalive};
+
+/// Ping the server until the client is closed or dropped, closing it when a ping times out.
+pub async fn keepalive_loop<K: Codec, S: AsRef<str>>(
+    client_id: S,
+    mut client: Client<K>,
+    handle: Weak<()>,
+    keepalive: Keepalive,
+    method: String,
+) -> RPCResult<()> {
+    loop {
+        timeout(keepalive.interval).await;
+
+        if handle.strong_count() == 0 || client.is_closed() {
+            break;
+        }
+
+        let pong = client
+            .call_with_timer::<_, _, serde_json::Value>(&method, (), timeout(keepalive.timeout))
+            .await;
+
+        // Any response proves the server alive.
+        if let Err(err) = pong {
+            if err.code == ErrorCode::Timeout {
+                log::error!(
+                    "rpc client {} keepalive timed out, closing",
+                    client_id.as_ref()
+                );
+
+                client.close();
+
+                break;
+            }
+        }
+    }
+
+    log::info!("rpc client {} keepalive stop.", client_id.as_ref());
+
+    Ok(())
+}
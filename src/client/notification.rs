@@ -0,0 +1,55 @@
+use std::sync::{Arc, Mutex};
+
+use futures::{
+    channel::mpsc::{self, UnboundedSender},
+    Stream,
+};
+
+use crate::Codec;
+
+use super::Client;
+
+/// Method name and params of one notification.
+type Notification = (String, serde_json::Value);
+
+/// Streams of all incoming notifications, see [`Client::notifications`].
+#[derive(Clone, Default)]
+pub(crate) struct NotificationListeners(Arc<Mutex<Vec<UnboundedSender<Notification>>>>);
+
+impl NotificationListeners {
+    /// Pass a notification to every listener, dropping the listeners whose stream
+    /// was dropped, returns false if nobody listens.
+    pub(crate) fn notify(&self, method: &str, params: &serde_json::Value) -> bool {
+        let mut listeners = self.0.lock().unwrap();
+
+        listeners.retain(|sender| {
+            sender
+                .unbounded_send((method.to_owned(), params.clone()))
+                .is_ok()
+        });
+
+        !listeners.is_empty()
+    }
+
+    /// End all notification streams, once the connection closed.
+    pub(crate) fn close_all(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+impl<K: Codec> Client<K> {
+    /// Stream of the method name and params of every notification received from now on,
+    /// including subscription notifications and partial results of streaming calls.
+    ///
+    /// Every stream receives each notification, so it can be called repeatedly to
+    /// consume notifications in several places. Notifications are buffered until the
+    /// stream is polled, a stream which isn't polled should be dropped. The stream ends
+    /// once the connection closed.
+    pub fn notifications(&self) -> impl Stream<Item = (String, serde_json::Value)> + Send + Unpin {
+        let (sender, receiver) = mpsc::unbounded();
+
+        self.listeners.0.lock().unwrap().push(sender);
+
+        receiver
+    }
+}
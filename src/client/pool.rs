@@ -0,0 +1,236 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use futures::{
+    future::{BoxFuture, Shared},
+    lock::Mutex,
+    FutureExt,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{channel::TransportChannel, Codec, JsonCodec, RPCResult};
+
+use super::{Client, ClientConfig, Connector};
+
+/// Connection of a slot, awaited by every call handed out on the slot meanwhile.
+type Connecting<K> = Shared<BoxFuture<'static, RPCResult<Client<K>>>>;
+
+enum SlotState<K: Codec> {
+    Vacant,
+    Connecting(Connecting<K>),
+    Open(Client<K>),
+}
+
+struct Slot<K: Codec> {
+    state: SlotState<K>,
+    /// Calls handed out on this slot's client and not completed yet.
+    inflight: Arc<AtomicUsize>,
+}
+
+impl<K: Codec> Slot<K> {
+    fn is_vacant(&self) -> bool {
+        matches!(self.state, SlotState::Vacant)
+    }
+}
+
+/// Decrements the in-flight count of a slot once the call completed.
+struct InflightGuard(Arc<AtomicUsize>);
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Pool of up to `size` [`Client`]s over transports created on demand, for
+/// request/response transports where a connection per call is expensive.
+///
+/// Every call goes to the least-loaded client. A new connection is only opened while
+/// every open client has calls in flight, and clients whose transport closed are
+/// dropped and replaced on demand.
+pub struct ClientPool<C: TransportChannel, K: Codec = JsonCodec> {
+    tag: String,
+    config: ClientConfig,
+    connector: Connector<C>,
+    slots: Arc<Mutex<Vec<Slot<K>>>>,
+}
+
+impl<C: TransportChannel, K: Codec> Clone for ClientPool<C, K> {
+    fn clone(&self) -> Self {
+        Self {
+            tag: self.tag.clone(),
+            config: self.config.clone(),
+            connector: self.connector.clone(),
+            slots: self.slots.clone(),
+        }
+    }
+}
+
+impl<C: TransportChannel> ClientPool<C> {
+    /// Create a pool of up to `size` clients connecting through transports created by `connect`.
+    pub fn new<S, F, FR>(tag: S, size: usize, connect: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn() -> FR + Send + Sync + 'static,
+        FR: Future<Output = RPCResult<C>> + Send + 'static,
+    {
+        Self::with_codec(tag, size, JsonCodec, connect)
+    }
+}
+
+impl<C: TransportChannel, K: Codec> ClientPool<C, K> {
+    /// Create a pool exchanging frames encoded by `codec`, see [`ClientPool::new`].
+    pub fn with_codec<S, F, FR>(tag: S, size: usize, _codec: K, connect: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn() -> FR + Send + Sync + 'static,
+        FR: Future<Output = RPCResult<C>> + Send + 'static,
+    {
+        let slots = (0..size.max(1))
+            .map(|_| Slot {
+                state: SlotState::Vacant,
+                inflight: Default::default(),
+            })
+            .collect();
+
+        Self {
+            tag: tag.into(),
+            config: ClientConfig::default(),
+            connector: Arc::new(move || Box::pin(connect())),
+            slots: Arc::new(Mutex::new(slots)),
+        }
+    }
+
+    /// Configure the clients of every connection.
+    pub fn config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+
+        self
+    }
+
+    /// Number of open clients.
+    pub async fn len(&self) -> usize {
+        self.slots
+            .lock()
+            .await
+            .iter()
+            .filter(|slot| matches!(&slot.state, SlotState::Open(client) if !client.is_closed()))
+            .count()
+    }
+
+    /// Returns true if no client is open.
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// Returns the least-loaded client, counted as in flight until the guard is dropped.
+    ///
+    /// The slots are only locked to pick one, a new connection is opened without
+    /// holding the lock, so calls on open clients don't wait for it.
+    async fn client(&self) -> RPCResult<(Client<K>, InflightGuard)> {
+        let (index, connecting, guard) = {
+            let mut slots = self.slots.lock().await;
+
+            for slot in slots.iter_mut() {
+                if matches!(&slot.state, SlotState::Open(client) if client.is_closed()) {
+                    log::info!("rpc client pool {} recycle closed client", self.tag);
+
+                    slot.state = SlotState::Vacant;
+                }
+            }
+
+            let least_loaded = slots
+                .iter()
+                .enumerate()
+                .filter(|(_, slot)| !slot.is_vacant())
+                .min_by_key(|(_, slot)| slot.inflight.load(Ordering::SeqCst))
+                .map(|(index, slot)| (index, slot.inflight.load(Ordering::SeqCst)));
+
+            let vacant = slots.iter().position(Slot::is_vacant);
+
+            let index = match (least_loaded, vacant) {
+                (Some((index, 0)), _) | (Some((index, _)), None) => index,
+                (_, Some(index)) => {
+                    slots[index].state = SlotState::Connecting(self.connect());
+
+                    index
+                }
+                (None, None) => unreachable!("pool has at least one slot"),
+            };
+
+            let slot = &slots[index];
+
+            slot.inflight.fetch_add(1, Ordering::SeqCst);
+
+            let guard = InflightGuard(slot.inflight.clone());
+
+            match &slot.state {
+                SlotState::Open(client) => return Ok((client.clone(), guard)),
+                SlotState::Connecting(connecting) => (index, connecting.clone(), guard),
+                SlotState::Vacant => unreachable!("selected slot is vacant"),
+            }
+        };
+
+        let connected = connecting.clone().await;
+
+        // Whichever call sees the connection completed first installs its result.
+        let mut slots = self.slots.lock().await;
+
+        let slot = &mut slots[index];
+
+        if matches!(&slot.state, SlotState::Connecting(pending) if pending.ptr_eq(&connecting)) {
+            slot.state = match &connected {
+                Ok(client) => SlotState::Open(client.clone()),
+                Err(_) => SlotState::Vacant,
+            };
+        }
+
+        Ok((connected?, guard))
+    }
+
+    /// Open a new connection, a client over it once connected.
+    fn connect(&self) -> Connecting<K> {
+        let connect = (self.connector)();
+        let tag = self.tag.clone();
+        let config = self.config.clone();
+
+        async move {
+            let channel = connect.await?;
+
+            Ok(Client::with_codec(
+                tag.as_str(),
+                channel,
+                K::default(),
+                config,
+            ))
+        }
+        .boxed()
+        .shared()
+    }
+
+    /// Call `method` on the least-loaded client, see [`Client::call`].
+    pub async fn call<P, R>(&self, method: &str, params: P) -> RPCResult<R>
+    where
+        P: Serialize,
+        for<'b> R: Deserialize<'b> + Send + 'static,
+    {
+        let (mut client, _guard) = self.client().await?;
+
+        client.call(method, params).await
+    }
+
+    /// Send notification on the least-loaded client, see [`Client::notification`].
+    pub async fn notification<P>(&self, method: &str, params: P) -> RPCResult<()>
+    where
+        P: Serialize,
+    {
+        let (mut client, _guard) = self.client().await?;
+
+        client.notification(method, params).await
+    }
+}
@@ -0,0 +1,172 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use futures::{future::BoxFuture, lock::Mutex};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    channel::TransportChannel, timer::timeout, Codec, ErrorCode, JsonCodec, RPCError, RPCResult,
+};
+
+use super::{Client, ClientConfig};
+
+/// Transport factory of [`ReconnectingClient`].
+pub type Connector<C> = Arc<dyn Fn() -> BoxFuture<'static, RPCResult<C>> + Send + Sync>;
+
+/// [`Client`] which reconnects once its transport closed.
+///
+/// The transport is rebuilt on the first call after the connection closed, retrying
+/// failed connection attempts with exponential backoff. Calls in flight when the
+/// connection closed fail with [`ErrorCode::ConnectionClosed`].
+pub struct ReconnectingClient<C: TransportChannel, K: Codec = JsonCodec> {
+    tag: String,
+    config: ClientConfig,
+    connector: Connector<C>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_attempts: Option<usize>,
+    client: Arc<Mutex<Option<Client<K>>>>,
+}
+
+impl<C: TransportChannel, K: Codec> Clone for ReconnectingClient<C, K> {
+    fn clone(&self) -> Self {
+        Self {
+            tag: self.tag.clone(),
+            config: self.config.clone(),
+            connector: self.connector.clone(),
+            initial_backoff: self.initial_backoff,
+            max_backoff: self.max_backoff,
+            max_attempts: self.max_attempts,
+            client: self.client.clone(),
+        }
+    }
+}
+
+impl<C: TransportChannel> ReconnectingClient<C> {
+    /// Create a client connecting through transports created by `connect`.
+    pub fn new<S, F, FR>(tag: S, connect: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn() -> FR + Send + Sync + 'static,
+        FR: Future<Output = RPCResult<C>> + Send + 'static,
+    {
+        Self::with_codec(tag, JsonCodec, connect)
+    }
+}
+
+impl<C: TransportChannel, K: Codec> ReconnectingClient<C, K> {
+    /// Create a client exchanging frames encoded by `codec`, see [`ReconnectingClient::new`].
+    pub fn with_codec<S, F, FR>(tag: S, _codec: K, connect: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn() -> FR + Send + Sync + 'static,
+        FR: Future<Output = RPCResult<C>> + Send + 'static,
+    {
+        Self {
+            tag: tag.into(),
+            config: ClientConfig::default(),
+            connector: Arc::new(move || Box::pin(connect())),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: None,
+            client: Default::default(),
+        }
+    }
+
+    /// Configure the clients of every connection.
+    pub fn config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+
+        self
+    }
+
+    /// Set the delay after the first failed connection attempt, doubled after each
+    /// further failure up to `max`. Defaults to 100ms up to 30s.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+
+        self
+    }
+
+    /// Give up reconnecting after `attempts` failed connection attempts in a row,
+    /// failing the call with [`ErrorCode::ConnectionClosed`]. Unlimited by default.
+    pub fn max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = Some(attempts);
+
+        self
+    }
+
+    /// Returns the client of the current connection, connecting first if the
+    /// previous connection closed.
+    pub async fn client(&self) -> RPCResult<Client<K>> {
+        let mut client = self.client.lock().await;
+
+        if let Some(client) = client.as_ref().filter(|client| !client.is_closed()) {
+            return Ok(client.clone());
+        }
+
+        let connected = self.connect().await?;
+
+        *client = Some(connected.clone());
+
+        Ok(connected)
+    }
+
+    async fn connect(&self) -> RPCResult<Client<K>> {
+        let mut backoff = self.initial_backoff;
+        let mut attempts = 0;
+
+        loop {
+            let err = match (self.connector)().await {
+                Ok(channel) => {
+                    return Ok(Client::with_codec(
+                        self.tag.as_str(),
+                        channel,
+                        K::default(),
+                        self.config.clone(),
+                    ))
+                }
+                Err(err) => err,
+            };
+
+            attempts += 1;
+
+            log::warn!(
+                "rpc client {} connect attempt {} failed, {}",
+                self.tag,
+                attempts,
+                err
+            );
+
+            if self.max_attempts.is_some_and(|max| attempts >= max) {
+                return Err(RPCError::new(
+                    ErrorCode::ConnectionClosed,
+                    format!("Reconnect failed after {} attempts: {}", attempts, err),
+                    None,
+                )
+                .with_source(err));
+            }
+
+            timeout(backoff).await;
+
+            backoff = (backoff * 2).min(self.max_backoff);
+        }
+    }
+
+    /// Call `method` on the current connection, see [`Client::call`].
+    pub async fn call<P, R>(&self, method: &str, params: P) -> RPCResult<R>
+    where
+        P: Serialize,
+        for<'b> R: Deserialize<'b> + Send + 'static,
+    {
+        self.client().await?.call(method, params).await
+    }
+
+    /// Send notification on the current connection, see [`Client::notification`].
+    pub async fn notification<P>(&self, method: &str, params: P) -> RPCResult<()>
+    where
+        P: Serialize,
+    {
+        self.client().await?.notification(method, params).await
+    }
+}
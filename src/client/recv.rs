@@ -1,57 +1,214 @@
-use futures::TryStreamExt;
+use std::sync::Arc;
 
-use crate::{channel::TransportChannel, map_error, RPCResult, Response};
+use futures::{channel::mpsc::Sender, TryStreamExt};
 
-use super::user_event::RPCCompletedQ;
+use serde::Deserialize;
 
-pub async fn recv_loop<C: TransportChannel, S: AsRef<str>>(
+use crate::{
+    channel::TransportChannel, Codec, ErrorCode, LenientResponse, RPCError, RPCResult, Request,
+    Response, StreamParams, SubscriptionParams,
+};
+
+use super::{
+    notification::NotificationListeners, send::OutputItem, stream::CallStreams,
+    subscription::Subscriptions, user_event::RPCCompletedQ, ClientConfig,
+};
+
+pub async fn recv_loop<C: TransportChannel, K: Codec, S: AsRef<str>>(
     client_id: S,
-    mut input: C::Input,
+    input: C::Input,
     completed_q: RPCCompletedQ,
+    notifications: Notifications,
+    config: ClientConfig,
+    mut output_sender: Sender<OutputItem>,
+) -> RPCResult<()> {
+    let result = recv_responses::<C, K>(input, &completed_q, &notifications, &config).await;
+
+    // Reject new calls before failing the pending ones, so callers seeing the
+    // failure also see the client closed.
+    output_sender.close_channel();
+
+    match &result {
+        Ok(()) => completed_q.cancel_all(),
+        Err(err) => completed_q.fail_all(connection_failed(err)),
+    }
+
+    notifications.close_all();
+
+    log::info!("rpc client {} recv_loop stop.", client_id.as_ref());
+
+    result
+}
+
+async fn recv_responses<C: TransportChannel, K: Codec>(
+    mut input: C::Input,
+    completed_q: &RPCCompletedQ,
+    notifications: &Notifications,
+    config: &ClientConfig,
 ) -> RPCResult<()> {
     loop {
         let data = match input.try_next().await {
             Ok(Some(data)) => data,
+            Ok(None) => break,
             Err(err) => {
                 log::error!("Error raise from input stream {}", err);
-                completed_q.cancel_all();
-                break;
-            }
-            _ => {
-                break;
+
+                return Err(RPCError::from_transport(err));
             }
         };
 
-        let response =
-            serde_json::from_slice::<Response<String, serde_json::Value, serde_json::Value>>(&data)
-                .map_err(map_error);
-
-        match response {
-            Ok(response) => {
-                log::trace!("parsed response: {:?}", response);
-                if let Some(result) = response.result {
-                    log::trace!("response {} with result: {}", response.id, result);
-                    completed_q.complete_one(response.id, Ok(result));
-                } else if let Some(err) = response.error {
-                    log::trace!("response {} with error: {}", response.id, err);
-                    completed_q.complete_one(response.id, Err(err));
-                } else {
-                    completed_q.complete_one(response.id, Ok(serde_json::Value::Null));
-                    log::trace!("response {} with null result", response.id);
+        match decode_responses::<K>(&data, config.lenient_version) {
+            Ok(responses) => {
+                for response in responses {
+                    log::trace!("parsed response: {:?}", response);
+
+                    let argument = if let Some(result) = response.result {
+                        log::trace!("response {} with result: {}", response.id, result);
+                        Ok(result)
+                    } else if let Some(err) = response.error {
+                        log::trace!("response {} with error: {}", response.id, err);
+                        Err(err)
+                    } else {
+                        log::error!("response {} without result or error", response.id);
+                        Err(RPCError::new(
+                            ErrorCode::InvalidRequest,
+                            "Response contains neither result nor error".to_owned(),
+                            None,
+                        ))
+                    };
+
+                    notifications.subscriptions.activate(response.id, &argument);
+
+                    complete_one(completed_q, config, response.id, argument);
                 }
             }
+            Err(_) if notifications.route::<K>(&data) => {}
             Err(err) => {
                 log::error!("parse response error,{}", err);
                 log::error!("response {}", String::from_utf8_lossy(&data));
-                completed_q.cancel_all();
-                return Err(err);
+
+                if config.strict {
+                    return Err(err);
+                }
+
+                // Fail only the call this frame belongs to, if its id is still readable.
+                if let Some(id) = recover_id::<K>(&data) {
+                    complete_one(completed_q, config, id, Err(err));
+                }
             }
         }
     }
 
-    completed_q.cancel_all();
+    Ok(())
+}
 
-    log::info!("rpc client {} recv_loop stop.", client_id.as_ref());
+/// Error of the calls pending once the connection failed, as opposed to closed by the peer.
+fn connection_failed(err: &RPCError) -> RPCError {
+    RPCError::new(
+        ErrorCode::ConnectionClosed,
+        format!("Connection failed: {}", err.message),
+        None,
+    )
+    .with_shared_source(
+        err.source()
+            .cloned()
+            .unwrap_or_else(|| Arc::new(err.clone())),
+    )
+}
 
-    Ok(())
+type ClientResponse = Response<String, serde_json::Value, serde_json::Value>;
+
+/// Decode one response frame, or a batch frame holding an array of responses.
+fn decode_responses<K: Codec>(data: &[u8], lenient: bool) -> RPCResult<Vec<ClientResponse>> {
+    let response = if lenient {
+        K::decode::<LenientResponse<String, serde_json::Value, serde_json::Value>>(data)
+            .map(Response::from)
+    } else {
+        K::decode::<ClientResponse>(data)
+    };
+
+    let err = match response {
+        Ok(response) => return Ok(vec![response]),
+        Err(err) => err,
+    };
+
+    let batch = if lenient {
+        K::decode::<Vec<LenientResponse<String, serde_json::Value, serde_json::Value>>>(data)
+            .map(|batch| batch.into_iter().map(Response::from).collect())
+    } else {
+        K::decode::<Vec<ClientResponse>>(data)
+    };
+
+    // Report the error of the single response for frames which aren't a batch either.
+    batch.map_err(|_| err)
+}
+
+/// Receivers of server notifications.
+pub(crate) struct Notifications {
+    pub(crate) subscriptions: Subscriptions,
+    pub(crate) streams: CallStreams,
+    pub(crate) listeners: NotificationListeners,
+}
+
+impl Notifications {
+    /// Pass a notification to the notification streams, and a subscription notification
+    /// or partial result of a streaming call to its receiver, returns false if the frame
+    /// isn't a notification.
+    fn route<K: Codec>(&self, data: &[u8]) -> bool {
+        let Ok(notification) = K::decode::<Request<String, Option<serde_json::Value>>>(data) else {
+            return false;
+        };
+
+        if notification.id.is_some() {
+            return false;
+        }
+
+        let method = notification.method;
+        let params = notification.params.unwrap_or_default();
+
+        let listened = self.listeners.notify(&method, &params);
+
+        if let Ok(params) = SubscriptionParams::deserialize(&params) {
+            if !self.subscriptions.notify(&method, params) {
+                log::warn!("notification {} matches no subscription", method);
+            }
+        } else if let Ok(params) = StreamParams::deserialize(&params) {
+            if !self.streams.notify(&method, params) {
+                log::warn!("partial result of {} matches no streaming call", method);
+            }
+        } else if !listened {
+            log::warn!("notification {} has no listener", method);
+        }
+
+        true
+    }
+
+    fn close_all(&self) {
+        self.subscriptions.close_all();
+        self.streams.close_all();
+        self.listeners.close_all();
+    }
+}
+
+/// Complete the call waiting for `id`, passing orphan responses to the configured hook.
+fn complete_one(
+    completed_q: &RPCCompletedQ,
+    config: &ClientConfig,
+    id: usize,
+    argument: RPCResult<serde_json::Value>,
+) {
+    if let Err(argument) = completed_q.complete_one(id, argument) {
+        log::warn!("response {} matches no pending call", id);
+
+        if let Some(on_orphan_response) = &config.on_orphan_response {
+            on_orphan_response(id, argument);
+        }
+    }
+}
+
+/// Try to read the `id` member of an otherwise malformed response frame.
+fn recover_id<K: Codec>(data: &[u8]) -> Option<usize> {
+    let value = K::decode::<serde_json::Value>(data).ok()?;
+
+    value.get("id")?.as_u64().map(|id| id as usize)
 }
@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{timer::timeout, Codec, ErrorCode, RPCResult};
+
+use super::Client;
+
+/// Policy of [`Client::call_with_retry`], deciding which failed calls are re-issued
+/// and how long to wait in between.
+///
+/// Retrying is only safe for idempotent methods: a call which timed out may well have
+/// been executed by the server, so a retry may execute it again. Choosing which methods
+/// to retry is up to the caller.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    retryable: Vec<ErrorCode>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            retryable: vec![ErrorCode::Timeout],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Give up after `attempts` attempts in total, including the first one. Defaults to 3.
+    pub fn max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = attempts.max(1);
+
+        self
+    }
+
+    /// Set the delay after the first failed attempt, doubled after each further
+    /// failure up to `max`. Defaults to 100ms up to 5s.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+
+        self
+    }
+
+    /// Also retry calls failing with `code`. Only [`ErrorCode::Timeout`] is retried by
+    /// default, which is raised locally: a server error with the same code, e.g. a
+    /// [`HANDLER_TIMEOUT`](crate::HANDLER_TIMEOUT), isn't retried unless added as an
+    /// [`ErrorCode::ServerError`]. Those match regardless of their message.
+    pub fn retry_on(mut self, code: ErrorCode) -> Self {
+        if !self.retryable.contains(&code) {
+            self.retryable.push(code);
+        }
+
+        self
+    }
+
+    /// Returns true if a call failing with `code` is retried.
+    pub fn is_retryable(&self, code: &ErrorCode) -> bool {
+        self.retryable.contains(code)
+    }
+}
+
+impl<K: Codec> Client<K> {
+    /// [`Client::call`] re-issued with a fresh id while it fails with an error the
+    /// `policy` considers retryable, returning the last error once out of attempts.
+    ///
+    /// The request may be executed more than once, so only use it for idempotent
+    /// methods. Notifications are never retried, there's no way to tell if they failed.
+    pub async fn call_with_retry<P, R>(
+        &mut self,
+        method: &str,
+        params: P,
+        policy: &RetryPolicy,
+    ) -> RPCResult<R>
+    where
+        P: Serialize,
+        for<'b> R: Deserialize<'b> + Send + 'static,
+    {
+        let mut backoff = policy.initial_backoff;
+        let mut attempts = 0;
+
+        loop {
+            let err = match self.call(method, &params).await {
+                Ok(result) => return Ok(result),
+                Err(err) => err,
+            };
+
+            attempts += 1;
+
+            if attempts >= policy.max_attempts || !policy.is_retryable(&err.code) {
+                return Err(err);
+            }
+
+            log::warn!(
+                "rpc call {} attempt {} failed, retrying, {}",
+                method,
+                attempts,
+                err
+            );
+
+            timeout(backoff).await;
+
+            backoff = (backoff * 2).min(policy.max_backoff);
+        }
+    }
+}
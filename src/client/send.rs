@@ -1,31 +1,101 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
 use futures::{channel::mpsc::Receiver, SinkExt, StreamExt};
 
 use crate::{
     channel::{RPCData, TransportChannel},
-    map_error, RPCResult, Request,
+    ErrorCode, Metrics, RPCError, RPCResult,
 };
 
 use super::user_event::RPCCompletedQ;
 
+/// Encoded request queued for sending, with its id unless it's a notification.
+pub type OutputItem = (Option<usize>, RPCData);
+
+/// Count of the frames queued but not yet taken by [`send_loop`],
+/// see [`ClientConfig::high_watermark`](super::ClientConfig::high_watermark).
+#[derive(Clone)]
+pub(crate) struct Backlog {
+    queued: Arc<AtomicUsize>,
+    high_watermark: Option<usize>,
+}
+
+impl Backlog {
+    pub(crate) fn new(high_watermark: Option<usize>) -> Self {
+        Self {
+            queued: Default::default(),
+            high_watermark,
+        }
+    }
+
+    /// Number of queued frames.
+    pub(crate) fn len(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// Count a frame about to be queued, failing with an [`ErrorCode::Backpressure`] once
+    /// the high watermark is reached.
+    pub(crate) fn try_reserve(&self) -> RPCResult<()> {
+        let reserved = self
+            .queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                match self.high_watermark {
+                    Some(max) if queued >= max => None,
+                    _ => Some(queued + 1),
+                }
+            });
+
+        reserved.map(|_| ()).map_err(|queued| {
+            RPCError::new(
+                ErrorCode::Backpressure,
+                format!("Backpressure, {} frames queued for sending", queued),
+                None,
+            )
+        })
+    }
+
+    /// Count a frame about to be queued regardless of the high watermark.
+    pub(crate) fn reserve(&self) {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Uncount a frame taken by [`send_loop`], or which failed to be queued.
+    pub(crate) fn release(&self) {
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 pub async fn send_loop<C: TransportChannel, S: AsRef<str>>(
     client_id: S,
     mut output: C::Output,
-    mut output_receiver: Receiver<RPCData>,
+    mut output_receiver: Receiver<OutputItem>,
     completed_q: RPCCompletedQ,
+    backlog: Backlog,
+    metrics: Option<Arc<dyn Metrics>>,
 ) -> RPCResult<()> {
-    while let Some(item) = output_receiver.next().await {
-        match output.send(item.clone()).await {
-            Err(err) => {
-                let request: Request<String, serde_json::Value> =
-                    serde_json::from_slice(&item).expect("Parse send json error");
+    while let Some((id, item)) = output_receiver.next().await {
+        backlog.release();
 
-                log::error!("RPC client send msg error, {}", err);
+        let started = metrics.as_ref().map(|_| Instant::now());
 
-                if let Some(id) = request.id {
-                    completed_q.complete_one(id, Err(map_error(err)));
-                }
+        let sent = output.send(item).await;
+
+        if let (Some(metrics), Some(started)) = (&metrics, started) {
+            metrics.on_sink_wait(started.elapsed());
+        }
+
+        if let Err(err) = sent {
+            log::error!("RPC client send msg error, {}", err);
+
+            if let Some(id) = id {
+                _ = completed_q.complete_one(id, Err(RPCError::from_transport(err)));
             }
-            _ => {}
         }
     }
 
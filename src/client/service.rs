@@ -0,0 +1,44 @@
+use std::task::{Context, Poll};
+
+use futures::future::BoxFuture;
+use tower_service::Service;
+
+use crate::{Codec, RPCError, RPCResult};
+
+use super::Client;
+
+/// Call `method` with `params`, ready once the outbound channel has capacity and
+/// the [`ClientConfig::max_inflight`](crate::ClientConfig::max_inflight) limit isn't reached.
+impl<K: Codec> Service<(String, serde_json::Value)> for Client<K> {
+    type Response = serde_json::Value;
+
+    type Error = RPCError;
+
+    type Future = BoxFuture<'static, RPCResult<serde_json::Value>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<RPCResult<()>> {
+        futures::ready!(self.completed_q.poll_ready(cx));
+
+        self.output_sender.poll_ready(cx).map_err(RPCError::from)
+    }
+
+    fn call(&mut self, (method, params): (String, serde_json::Value)) -> Self::Future {
+        let sent = self
+            .completed_q
+            .try_wait_one(self.timeout)
+            .and_then(|receiver| self.request_item(&method, params, receiver))
+            .and_then(|(responser, item)| {
+                self.backlog.try_reserve()?;
+
+                if let Err(err) = self.output_sender.start_send(item) {
+                    self.backlog.release();
+
+                    return Err(err.into());
+                }
+
+                Ok(responser)
+            });
+
+        Box::pin(async move { sent?.recv().await })
+    }
+}
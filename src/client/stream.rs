@@ -0,0 +1,115 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    future::{select, Either},
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{map_error, Codec, RPCResult, StreamParams};
+
+use super::Client;
+
+/// Partial result receivers of streaming calls, keyed by method and request id.
+type Receivers = HashMap<(String, usize), UnboundedSender<serde_json::Value>>;
+
+/// Routes the partial results of streaming calls to their streams, by method and request id.
+#[derive(Clone, Default)]
+pub(crate) struct CallStreams(Arc<Mutex<Receivers>>);
+
+impl CallStreams {
+    fn register(&self, method: &str, id: usize) -> UnboundedReceiver<serde_json::Value> {
+        let (sender, receiver) = mpsc::unbounded();
+
+        self.0
+            .lock()
+            .unwrap()
+            .insert((method.to_owned(), id), sender);
+
+        receiver
+    }
+
+    /// Pass a partial result of `method` to its call, returns false if no streaming
+    /// call is waiting for it.
+    pub(crate) fn notify(&self, method: &str, params: StreamParams<serde_json::Value>) -> bool {
+        match self.0.lock().unwrap().get(&(method.to_owned(), params.id)) {
+            Some(sender) => sender.unbounded_send(params.result).is_ok(),
+            None => false,
+        }
+    }
+
+    /// End all partial result streams, once the connection closed.
+    pub(crate) fn close_all(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// Unregisters a streaming call once its stream ended or was dropped.
+struct Registration {
+    streams: CallStreams,
+    method: String,
+    id: usize,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.streams
+            .0
+            .lock()
+            .unwrap()
+            .remove(&(std::mem::take(&mut self.method), self.id));
+    }
+}
+
+impl<K: Codec> Client<K> {
+    /// Call the streaming `method`, returning the stream of its partial results
+    /// followed by its final result, see [`Server::stream_handle`](crate::Server::stream_handle).
+    ///
+    /// Partial results are received as `method` notifications carrying the request id.
+    /// The stream ends after the final result, or after the first error.
+    pub async fn call_stream<P, R>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> RPCResult<BoxStream<'static, RPCResult<R>>>
+    where
+        P: Serialize,
+        for<'b> R: Deserialize<'b> + Send + 'static,
+    {
+        let receiver = self.completed_q.wait_one(self.timeout).await?;
+
+        let registration = Registration {
+            streams: self.streams.clone(),
+            method: method.to_owned(),
+            id: receiver.event_id(),
+        };
+
+        let partials = self.streams.register(method, registration.id);
+
+        let responser = self.send_request(method, params, receiver).await?;
+
+        let state = Some((partials, responser, registration));
+
+        Ok(stream::unfold(state, |state| async move {
+            let (mut partials, mut responser, registration) = state?;
+
+            // Partial results arrive before the response, so they are taken first.
+            let partial = match select(partials.next(), Box::pin(responser.recv())).await {
+                Either::Left((Some(partial), _)) => partial,
+                Either::Left((None, final_result)) => return Some((final_result.await, None)),
+                Either::Right((final_result, _)) => return Some((final_result, None)),
+            };
+
+            match serde_json::from_value(partial).map_err(map_error) {
+                Ok(partial) => Some((Ok(partial), Some((partials, responser, registration)))),
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+        .boxed())
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    Stream, StreamExt,
+};
+use serde::Deserialize;
+
+use crate::{map_error, Codec, JsonCodec, RPCResult, Request, SubscriptionParams};
+
+use super::Client;
+
+#[derive(Default)]
+struct Inner {
+    /// Subscribe calls waiting for their response, keyed by call id, along with
+    /// the notification method of their subscription.
+    pending: HashMap<usize, (String, UnboundedSender<serde_json::Value>)>,
+    /// Active subscriptions, keyed by notification method and subscription id.
+    active: HashMap<(String, u64), UnboundedSender<serde_json::Value>>,
+    /// Notifications received while a subscribe call of their method is pending.
+    early: Vec<(String, SubscriptionParams<serde_json::Value>)>,
+}
+
+impl Inner {
+    /// Drop the early notifications no pending subscribe call may claim anymore.
+    fn retain_early(&mut self) {
+        let pending = &self.pending;
+
+        self.early.retain(|(method, _)| {
+            pending
+                .values()
+                .any(|(notification, _)| notification == method)
+        });
+    }
+}
+
+/// Routes incoming subscription notifications to their [`Subscription`] streams.
+///
+/// Subscriptions are activated by the receive loop while it handles the subscribe
+/// response, so notifications following the response right away aren't lost. Those
+/// the server pushes before its response are held back until the subscription is activated.
+#[derive(Clone, Default)]
+pub(crate) struct Subscriptions(Arc<Mutex<Inner>>);
+
+impl Subscriptions {
+    /// Register the subscribe call `id`, receiving the `notification` notifications of its subscription.
+    fn register(&self, id: usize, notification: &str) -> UnboundedReceiver<serde_json::Value> {
+        let (sender, receiver) = mpsc::unbounded();
+
+        self.0
+            .lock()
+            .unwrap()
+            .pending
+            .insert(id, (notification.to_owned(), sender));
+
+        receiver
+    }
+
+    /// Activate the subscription returned by the subscribe call `id`, if it is one.
+    pub(crate) fn activate(&self, id: usize, result: &RPCResult<serde_json::Value>) {
+        let mut inner = self.0.lock().unwrap();
+
+        let Some((notification, sender)) = inner.pending.remove(&id) else {
+            return;
+        };
+
+        if let Some(subscription) = result.as_ref().ok().and_then(|result| result.as_u64()) {
+            inner.early.retain(|(method, params)| {
+                if *method != notification || params.subscription != subscription {
+                    return true;
+                }
+
+                _ = sender.unbounded_send(params.result.clone());
+
+                false
+            });
+
+            inner.active.insert((notification, subscription), sender);
+        }
+
+        inner.retain_early();
+    }
+
+    /// Forget a subscribe call whose response was lost.
+    fn cancel(&self, id: usize) {
+        let mut inner = self.0.lock().unwrap();
+
+        inner.pending.remove(&id);
+        inner.retain_early();
+    }
+
+    fn remove(&self, notification: &str, subscription: u64) {
+        self.0
+            .lock()
+            .unwrap()
+            .active
+            .remove(&(notification.to_owned(), subscription));
+    }
+
+    /// Pass a `method` notification to its subscription, or hold it back while a subscribe
+    /// call of `method` is pending, returns false if it isn't subscribed.
+    pub(crate) fn notify(
+        &self,
+        method: &str,
+        params: SubscriptionParams<serde_json::Value>,
+    ) -> bool {
+        let mut inner = self.0.lock().unwrap();
+
+        if let Some(sender) = inner.active.get(&(method.to_owned(), params.subscription)) {
+            return sender.unbounded_send(params.result).is_ok();
+        }
+
+        if !inner
+            .pending
+            .values()
+            .any(|(notification, _)| notification == method)
+        {
+            return false;
+        }
+
+        inner.early.push((method.to_owned(), params));
+
+        true
+    }
+
+    /// End all subscription streams, once the connection closed.
+    pub(crate) fn close_all(&self) {
+        let mut inner = self.0.lock().unwrap();
+
+        inner.pending.clear();
+        inner.active.clear();
+        inner.early.clear();
+    }
+}
+
+/// Stream of the values pushed to one subscription, see [`Client::subscribe`].
+///
+/// The stream ends once the connection closed. Dropping it unsubscribes with
+/// a notification, use [`Subscription::unsubscribe`] to wait for the server instead.
+pub struct Subscription<T, K: Codec = JsonCodec> {
+    id: u64,
+    notification: String,
+    notifications: UnboundedReceiver<serde_json::Value>,
+    client: Client<K>,
+    unsubscribe: Option<String>,
+    result: PhantomData<fn() -> T>,
+}
+
+// No field is structurally pinned.
+impl<T, K: Codec> Unpin for Subscription<T, K> {}
+
+impl<T, K: Codec> Subscription<T, K> {
+    /// Subscription id, returned by the server.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Unsubscribe, returns the server's answer whether the subscription was active.
+    pub async fn unsubscribe(mut self) -> RPCResult<bool> {
+        let method = self.unsubscribe.take().expect("unsubscribed once");
+
+        self.client
+            .subscriptions
+            .remove(&self.notification, self.id);
+
+        self.client.call(&method, [self.id]).await
+    }
+}
+
+impl<T, K: Codec> Drop for Subscription<T, K> {
+    fn drop(&mut self) {
+        let Some(method) = self.unsubscribe.take() else {
+            return;
+        };
+
+        self.client
+            .subscriptions
+            .remove(&self.notification, self.id);
+
+        let sent = K::encode(&Request::notification(method.as_str(), [self.id])).and_then(|data| {
+            self.client.backlog.reserve();
+
+            self.client
+                .output_sender
+                .try_send((None, data))
+                .map_err(|err| {
+                    self.client.backlog.release();
+
+                    err.into_send_error().into()
+                })
+        });
+
+        if let Err(err) = sent {
+            log::warn!("unsubscribe {} error, {}", self.id, err);
+        }
+    }
+}
+
+impl<T, K: Codec> Stream for Subscription<T, K>
+where
+    for<'b> T: Deserialize<'b>,
+{
+    type Item = RPCResult<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.notifications
+            .poll_next_unpin(cx)
+            .map(|next| next.map(|value| serde_json::from_value(value).map_err(map_error)))
+    }
+}
+
+impl<K: Codec> Client<K> {
+    /// Call `method` to subscribe, returning the stream of values pushed to the
+    /// subscription, see [`Server::handle_subscription`](crate::Server::handle_subscription).
+    ///
+    /// `method` must return the subscription id, values are received as `notification`
+    /// notifications carrying it. The subscription is closed by calling `unsubscribe`
+    /// with the subscription id, once the stream is dropped or by [`Subscription::unsubscribe`].
+    pub async fn subscribe<P, T>(
+        &mut self,
+        method: &str,
+        unsubscribe: &str,
+        notification: &str,
+        params: P,
+    ) -> RPCResult<Subscription<T, K>>
+    where
+        P: serde::Serialize,
+        for<'b> T: Deserialize<'b>,
+    {
+        let receiver = self.completed_q.wait_one(self.timeout).await?;
+
+        let call = receiver.event_id();
+
+        let notifications = self.subscriptions.register(call, notification);
+
+        let id = match self.send_request(method, params, receiver).await {
+            Ok(mut responser) => responser.recv::<u64>().await,
+            Err(err) => Err(err),
+        };
+
+        let id = match id {
+            Ok(id) => id,
+            Err(err) => {
+                self.subscriptions.cancel(call);
+
+                return Err(err);
+            }
+        };
+
+        Ok(Subscription {
+            id,
+            notification: notification.to_owned(),
+            notifications,
+            client: self.clone(),
+            unsubscribe: Some(unsubscribe.to_owned()),
+            result: PhantomData,
+        })
+    }
+}
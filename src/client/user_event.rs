@@ -1,9 +1,287 @@
-use completeq_rs::{oneshot::CompleteQ, user_event::RPCResponser};
+use std::{
+    collections::HashSet,
+    future::poll_fn,
+    sync::{Arc, Mutex},
+    task::{Poll, Waker},
+    time::Duration,
+};
 
-use crate::RPCResult;
+use completeq_rs::{
+    oneshot::{CompleteQ, EventReceiver},
+    user_event::RPCResponser,
+};
+
+use crate::{
+    timer::{self, Timeout, Timer},
+    ErrorCode, RPCError, RPCResult,
+};
+
+use super::InflightOverflow;
 
 pub(crate) type ResponserArgument = RPCResult<serde_json::Value>;
 
 pub(crate) type RPCEvent = RPCResponser<ResponserArgument>;
 
-pub(crate) type RPCCompletedQ = CompleteQ<RPCEvent>;
+/// Ids of pending calls and the tasks waiting for an in-flight slot.
+struct Pending {
+    ids: HashSet<usize>,
+    /// Ids whose receiver may still be alive, kept until [`RPCCompletedQ::remove`]
+    /// even once the call completed, so they aren't reused meanwhile.
+    in_use: HashSet<usize>,
+    /// Next id to try, wrapping around to 1 after the max request id.
+    next_id: usize,
+    waiters: Vec<Waker>,
+}
+
+impl Default for Pending {
+    fn default() -> Self {
+        Self {
+            ids: HashSet::new(),
+            in_use: HashSet::new(),
+            next_id: 1,
+            waiters: vec![],
+        }
+    }
+}
+
+impl Pending {
+    /// Allocate the next id in `1..=max_id` which isn't in use, returns `None` once
+    /// every id is.
+    fn allocate(&mut self, max_id: usize) -> Option<usize> {
+        if self.in_use.len() >= max_id {
+            return None;
+        }
+
+        loop {
+            let id = self.next_id;
+
+            self.next_id = if id >= max_id { 1 } else { id + 1 };
+
+            if self.in_use.insert(id) {
+                self.ids.insert(id);
+
+                return Some(id);
+            }
+        }
+    }
+
+    fn remove(&mut self, id: usize) -> bool {
+        let removed = self.ids.remove(&id);
+
+        if removed {
+            self.waiters.drain(..).for_each(Waker::wake);
+        }
+
+        removed
+    }
+}
+
+/// [`CompleteQ`] wrapper which tracks the ids of pending calls.
+///
+/// `CompleteQ::cancel_all` drops the channels of receivers still waiting, which makes
+/// them panic on their next poll, so pending calls are canceled by completing each one.
+///
+/// With `max_inflight` set, new calls wait until fewer calls are pending or fail,
+/// depending on `overflow`.
+#[derive(Clone)]
+pub(crate) struct RPCCompletedQ {
+    q: CompleteQ<RPCEvent>,
+    pending: Arc<Mutex<Pending>>,
+    max_inflight: Option<usize>,
+    overflow: InflightOverflow,
+    max_id: usize,
+}
+
+impl RPCCompletedQ {
+    pub(crate) fn new(
+        max_inflight: Option<usize>,
+        overflow: InflightOverflow,
+        max_id: usize,
+    ) -> Self {
+        Self {
+            q: CompleteQ::new(),
+            pending: Default::default(),
+            max_inflight,
+            overflow,
+            max_id,
+        }
+    }
+
+    /// Number of pending calls.
+    pub(crate) fn len(&self) -> usize {
+        self.pending.lock().unwrap().ids.len()
+    }
+
+    fn is_full(&self, pending: &Pending) -> bool {
+        self.max_inflight
+            .is_some_and(|max| pending.ids.len() >= max)
+    }
+
+    fn overflow_error(&self) -> RPCError {
+        RPCError::new(
+            ErrorCode::TooManyInflight,
+            format!(
+                "Too many in-flight calls, limit {}",
+                self.max_inflight.unwrap_or_default()
+            ),
+            None,
+        )
+    }
+
+    /// Allocate the id of a new call, failing once every id up to the max is in use.
+    fn allocate(&self, pending: &mut Pending) -> RPCResult<usize> {
+        pending.allocate(self.max_id).ok_or_else(|| {
+            RPCError::new(
+                ErrorCode::RequestIdsExhausted,
+                format!("Request ids exhausted, max request id {}", self.max_id),
+                None,
+            )
+        })
+    }
+
+    /// Ready once a new call doesn't exceed the in-flight limit, or at once if
+    /// such calls are rejected.
+    #[cfg(feature = "tower")]
+    pub(crate) fn poll_ready(&self, cx: &mut std::task::Context<'_>) -> Poll<()> {
+        let mut pending = self.pending.lock().unwrap();
+
+        if self.is_full(&pending) && self.overflow == InflightOverflow::Wait {
+            pending.waiters.push(cx.waker().clone());
+
+            return Poll::Pending;
+        }
+
+        Poll::Ready(())
+    }
+
+    /// Register a new pending call, waiting for an in-flight slot first unless
+    /// [`InflightOverflow::Reject`] is set.
+    async fn reserve<T, F>(&mut self, wait: F) -> RPCResult<EventReceiver<RPCEvent, T>>
+    where
+        T: Timer,
+        F: FnOnce(&CompleteQ<RPCEvent>, usize) -> EventReceiver<RPCEvent, T>,
+    {
+        let mut wait = Some(wait);
+
+        poll_fn(|cx| {
+            let mut pending = self.pending.lock().unwrap();
+
+            if self.is_full(&pending) {
+                if self.overflow == InflightOverflow::Reject {
+                    return Poll::Ready(Err(self.overflow_error()));
+                }
+
+                pending.waiters.push(cx.waker().clone());
+
+                return Poll::Pending;
+            }
+
+            let id = self.allocate(&mut pending)?;
+
+            let wait = wait.take().expect("polled after completion");
+
+            Poll::Ready(Ok(wait(&self.q, id)))
+        })
+        .await
+    }
+
+    /// Register a new pending call, failing with a timeout after `timeout` if set.
+    pub(crate) async fn wait_one(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> RPCResult<EventReceiver<RPCEvent, Timeout>> {
+        self.reserve(|q, id| wait_for(q, id, timeout)).await
+    }
+
+    pub(crate) async fn wait_one_with_timer<T: Timer>(
+        &mut self,
+        timer: T,
+    ) -> RPCResult<EventReceiver<RPCEvent, T>> {
+        self.reserve(move |q, id| q.wait_for_with_timer(id, timer))
+            .await
+    }
+
+    /// Register a new pending call unless the in-flight limit is reached.
+    #[cfg(feature = "tower")]
+    pub(crate) fn try_wait_one(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> RPCResult<EventReceiver<RPCEvent, Timeout>> {
+        let mut pending = self.pending.lock().unwrap();
+
+        if self.is_full(&pending) {
+            return Err(match self.overflow {
+                InflightOverflow::Wait => RPCError::new(
+                    ErrorCode::InternalError,
+                    "Too many in-flight calls, poll_ready first".to_owned(),
+                    None,
+                ),
+                InflightOverflow::Reject => self.overflow_error(),
+            });
+        }
+
+        let id = self.allocate(&mut pending)?;
+
+        Ok(wait_for(&self.q, id, timeout))
+    }
+
+    /// Complete a pending call, returns the argument back if no call is waiting for `id`.
+    pub(crate) fn complete_one(
+        &self,
+        id: usize,
+        argument: ResponserArgument,
+    ) -> Result<(), ResponserArgument> {
+        if !self.pending.lock().unwrap().remove(id) {
+            return Err(argument);
+        }
+
+        if self.q.complete_one(id, argument).is_closed() {
+            log::trace!("call {} receiver dropped before completion", id);
+        }
+
+        Ok(())
+    }
+
+    /// Forget a pending call whose receiver was dropped.
+    pub(crate) fn remove(&self, id: usize) {
+        let mut pending = self.pending.lock().unwrap();
+
+        pending.remove(id);
+        pending.in_use.remove(&id);
+    }
+
+    /// Complete all pending calls with an [`ErrorCode::ConnectionClosed`] error.
+    pub(crate) fn cancel_all(&self) {
+        self.fail_all(RPCError::new(
+            ErrorCode::ConnectionClosed,
+            "Connection closed".to_owned(),
+            None,
+        ));
+    }
+
+    /// Complete all pending calls with `err`.
+    pub(crate) fn fail_all(&self, err: RPCError) {
+        let pending = {
+            let mut pending = self.pending.lock().unwrap();
+
+            pending.waiters.drain(..).for_each(Waker::wake);
+
+            std::mem::take(&mut pending.ids)
+        };
+
+        for id in pending {
+            _ = self.q.complete_one(id, Err(err.clone()));
+        }
+    }
+}
+
+fn wait_for(
+    q: &CompleteQ<RPCEvent>,
+    id: usize,
+    timeout: Option<Duration>,
+) -> EventReceiver<RPCEvent, Timeout> {
+    match timeout {
+        Some(timeout) => q.wait_for_with_timer(id, timer::timeout(timeout)),
+        None => q.wait_for(id),
+    }
+}
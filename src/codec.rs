@@ -0,0 +1,265 @@
+//! Wire format of JSONRPC objects.
+
+use std::{cell::RefCell, fmt::Debug};
+
+use bytes::{BufMut, BytesMut};
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "extensions"))]
+use serde_json::value::RawValue;
+
+use crate::{channel::RPCData, BorrowedResponse, RPCResult};
+
+/// Serialize/deserialize JSONRPC objects to/from transport frames.
+///
+/// [`crate::Client`] and [`crate::Server`] are generic over the codec, both peers
+/// of one connection must use the same codec.
+pub trait Codec: Default + Clone + Send + Sync + 'static {
+    /// Undecoded params of an incoming request, deserialized by the handler
+    /// directly into its own params type.
+    type Params: for<'de> Deserialize<'de> + Debug + Send + Sync + 'static;
+
+    /// Serialize `value` into one transport frame.
+    fn encode<T>(value: &T) -> RPCResult<RPCData>
+    where
+        T: Serialize + ?Sized;
+
+    /// Deserialize one transport frame.
+    fn decode<'a, T>(data: &'a [u8]) -> RPCResult<T>
+    where
+        T: Deserialize<'a>;
+
+    /// Deserialize undecoded request params.
+    fn decode_params<P>(params: &Self::Params) -> RPCResult<P>
+    where
+        for<'de> P: Deserialize<'de>;
+
+    /// Join encoded frames into one frame holding the array of them.
+    ///
+    /// Defaults to a JSON array.
+    fn encode_batch(frames: &[RPCData]) -> RPCData {
+        let mut batch =
+            Vec::with_capacity(frames.iter().map(|frame| frame.len() + 1).sum::<usize>() + 1);
+
+        batch.push(b'[');
+
+        for (i, frame) in frames.iter().enumerate() {
+            if i > 0 {
+                batch.push(b',');
+            }
+
+            batch.extend_from_slice(frame);
+        }
+
+        batch.push(b']');
+
+        batch.into()
+    }
+}
+
+/// Default codec, JSON text frames.
+///
+/// Frames are serialized into the spare capacity of a per-thread buffer and split off
+/// of it without copying, instead of growing a new buffer for every frame.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonCodec;
+
+/// Spare capacity of the encode buffer reserved before every frame.
+const ENCODE_BUFFER_CAPACITY: usize = 4096;
+
+thread_local! {
+    static ENCODE_BUFFER: RefCell<BytesMut> = RefCell::new(BytesMut::new());
+}
+
+impl JsonCodec {
+    /// Deserialize one response frame without copying its result, which is handed
+    /// out as raw JSON text, see [`BorrowedResponse`].
+    pub fn decode_response(data: &[u8]) -> RPCResult<BorrowedResponse<'_>> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
+
+impl Codec for JsonCodec {
+    /// Flattened extension members can't be deserialized along with [`serde_json::value::RawValue`].
+    #[cfg(feature = "extensions")]
+    type Params = serde_json::Value;
+
+    #[cfg(not(feature = "extensions"))]
+    type Params = Box<RawValue>;
+
+    fn encode<T>(value: &T) -> RPCResult<RPCData>
+    where
+        T: Serialize + ?Sized,
+    {
+        ENCODE_BUFFER.with(|buffer| {
+            // A value serializing another frame on the way can't share the buffer.
+            let Ok(mut buffer) = buffer.try_borrow_mut() else {
+                return Ok(serde_json::to_vec(value)?.into());
+            };
+
+            buffer.reserve(ENCODE_BUFFER_CAPACITY);
+
+            if let Err(err) = serde_json::to_writer((&mut *buffer).writer(), value) {
+                buffer.clear();
+
+                return Err(err.into());
+            }
+
+            Ok(buffer.split().freeze())
+        })
+    }
+
+    fn decode<'a, T>(data: &'a [u8]) -> RPCResult<T>
+    where
+        T: Deserialize<'a>,
+    {
+        Ok(serde_json::from_slice(data)?)
+    }
+
+    fn decode_params<P>(params: &Self::Params) -> RPCResult<P>
+    where
+        for<'de> P: Deserialize<'de>,
+    {
+        #[cfg(feature = "extensions")]
+        return Ok(P::deserialize(params)?);
+
+        #[cfg(not(feature = "extensions"))]
+        return Ok(serde_json::from_str(params.get())?);
+    }
+}
+
+/// MessagePack codec, JSONRPC objects are encoded as maps keyed by member names.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MsgpackCodec;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MsgpackCodec {
+    type Params = serde_json::Value;
+
+    fn encode<T>(value: &T) -> RPCResult<RPCData>
+    where
+        T: Serialize + ?Sized,
+    {
+        rmp_serde::to_vec_named(value)
+            .map(Into::into)
+            .map_err(|err| codec_error("msgpack", err))
+    }
+
+    fn decode<'a, T>(data: &'a [u8]) -> RPCResult<T>
+    where
+        T: Deserialize<'a>,
+    {
+        rmp_serde::from_slice(data).map_err(|err| codec_error("msgpack", err))
+    }
+
+    fn decode_params<P>(params: &Self::Params) -> RPCResult<P>
+    where
+        for<'de> P: Deserialize<'de>,
+    {
+        Ok(P::deserialize(params)?)
+    }
+
+    /// MessagePack arrays are a length header followed by the encoded elements.
+    fn encode_batch(frames: &[RPCData]) -> RPCData {
+        let len = frames.len();
+
+        let mut batch =
+            Vec::with_capacity(frames.iter().map(|frame| frame.len()).sum::<usize>() + 5);
+
+        if len < 16 {
+            batch.push(0x90 | len as u8);
+        } else if let Ok(len) = u16::try_from(len) {
+            batch.push(0xdc);
+            batch.extend_from_slice(&len.to_be_bytes());
+        } else {
+            batch.push(0xdd);
+            batch.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+
+        for frame in frames {
+            batch.extend_from_slice(frame);
+        }
+
+        batch.into()
+    }
+}
+
+/// CBOR codec, JSONRPC objects are encoded as maps keyed by member names.
+///
+/// Frames are decoded through a [`ciborium::Value`], strings are copied out of the frame.
+#[cfg(feature = "cbor")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CborCodec;
+
+#[cfg(feature = "cbor")]
+impl Codec for CborCodec {
+    type Params = serde_json::Value;
+
+    fn encode<T>(value: &T) -> RPCResult<RPCData>
+    where
+        T: Serialize + ?Sized,
+    {
+        let mut data = vec![];
+
+        ciborium::into_writer(value, &mut data).map_err(|err| codec_error("cbor", err))?;
+
+        Ok(data.into())
+    }
+
+    fn decode<'a, T>(data: &'a [u8]) -> RPCResult<T>
+    where
+        T: Deserialize<'a>,
+    {
+        ciborium::from_reader::<ciborium::Value, _>(data)
+            .map_err(|err| codec_error("cbor", err))?
+            .deserialized()
+            .map_err(|err| codec_error("cbor", err))
+    }
+
+    fn decode_params<P>(params: &Self::Params) -> RPCResult<P>
+    where
+        for<'de> P: Deserialize<'de>,
+    {
+        Ok(P::deserialize(params)?)
+    }
+
+    /// CBOR arrays are a length header followed by the encoded elements.
+    fn encode_batch(frames: &[RPCData]) -> RPCData {
+        let len = frames.len();
+
+        let mut batch =
+            Vec::with_capacity(frames.iter().map(|frame| frame.len()).sum::<usize>() + 5);
+
+        if len < 24 {
+            batch.push(0x80 | len as u8);
+        } else if let Ok(len) = u8::try_from(len) {
+            batch.push(0x98);
+            batch.push(len);
+        } else if let Ok(len) = u16::try_from(len) {
+            batch.push(0x99);
+            batch.extend_from_slice(&len.to_be_bytes());
+        } else {
+            batch.push(0x9a);
+            batch.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+
+        for frame in frames {
+            batch.extend_from_slice(frame);
+        }
+
+        batch.into()
+    }
+}
+
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+fn codec_error<E>(format: &str, err: E) -> crate::RPCError
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    crate::RPCError::new(
+        crate::ErrorCode::ParseError,
+        format!("Serialize/Deserialize {} data error: {}", format, err),
+        None,
+    )
+    .with_source(err)
+}
@@ -0,0 +1,97 @@
+use std::{future::Future, sync::Arc};
+
+use futures::{
+    future::{AbortHandle, AbortRegistration, Abortable, BoxFuture},
+    FutureExt,
+};
+
+use crate::{channel::TransportChannel, RPCResult};
+
+/// Background task of a client or a server session, see [`Executor`].
+pub type Task = BoxFuture<'static, RPCResult<()>>;
+
+/// Runtime running the background tasks of clients and server sessions, see
+/// [`ClientConfig::executor`](crate::ClientConfig::executor) and
+/// [`ServerBuilder::executor`](crate::ServerBuilder::executor).
+///
+/// Without an executor configured tasks are spawned by
+/// [`TransportChannel::spawn`], tying the runtime to the transport. Any
+/// `Fn(Task)` is an executor.
+pub trait Executor: Send + Sync + 'static {
+    /// Run `task` to completion in the background.
+    fn spawn(&self, task: Task);
+}
+
+impl<F> Executor for F
+where
+    F: Fn(Task) + Send + Sync + 'static,
+{
+    fn spawn(&self, task: Task) {
+        self(task)
+    }
+}
+
+/// Spawns tasks on the [`tokio`](https://docs.rs/tokio) runtime of the caller.
+///
+/// Clients must be created and sessions accepted within a tokio runtime.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioExecutor;
+
+#[cfg(feature = "tokio")]
+impl Executor for TokioExecutor {
+    fn spawn(&self, task: Task) {
+        tokio::spawn(run(task));
+    }
+}
+
+/// Spawns tasks on the [`async-std`](https://docs.rs/async-std) runtime.
+#[cfg(feature = "async-std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsyncStdExecutor;
+
+#[cfg(feature = "async-std")]
+impl Executor for AsyncStdExecutor {
+    fn spawn(&self, task: Task) {
+        async_std::task::spawn(run(task));
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+async fn run(task: Task) {
+    if let Err(err) = task.await {
+        log::error!("rpc background task error, {}", err);
+    }
+}
+
+/// Spawn `future` on `executor`, or by the transport `C` if there is none,
+/// returns the handle aborting it.
+pub(crate) fn spawn<C, Fut>(executor: Option<&Arc<dyn Executor>>, future: Fut) -> AbortHandle
+where
+    C: TransportChannel,
+    Fut: Future<Output = RPCResult<()>> + Send + 'static,
+{
+    let (handle, registration) = AbortHandle::new_pair();
+
+    spawn_abortable::<C, _>(executor, future, registration);
+
+    handle
+}
+
+/// Spawn `future`, aborted by the handle of `registration`, see [`spawn`].
+pub(crate) fn spawn_abortable<C, Fut>(
+    executor: Option<&Arc<dyn Executor>>,
+    future: Fut,
+    registration: AbortRegistration,
+) where
+    C: TransportChannel,
+    Fut: Future<Output = RPCResult<()>> + Send + 'static,
+{
+    // An aborted task ends as if it completed.
+    let future = Abortable::new(future, registration).map(|result| result.unwrap_or(Ok(())));
+
+    match executor {
+        Some(executor) => executor.spawn(Box::pin(future)),
+        None => C::spawn(future),
+    }
+}
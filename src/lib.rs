@@ -1,16 +1,65 @@
+//! Without the default `std` feature only the JSONRPC objects ([`Request`], [`Response`],
+//! [`Error`] and [`ErrorCode`]) are built, on `core` and `alloc`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 mod object;
 pub use object::*;
+
+#[cfg(feature = "std")]
 mod client;
+pub mod prelude;
+#[cfg(feature = "std")]
 pub use client::*;
 
+#[cfg(feature = "std")]
 mod server;
+#[cfg(feature = "std")]
 pub use server::*;
 
 mod result;
 pub use result::*;
 
+#[cfg(feature = "std")]
 pub mod channel;
 
+#[cfg(feature = "std")]
+mod codec;
+#[cfg(feature = "std")]
+pub use codec::*;
+
+#[cfg(feature = "std")]
+mod metrics;
+#[cfg(feature = "std")]
+pub use metrics::*;
+
+#[cfg(feature = "std")]
+mod executor;
+#[cfg(feature = "std")]
+pub use executor::*;
+
+#[cfg(feature = "std")]
+mod trace;
+
+#[cfg(feature = "std")]
+pub mod timer;
+
+#[cfg(feature = "std")]
+pub mod transport;
+
+#[cfg(feature = "std")]
 pub use channel::RPCData;
 
+#[cfg(feature = "std")]
 pub use bytes;
+
+#[cfg(feature = "macros")]
+pub use jsonrpc_rs_macros::{rpc, rpc_client, RpcParams};
+
+/// Dependencies of macro generated code.
+#[doc(hidden)]
+pub mod __private {
+    pub use serde;
+}
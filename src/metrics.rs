@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+/// Observability hooks of calls, see [`ClientConfig::metrics`](crate::ClientConfig::metrics)
+/// and [`ServerBuilder::metrics`](crate::ServerBuilder::metrics).
+///
+/// All callbacks default to no-ops, and without metrics configured no call timing is
+/// taken at all. Callbacks run inline on the call path and should not block.
+///
+/// An implementation backed by the [`metrics`](https://docs.rs/metrics) crate:
+///
+/// ```ignore
+/// struct RpcMetrics;
+///
+/// impl jsonrpc_rs::Metrics for RpcMetrics {
+///     fn on_request(&self, method: &str) {
+///         metrics::counter!("rpc_requests_total", "method" => method.to_owned()).increment(1);
+///     }
+///
+///     fn on_response(&self, method: &str, elapsed: Duration, is_error: bool) {
+///         let labels = [("method", method.to_owned()), ("error", is_error.to_string())];
+///
+///         metrics::histogram!("rpc_duration_seconds", &labels).record(elapsed);
+///     }
+/// }
+/// ```
+///
+/// With [`prometheus`](https://docs.rs/prometheus), `on_request` increments an
+/// `IntCounterVec` and `on_response` observes a `HistogramVec`, both labeled by method.
+pub trait Metrics: Send + Sync + 'static {
+    /// A call of `method` was sent by the client or received by the server.
+    fn on_request(&self, method: &str) {
+        _ = method;
+    }
+
+    /// A call of `method` completed after `elapsed`, `is_error` if it failed.
+    ///
+    /// On the client `elapsed` runs from sending the request to receiving its response,
+    /// on the server from receiving the request to its handler completing.
+    fn on_response(&self, method: &str, elapsed: Duration, is_error: bool) {
+        _ = (method, elapsed, is_error);
+    }
+
+    /// The client's transport took `elapsed` to accept an outgoing frame.
+    ///
+    /// Long waits mean the transport applies backpressure, see
+    /// [`ClientConfig::high_watermark`](crate::ClientConfig::high_watermark).
+    fn on_sink_wait(&self, elapsed: Duration) {
+        _ = elapsed;
+    }
+}
+
+/// [`Metrics`] implementation ignoring all calls.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {}
@@ -1,6 +1,14 @@
-use std::fmt::{Display, Formatter};
+use alloc::{
+    borrow::{Cow, ToOwned},
+    format,
+    string::String,
+    sync::Arc,
+};
+use core::fmt::{Debug, Display, Formatter};
 
+#[cfg(feature = "std")]
 use completeq_rs::error::CompleteQError;
+#[cfg(feature = "std")]
 use futures::channel::mpsc::SendError;
 use serde::*;
 
@@ -25,6 +33,48 @@ where
     pub method: S,
     /// A Structured value that holds the parameter values to be used during the invocation of the method. This member MAY be omitted.
     pub params: P,
+    /// [W3C trace context](https://www.w3.org/TR/trace-context/#traceparent-header) of the
+    /// caller, only sent when the client has a trace context.
+    #[cfg(feature = "traceparent")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traceparent: Option<S>,
+    /// Non-standard members, kept so they can be re-emitted unchanged.
+    #[cfg(feature = "extensions")]
+    #[serde(flatten)]
+    pub extensions: serde_json::Map<String, serde_json::Value>,
+}
+
+impl<S, P> Request<S, P>
+where
+    S: AsRef<str>,
+{
+    /// Create a method call expecting a response with the same `id`.
+    pub fn call(id: usize, method: S, params: P) -> Self {
+        Self {
+            id: Some(id),
+            jsonrpc: Version,
+            method,
+            params,
+            #[cfg(feature = "traceparent")]
+            traceparent: None,
+            #[cfg(feature = "extensions")]
+            extensions: Default::default(),
+        }
+    }
+
+    /// Create a notification, which the server doesn't respond to.
+    pub fn notification(method: S, params: P) -> Self {
+        Self {
+            id: None,
+            jsonrpc: Version,
+            method,
+            params,
+            #[cfg(feature = "traceparent")]
+            traceparent: None,
+            #[cfg(feature = "extensions")]
+            extensions: Default::default(),
+        }
+    }
 }
 
 /// JSONRPC version type.
@@ -55,7 +105,10 @@ impl<'de> Deserialize<'de> for Version {
 /// except for in the case of Notifications.
 ///
 /// visit [`here`](https://www.jsonrpc.org/specification) for details
-#[derive(Debug, Serialize, Deserialize, Default, PartialEq)]
+///
+/// Serializing fails unless exactly one of `result` and `error` is set, see
+/// [`Response::success`] and [`Response::error`].
+#[derive(Debug, Deserialize, Default, PartialEq)]
 pub struct Response<S, R, D>
 where
     S: AsRef<str>,
@@ -68,48 +121,327 @@ where
     /// This member is REQUIRED on success.
     /// This member MUST NOT exist if there was an error invoking the method.
     /// The value of this member is determined by the method invoked on the Server.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    ///
+    /// A `null` result deserializes as `Some`, only a missing member is [`None`].
+    // A plain `default` would bound `R: Default`, which borrowed results don't implement.
+    #[serde(
+        default = "Option::default",
+        deserialize_with = "deserialize_present",
+        bound(deserialize = "R: Deserialize<'de>")
+    )]
     pub result: Option<R>,
 
     ///This member is REQUIRED on error.
     /// This member MUST NOT exist if there was no error triggered during invocation.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<Error<S, D>>,
+    /// Non-standard members, kept so they can be re-emitted unchanged.
+    #[cfg(feature = "extensions")]
+    #[serde(flatten)]
+    pub extensions: serde_json::Map<String, serde_json::Value>,
 }
 
-/// JSONRPC type compatible with both [`Request`] and [`Response`] data structures
-#[derive(Debug, Serialize, Deserialize, Default, PartialEq)]
-struct JSONRPC<S, P, R, D> {
-    /// An identifier established by the Client that MUST contain a String, Number,
-    /// or NULL value if included. If it is not included it is assumed to be a notification.
-    /// The value SHOULD normally not be Null and Numbers SHOULD NOT contain fractional parts
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub id: Option<usize>,
-    /// A String specifying the version of the JSON-RPC protocol. MUST be exactly "2.0".
-    pub jsonrpc: Version,
-    /// A String containing the name of the method to be invoked. Method names
-    /// that begin with the word rpc followed by a period character (U+002E or ASCII 46)
-    /// are reserved for rpc-internal methods and extensions and MUST NOT be used for anything else
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub method: Option<S>,
-    /// A Structured value that holds the parameter values to be used during the invocation of the method. This member MAY be omitted.
+impl<S, R, D> Response<S, R, D>
+where
+    S: AsRef<str>,
+{
+    /// Create the response of a successful call.
+    pub fn success(id: usize, result: R) -> Self {
+        Self {
+            id,
+            jsonrpc: Version,
+            result: Some(result),
+            error: None,
+            #[cfg(feature = "extensions")]
+            extensions: Default::default(),
+        }
+    }
+
+    /// Create the response of a failed call.
+    pub fn error(id: usize, error: Error<S, D>) -> Self {
+        Self {
+            id,
+            jsonrpc: Version,
+            result: None,
+            error: Some(error),
+            #[cfg(feature = "extensions")]
+            extensions: Default::default(),
+        }
+    }
+}
+
+impl<S, R, D> Serialize for Response<S, R, D>
+where
+    S: AsRef<str> + Serialize,
+    R: Serialize,
+    D: Serialize,
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: Serializer,
+    {
+        if self.result.is_some() == self.error.is_some() {
+            return Err(ser::Error::custom(format_args!(
+                "Response {} MUST contain exactly one of result and error",
+                self.id
+            )));
+        }
+
+        SerializeResponse {
+            id: self.id,
+            jsonrpc: &self.jsonrpc,
+            result: &self.result,
+            error: &self.error,
+            #[cfg(feature = "extensions")]
+            extensions: &self.extensions,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Serialized members of a validated [`Response`].
+#[derive(Serialize)]
+#[serde(rename = "Response")]
+struct SerializeResponse<'a, S, R, D> {
+    id: usize,
+    jsonrpc: &'a Version,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub params: Option<P>,
-    /// This member is REQUIRED on success.
-    /// This member MUST NOT exist if there was an error invoking the method.
-    /// The value of this member is determined by the method invoked on the Server.
+    result: &'a Option<R>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub result: Option<R>,
+    error: &'a Option<Error<S, D>>,
+    #[cfg(feature = "extensions")]
+    #[serde(flatten)]
+    extensions: &'a serde_json::Map<String, serde_json::Value>,
+}
 
-    ///This member is REQUIRED on error.
-    /// This member MUST NOT exist if there was no error triggered during invocation.
+/// [`Response`] borrowing from the JSON text it's parsed from, see
+/// [`JsonCodec::decode_response`](crate::JsonCodec::decode_response).
+///
+/// Parsing allocates no [`serde_json::Value`] of the result, which stays raw JSON text
+/// until the caller deserializes it, nor the error message unless it's escaped. The
+/// input buffer must outlive the response. Responses with the `extensions` feature
+/// fail to parse, as their flattened members can't be captured as
+/// [`RawValue`](serde_json::value::RawValue).
+pub type BorrowedResponse<'a> =
+    Response<CowStr<'a>, &'a serde_json::value::RawValue, &'a serde_json::value::RawValue>;
+
+/// Deserialize a present member as `Some`, even when its value is `null`.
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Deserialize `T` from a map only, rejecting the sequence form which derived
+/// struct impls accept as well.
+#[cfg(feature = "std")]
+pub(crate) fn deserialize_map<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    deserializer.deserialize_map(visitor::MapVisitor(core::marker::PhantomData))
+}
+
+/// String member of an incoming object, borrowed from the frame if the codec can lend it.
+///
+/// Unlike `&str` it also accepts strings the codec has to copy, e.g. escaped JSON strings
+/// or any string of a reader based codec like CBOR.
+#[derive(Debug, Default, PartialEq)]
+pub struct CowStr<'a>(Cow<'a, str>);
+
+impl<'a> CowStr<'a> {
+    /// The string, borrowed unless it had to be copied.
+    pub fn into_inner(self) -> Cow<'a, str> {
+        self.0
+    }
+}
+
+impl AsRef<str> for CowStr<'_> {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for CowStr<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_str(visitor::CowStrVisitor)
+            .map(CowStr)
+    }
+}
+
+/// [`Request`] accepting a missing `jsonrpc` member as version 2.0, for lenient peers.
+#[derive(Deserialize)]
+pub(crate) struct LenientRequest<S, P> {
+    id: Option<usize>,
+    #[serde(default)]
+    jsonrpc: Version,
+    method: S,
+    params: P,
+    #[cfg(feature = "traceparent")]
+    #[serde(default)]
+    traceparent: Option<S>,
+    #[cfg(feature = "extensions")]
+    #[serde(flatten)]
+    extensions: serde_json::Map<String, serde_json::Value>,
+}
+
+impl<S: AsRef<str>, P> From<LenientRequest<S, P>> for Request<S, P> {
+    fn from(request: LenientRequest<S, P>) -> Self {
+        Self {
+            id: request.id,
+            jsonrpc: request.jsonrpc,
+            method: request.method,
+            params: request.params,
+            #[cfg(feature = "traceparent")]
+            traceparent: request.traceparent,
+            #[cfg(feature = "extensions")]
+            extensions: request.extensions,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// JSON-RPC 1.0 request version, echoed in its response by [`LegacyResponse`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LegacyVersion {
+    /// The request carried `"jsonrpc": "1.0"`.
+    Tagged,
+    /// The request omitted the `jsonrpc` member.
+    Untagged,
+}
+
+/// `jsonrpc` member of a [`CompatRequest`], `None` for version 2.0.
+#[cfg(feature = "std")]
+pub(crate) struct CompatVersion(Option<LegacyVersion>);
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for CompatVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(visitor::CompatVersionVisitor)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for CompatVersion {
+    fn default() -> Self {
+        Self(Some(LegacyVersion::Untagged))
+    }
+}
+
+#[cfg(feature = "std")]
+/// [`Request`] of either a JSON-RPC 2.0 or 1.0 peer, the latter with a `"1.0"` or
+/// missing `jsonrpc` member.
+#[derive(Deserialize)]
+pub(crate) struct CompatRequest<S, P> {
+    id: Option<usize>,
+    #[serde(default)]
+    jsonrpc: CompatVersion,
+    method: S,
+    params: P,
+    #[cfg(feature = "traceparent")]
+    #[serde(default)]
+    traceparent: Option<S>,
+    #[cfg(feature = "extensions")]
+    #[serde(flatten)]
+    extensions: serde_json::Map<String, serde_json::Value>,
+}
+
+#[cfg(feature = "std")]
+impl<S: AsRef<str>, P> CompatRequest<S, P> {
+    /// Returns the request and the version of a 1.0 request.
+    pub(crate) fn split(self) -> (Request<S, P>, Option<LegacyVersion>) {
+        let request = Request {
+            id: self.id,
+            jsonrpc: Version,
+            method: self.method,
+            params: self.params,
+            #[cfg(feature = "traceparent")]
+            traceparent: self.traceparent,
+            #[cfg(feature = "extensions")]
+            extensions: self.extensions,
+        };
+
+        (request, self.jsonrpc.0)
+    }
+}
+
+#[cfg(feature = "std")]
+/// JSON-RPC 1.0 response, carrying both `result` and `error` with one of them `null`.
+#[derive(Serialize)]
+pub(crate) struct LegacyResponse<R, E> {
+    id: usize,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub error: Option<Error<S, D>>,
+    jsonrpc: Option<&'static str>,
+    result: Option<R>,
+    error: Option<E>,
+}
+
+#[cfg(feature = "std")]
+impl<R, E> LegacyResponse<R, E> {
+    pub(crate) fn new(
+        version: LegacyVersion,
+        id: usize,
+        result: Option<R>,
+        error: Option<E>,
+    ) -> Self {
+        Self {
+            id,
+            jsonrpc: match version {
+                LegacyVersion::Tagged => Some("1.0"),
+                LegacyVersion::Untagged => None,
+            },
+            result,
+            error,
+        }
+    }
+}
+
+/// [`Response`] accepting a missing `jsonrpc` member as version 2.0, for lenient peers.
+#[derive(Deserialize)]
+pub(crate) struct LenientResponse<S, R, D> {
+    id: usize,
+    #[serde(default)]
+    jsonrpc: Version,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_present",
+        bound(deserialize = "R: Deserialize<'de>")
+    )]
+    result: Option<R>,
+    error: Option<Error<S, D>>,
+    #[cfg(feature = "extensions")]
+    #[serde(flatten)]
+    extensions: serde_json::Map<String, serde_json::Value>,
 }
 
+impl<S: AsRef<str>, R, D> From<LenientResponse<S, R, D>> for Response<S, R, D> {
+    fn from(response: LenientResponse<S, R, D>) -> Self {
+        Self {
+            id: response.id,
+            jsonrpc: response.jsonrpc,
+            result: response.result,
+            error: response.error,
+            #[cfg(feature = "extensions")]
+            extensions: response.extensions,
+        }
+    }
+}
+
+/// The underlying cause of an [`Error`], see [`core::error::Error::source`].
+pub type ErrorSource = Arc<dyn core::error::Error + Send + Sync + 'static>;
+
 /// When a rpc call encounters an error,
 /// the Response Object MUST contain the error member with a value that is a Object.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, thiserror::Error)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Error<S, D> {
     /// A Number that indicates the error type that occurred.
     pub code: ErrorCode,
@@ -121,11 +453,77 @@ pub struct Error<S, D> {
     /// The value of this member is defined by the Server (e.g. detailed error information, nested errors etc.).
     ///
     pub data: Option<D>,
+    #[serde(skip)]
+    source: Option<ErrorSource>,
+}
+
+impl<S, D> Error<S, D> {
+    /// Create an error without a source.
+    pub fn new(code: ErrorCode, message: S, data: Option<D>) -> Self {
+        Self {
+            code,
+            message,
+            data,
+            source: None,
+        }
+    }
+
+    /// Local error which caused this error, never transferred to the peer.
+    pub fn source(&self) -> Option<&ErrorSource> {
+        self.source.as_ref()
+    }
+
+    /// Keep `source` as the local error which caused this error.
+    pub fn with_source<E>(mut self, source: E) -> Self
+    where
+        E: core::error::Error + Send + Sync + 'static,
+    {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
+    /// Same as [`with_source`](Self::with_source) for an already shared source.
+    #[cfg(feature = "std")]
+    pub(crate) fn with_shared_source(mut self, source: ErrorSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+}
+
+impl<S, D> core::error::Error for Error<S, D>
+where
+    S: Debug,
+    D: Debug,
+    Self: Display,
+{
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.source.as_deref().map(|err| err as _)
+    }
+}
+
+impl<S: PartialEq, D: PartialEq> PartialEq for Error<S, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code && self.message == other.message && self.data == other.data
+    }
 }
 
-impl Display for Error<String, serde_json::Value> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "RPCError({}) {}", self.code, self.message)
+/// Formats the code and message, followed by the `data` as JSON when present.
+impl<S: AsRef<str>, D: Serialize> Display for Error<S, D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "RPCError({}) {}", self.code, self.message.as_ref())?;
+
+        match self.data.as_ref().map(serde_json::to_string) {
+            Some(Ok(data)) => write!(f, ", data: {}", data),
+            Some(Err(_)) => f.write_str(", data: <unserializable>"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<S: AsRef<str>, D> Error<S, D> {
+    /// The error message, whichever string type the error was decoded with.
+    pub fn message(&self) -> &str {
+        self.message.as_ref()
     }
 }
 
@@ -135,44 +533,148 @@ impl From<serde_json::Error> for Error<String, serde_json::Value> {
             code: ErrorCode::ParseError,
             message: format!("Serialize/Deserialize json data error: {}", err),
             data: None,
+            source: Some(Arc::new(err)),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl From<CompleteQError> for Error<String, serde_json::Value> {
     fn from(err: CompleteQError) -> Self {
+        let code = match err {
+            CompleteQError::Timeout => ErrorCode::Timeout,
+            _ => ErrorCode::InternalError,
+        };
+
         Self {
-            code: ErrorCode::InternalError,
+            code,
             message: format!("RPC call channel broken: {}", err),
             data: None,
+            source: Some(Arc::new(err)),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl From<SendError> for Error<String, serde_json::Value> {
     fn from(err: SendError) -> Self {
+        let code = if err.is_disconnected() {
+            ErrorCode::ConnectionClosed
+        } else {
+            ErrorCode::InternalError
+        };
+
         Self {
-            code: ErrorCode::InternalError,
+            code,
             message: format!("RPC send channel broken: {}", err),
             data: None,
+            source: Some(Arc::new(err)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error<String, serde_json::Value> {
+    fn from(err: std::io::Error) -> Self {
+        Self {
+            code: ErrorCode::InternalError,
+            message: format!("IO error({:?}): {}", err.kind(), err),
+            data: None,
+            source: Some(Arc::new(err)),
         }
     }
 }
 
 impl Error<String, serde_json::Value> {
+    /// Create an [`ErrorCode::InternalError`] from any displayable error, see [`map_error`].
     pub fn from_std_error<E>(e: E) -> Self
     where
         E: Display,
+    {
+        Self::new(
+            ErrorCode::InternalError,
+            format!("Unknown error: {}", e),
+            None,
+        )
+    }
+
+    /// Create an [`ErrorCode::InternalError`] from an error of a custom
+    /// [`TransportChannel`](crate::channel::TransportChannel), keeping it as the error source.
+    pub fn from_transport<E>(err: E) -> Self
+    where
+        E: core::error::Error + Send + Sync + 'static,
     {
         Self {
             code: ErrorCode::InternalError,
-            message: format!("Unknown error: {}", e),
+            message: format!("Transport error: {}", err),
             data: None,
+            source: Some(Arc::new(err)),
+        }
+    }
+
+    /// Create an implementation-defined server error with structured `data`.
+    ///
+    /// The `code` SHOULD be within the reserved server error range `-32099..=-32000`.
+    pub fn server_error<M>(code: i64, message: M, data: serde_json::Value) -> Self
+    where
+        M: Into<String>,
+    {
+        let message = message.into();
+
+        Self::new(
+            ErrorCode::ServerError(code, message.clone()),
+            message,
+            Some(data),
+        )
+    }
+}
+
+impl<S> Error<S, serde_json::Value> {
+    /// Deserialize the error `data` as a `D`, e.g. the typed payload of an error returned
+    /// by [`Client::call`](crate::Client::call).
+    ///
+    /// If the data isn't a valid `D` it's dropped, the deserialize error becomes the source.
+    pub fn typed<D>(self) -> Error<S, D>
+    where
+        D: de::DeserializeOwned,
+    {
+        let (data, source) = match self.data.map(serde_json::from_value).transpose() {
+            Ok(data) => (data, self.source),
+            Err(err) => (None, Some(Arc::new(err) as ErrorSource)),
+        };
+
+        Error {
+            code: self.code,
+            message: self.message,
+            data,
+            source,
+        }
+    }
+}
+
+impl<S, D: Serialize> Error<S, D> {
+    /// Serialize the typed error `data`, e.g. to return a typed error from a handler.
+    ///
+    /// If the data can't be serialized it's dropped, the serialize error becomes the source.
+    pub fn untyped(self) -> Error<S, serde_json::Value> {
+        let (data, source) = match self.data.as_ref().map(serde_json::to_value).transpose() {
+            Ok(data) => (data, self.source),
+            Err(err) => (None, Some(Arc::new(err) as ErrorSource)),
+        };
+
+        Error {
+            code: self.code,
+            message: self.message,
+            data,
+            source,
         }
     }
 }
 
 /// Maping other error type to JSONRPC [`Error`]
+///
+/// Stream and sink errors of [`TransportChannel`](crate::channel::TransportChannel)s are
+/// converted by this function, use [`Error::from_transport`] to also keep the error source.
 pub fn map_error<E>(err: E) -> Error<String, serde_json::Value>
 where
     E: Display,
@@ -184,24 +686,96 @@ where
 /// Any code within this range, but not defined explicitly below is reserved for future use.
 /// The error codes are nearly the same as those suggested for XML-RPC at the following url:
 /// <http://xmlrpc-epi.sourceforge.net/specs/rfc.fault_codes.php>
-#[derive(thiserror::Error, Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
 pub enum ErrorCode {
     /// An error occurred on the server while parsing the JSON text.
-    #[error("Invalid JSON was received by the server.")]
     ParseError,
-    #[error("The JSON sent is not a valid Request object.")]
     InvalidRequest,
-    #[error("The method does not exist / is not available.")]
     MethodNotFound,
-    #[error("Invalid method parameter(s).")]
     InvalidParams,
-    #[error("Internal JSON-RPC error.")]
     InternalError,
+    /// Local error, the call didn't complete in time.
+    ///
+    /// Never decoded from a peer's error, whose code -32001 is an [`ErrorCode::ServerError`].
+    Timeout,
+    /// Local error, the connection closed before the call completed.
+    ///
+    /// Pending calls of a connection which failed carry the transport error as their
+    /// [`Error::source`], those of a connection closed by the peer have none. Never
+    /// decoded from a peer's error, whose code -32002 is an [`ErrorCode::ServerError`].
+    ConnectionClosed,
+    /// Local error, the call was rejected as [`ClientConfig::max_inflight`](crate::ClientConfig::max_inflight)
+    /// calls are pending, see [`InflightOverflow::Reject`](crate::InflightOverflow::Reject).
+    ///
+    /// Never decoded from a peer's error, whose code -32004 is an [`ErrorCode::ServerError`].
+    TooManyInflight,
+    /// Local error, the call was rejected as [`ClientConfig::high_watermark`](crate::ClientConfig::high_watermark)
+    /// frames are queued for sending.
+    ///
+    /// Never decoded from a peer's error, whose code -32005 is an [`ErrorCode::ServerError`].
+    Backpressure,
+    /// Local error, the call was rejected as every request id up to
+    /// [`ClientConfig::max_request_id`](crate::ClientConfig::max_request_id) is in use.
+    ///
+    /// Never decoded from a peer's error, whose code -32006 is an [`ErrorCode::ServerError`].
+    RequestIdsExhausted,
     /// Reserved for implementation-defined server-errors.
-    #[error("Server error({0}),{1}")]
     ServerError(i64, String),
+    /// Code within the range reserved by the specification, but neither pre-defined
+    /// nor a server error, kept as is.
+    Reserved(i64),
+    /// Application-defined error, any code outside the range reserved by the specification.
+    ///
+    /// Constructed with a code within the reserved range it's still written as that code,
+    /// but decoded as the variant of the range, see [`ErrorCode::from_code`].
+    Application(i64),
 }
 
+impl ErrorCode {
+    /// Variant of the numeric `code`, as a peer's error code is decoded.
+    ///
+    /// Pre-defined codes map to their variant, other codes from -32099 to -32000 to
+    /// [`ErrorCode::ServerError`], the rest of the reserved range from -32768 to -32000
+    /// to [`ErrorCode::Reserved`] and any other code to [`ErrorCode::Application`].
+    /// Local errors are never returned.
+    pub fn from_code(code: i64) -> Self {
+        match code {
+            -32700 => ErrorCode::ParseError,
+            -32600 => ErrorCode::InvalidRequest,
+            -32601 => ErrorCode::MethodNotFound,
+            -32602 => ErrorCode::InvalidParams,
+            -32603 => ErrorCode::InternalError,
+            // Reserved implementation-defined server-errors range.
+            -32099..=-32000 => ErrorCode::ServerError(code, "".to_owned()),
+            -32768..=-32000 => ErrorCode::Reserved(code),
+            _ => ErrorCode::Application(code),
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ParseError => f.write_str("Invalid JSON was received by the server."),
+            Self::InvalidRequest => f.write_str("The JSON sent is not a valid Request object."),
+            Self::MethodNotFound => f.write_str("The method does not exist / is not available."),
+            Self::InvalidParams => f.write_str("Invalid method parameter(s)."),
+            Self::InternalError => f.write_str("Internal JSON-RPC error."),
+            Self::Timeout => f.write_str("Request timed out."),
+            Self::ConnectionClosed => f.write_str("Connection closed."),
+            Self::TooManyInflight => f.write_str("Too many in-flight calls."),
+            Self::Backpressure => f.write_str("Too many frames queued for sending."),
+            Self::RequestIdsExhausted => f.write_str("Request ids exhausted."),
+            Self::ServerError(code, message) => write!(f, "Server error({}),{}", code, message),
+            Self::Reserved(code) => write!(f, "Reserved error({})", code),
+            Self::Application(code) => write!(f, "Application error({})", code),
+        }
+    }
+}
+
+impl core::error::Error for ErrorCode {}
+
 impl serde::Serialize for ErrorCode {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -213,7 +787,14 @@ impl serde::Serialize for ErrorCode {
             Self::MethodNotFound => serializer.serialize_i64(-32601),
             Self::InvalidParams => serializer.serialize_i64(-32602),
             Self::InternalError => serializer.serialize_i64(-32603),
+            Self::Timeout => serializer.serialize_i64(-32001),
+            Self::ConnectionClosed => serializer.serialize_i64(-32002),
+            Self::TooManyInflight => serializer.serialize_i64(-32004),
+            Self::Backpressure => serializer.serialize_i64(-32005),
+            Self::RequestIdsExhausted => serializer.serialize_i64(-32006),
             Self::ServerError(code, _) => serializer.serialize_i64(*code),
+            Self::Reserved(code) => serializer.serialize_i64(*code),
+            Self::Application(code) => serializer.serialize_i64(*code),
         }
     }
 }
@@ -223,40 +804,103 @@ impl<'de> serde::Deserialize<'de> for ErrorCode {
     where
         D: Deserializer<'de>,
     {
-        let code = deserializer.deserialize_i64(visitor::ErrorCodeVisitor)?;
-
-        match code {
-            -32700 => Ok(ErrorCode::ParseError),
-            -32600 => Ok(ErrorCode::InvalidRequest),
-            -32601 => Ok(ErrorCode::MethodNotFound),
-            -32602 => Ok(ErrorCode::InvalidParams),
-            -32603 => Ok(ErrorCode::InternalError),
-            _ => {
-                // Check reserved implementation-defined server-errors range.
-                if code <= -32000 && code >= -32099 {
-                    Ok(ErrorCode::ServerError(code, "".to_owned()))
-                } else {
-                    Err(anyhow::format_err!("Invalid JSONRPC error code {}", code))
-                        .map_err(serde::de::Error::custom)
-                }
-            }
-        }
+        deserializer
+            .deserialize_i64(visitor::ErrorCodeVisitor)
+            .map(ErrorCode::from_code)
     }
 }
 
 mod visitor {
+    use alloc::borrow::Cow;
+    use alloc::string::String;
+    use core::fmt;
     use serde::de;
-    use std::fmt;
 
     use crate::Version;
 
+    #[cfg(feature = "std")]
+    pub(crate) struct MapVisitor<T>(pub(crate) core::marker::PhantomData<T>);
+
+    #[cfg(feature = "std")]
+    impl<'de, T: de::Deserialize<'de>> de::Visitor<'de> for MapVisitor<T> {
+        type Value = T;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map")
+        }
+
+        fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+        where
+            A: de::MapAccess<'de>,
+        {
+            T::deserialize(de::value::MapAccessDeserializer::new(map))
+        }
+    }
+
+    pub struct CowStrVisitor;
+
+    impl<'de> de::Visitor<'de> for CowStrVisitor {
+        type Value = Cow<'de, str>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a string")
+        }
+
+        fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(Cow::Borrowed(v))
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(Cow::Owned(String::from(v)))
+        }
+
+        fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(Cow::Owned(v))
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) struct CompatVersionVisitor;
+
+    #[cfg(feature = "std")]
+    impl<'de> de::Visitor<'de> for CompatVersionVisitor {
+        type Value = super::CompatVersion;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("Version string 1.0 or 2.0")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            match v {
+                "1.0" => Ok(super::CompatVersion(Some(super::LegacyVersion::Tagged))),
+                "2.0" => Ok(super::CompatVersion(None)),
+                _ => Err(de::Error::custom(format_args!(
+                    "Version string MUST be 1.0 or 2.0, but got `{}`",
+                    v
+                ))),
+            }
+        }
+    }
+
     pub struct ErrorCodeVisitor;
 
     impl<'de> de::Visitor<'de> for ErrorCodeVisitor {
         type Value = i64;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("Version string MUST be exactly 2.0")
+            formatter.write_str("an error code integer between -2^63 and 2^63")
         }
 
         fn visit_i8<E>(self, value: i8) -> Result<Self::Value, E>
@@ -266,6 +910,13 @@ mod visitor {
             Ok(i64::from(value))
         }
 
+        fn visit_i16<E>(self, value: i16) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(i64::from(value))
+        }
+
         fn visit_i32<E>(self, value: i32) -> Result<Self::Value, E>
         where
             E: de::Error,
@@ -279,6 +930,52 @@ mod visitor {
         {
             Ok(value)
         }
+
+        fn visit_i128<E>(self, value: i128) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            i64::try_from(value)
+                .map_err(|_| de::Error::invalid_value(de::Unexpected::Other("i128"), &self))
+        }
+
+        // Positive application-defined codes.
+        fn visit_u8<E>(self, value: u8) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(i64::from(value))
+        }
+
+        fn visit_u16<E>(self, value: u16) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(i64::from(value))
+        }
+
+        fn visit_u32<E>(self, value: u32) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(i64::from(value))
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            i64::try_from(value)
+                .map_err(|_| de::Error::invalid_value(de::Unexpected::Unsigned(value), &self))
+        }
+
+        fn visit_u128<E>(self, value: u128) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            i64::try_from(value)
+                .map_err(|_| de::Error::invalid_value(de::Unexpected::Other("u128"), &self))
+        }
     }
 
     pub struct VersionVisitor;
@@ -287,7 +984,7 @@ mod visitor {
         type Value = Version;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("an integer between -2^63 and 2^63")
+            formatter.write_str("a version string which MUST be exactly 2.0")
         }
 
         fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
@@ -295,11 +992,10 @@ mod visitor {
             E: de::Error,
         {
             if v != "2.0" {
-                return Err(anyhow::format_err!(
+                return Err(de::Error::custom(format_args!(
                     "Version string MUST be exactly 2.0, but got `{}`",
                     v
-                ))
-                .map_err(serde::de::Error::custom);
+                )));
             }
 
             Ok(Version {})
@@ -310,11 +1006,10 @@ mod visitor {
             E: de::Error,
         {
             if v.as_str() != "2.0" {
-                return Err(anyhow::format_err!(
+                return Err(de::Error::custom(format_args!(
                     "Version string MUST be exactly 2.0, but got `{}`",
                     v
-                ))
-                .map_err(serde::de::Error::custom);
+                )));
             }
 
             Ok(Version {})
@@ -328,7 +1023,12 @@ mod tests {
     use serde::{Deserialize, Serialize};
     use serde_json::json;
 
-    use crate::Request;
+    #[cfg(not(feature = "extensions"))]
+    use alloc::borrow::Cow;
+
+    #[cfg(not(feature = "extensions"))]
+    use crate::BorrowedResponse;
+    use crate::{Error, ErrorCode, RPCError, Request, Response};
 
     #[test]
     fn test_array_params() {
@@ -371,6 +1071,16 @@ mod tests {
             format!("{}", request.unwrap_err()),
             "Version string MUST be exactly 2.0, but got `3.0`",
         );
+
+        // Borrowed version strings are checked by `visit_str`.
+        let request = serde_json::from_str::<Request<String, Params<String>>>(
+            r#"{"jsonrpc":"1.0", "method":"hello","params":[10, "world"]}"#,
+        );
+
+        assert_eq!(
+            format!("{}", request.unwrap_err()),
+            "Version string MUST be exactly 2.0, but got `1.0` at line 1 column 16",
+        );
     }
 
     #[test]
@@ -421,4 +1131,326 @@ mod tests {
         assert_eq!(request.params.id, 20);
         assert_eq!(request.params.name, "hello");
     }
+
+    #[cfg(feature = "extensions")]
+    #[test]
+    fn test_extensions() {
+        let value =
+            json!({"jsonrpc":"2.0", "id":1, "method":"hello", "params":[], "trace":{"id":"x"}});
+
+        let request =
+            serde_json::from_value::<Request<String, serde_json::Value>>(value.clone()).unwrap();
+
+        assert_eq!(request.extensions.get("trace"), Some(&json!({"id":"x"})));
+        assert_eq!(serde_json::to_value(&request).unwrap(), value);
+
+        let value = json!({"jsonrpc":"2.0", "id":1, "result":true, "node":"a"});
+
+        let response = serde_json::from_value::<Response<String, bool, ()>>(value.clone()).unwrap();
+
+        assert_eq!(response.extensions.get("node"), Some(&json!("a")));
+        assert_eq!(serde_json::to_value(&response).unwrap(), value);
+    }
+
+    #[test]
+    fn test_application_error_code() {
+        for code in [-1, 0, 42, -31999, -32769] {
+            let value = serde_json::from_value::<ErrorCode>(json!(code)).unwrap();
+
+            assert_eq!(value, ErrorCode::Application(code));
+            assert_eq!(serde_json::to_value(&value).unwrap(), json!(code));
+        }
+
+        // Undefined codes of the reserved range aren't application errors.
+        for code in [-32100, -32604, -32768] {
+            let value = serde_json::from_value::<ErrorCode>(json!(code)).unwrap();
+
+            assert_eq!(value, ErrorCode::Reserved(code));
+            assert_eq!(serde_json::to_value(&value).unwrap(), json!(code));
+        }
+
+        assert_eq!(
+            serde_json::from_value::<ErrorCode>(json!(-32050)).unwrap(),
+            ErrorCode::ServerError(-32050, "".to_owned())
+        );
+
+        // Codes of the reserved range decode as the variant of the range.
+        let value = serde_json::to_value(ErrorCode::Application(-32600)).unwrap();
+
+        assert_eq!(
+            serde_json::from_value::<ErrorCode>(value).unwrap(),
+            ErrorCode::InvalidRequest
+        );
+        assert_eq!(ErrorCode::from_code(-32600), ErrorCode::InvalidRequest);
+    }
+
+    #[test]
+    fn test_local_error_codes() {
+        // Codes of local errors received from a peer are plain server errors.
+        for code in [-32001, -32002, -32004, -32005, -32006] {
+            let err = RPCError::server_error(code, "remote", json!(null));
+
+            let response: Response<String, (), serde_json::Value> = serde_json::from_slice(
+                &serde_json::to_vec(&Response::<String, (), _>::error(1, err)).unwrap(),
+            )
+            .unwrap();
+
+            let err = response.error.unwrap();
+
+            assert_eq!(err.code, ErrorCode::ServerError(code, "".to_owned()));
+            assert_eq!(err.message, "remote");
+        }
+
+        // Local errors keep their code on the wire.
+        assert_eq!(
+            serde_json::to_value(ErrorCode::Timeout).unwrap(),
+            json!(-32001)
+        );
+        assert_eq!(
+            serde_json::to_value(ErrorCode::ConnectionClosed).unwrap(),
+            json!(-32002)
+        );
+        assert_eq!(
+            serde_json::to_value(ErrorCode::TooManyInflight).unwrap(),
+            json!(-32004)
+        );
+        assert_eq!(
+            serde_json::to_value(ErrorCode::Backpressure).unwrap(),
+            json!(-32005)
+        );
+        assert_eq!(
+            serde_json::to_value(ErrorCode::RequestIdsExhausted).unwrap(),
+            json!(-32006)
+        );
+    }
+
+    #[test]
+    fn test_error_code_integer_types() {
+        use serde::de::{value::Error, IntoDeserializer};
+
+        fn decode<'de, D: IntoDeserializer<'de, Error>>(code: D) -> Result<ErrorCode, Error> {
+            ErrorCode::deserialize(code.into_deserializer())
+        }
+
+        assert_eq!(decode(-32601i16).unwrap(), ErrorCode::MethodNotFound);
+        assert_eq!(decode(-32601i128).unwrap(), ErrorCode::MethodNotFound);
+        assert_eq!(decode(42u8).unwrap(), ErrorCode::Application(42));
+        assert_eq!(decode(42u16).unwrap(), ErrorCode::Application(42));
+        assert_eq!(decode(42u32).unwrap(), ErrorCode::Application(42));
+        assert_eq!(decode(42u64).unwrap(), ErrorCode::Application(42));
+        assert_eq!(decode(42u128).unwrap(), ErrorCode::Application(42));
+
+        assert!(decode(u64::MAX).is_err());
+        assert!(decode(i128::MIN).is_err());
+        assert!(decode(u128::MAX).is_err());
+    }
+
+    #[test]
+    fn test_error_display() {
+        let err = RPCError::new(ErrorCode::InvalidParams, "bad amount".to_owned(), None);
+
+        assert_eq!(
+            err.to_string(),
+            "RPCError(Invalid method parameter(s).) bad amount"
+        );
+        assert_eq!(err.message(), "bad amount");
+
+        let err = RPCError {
+            data: Some(json!({"min": 1})),
+            ..err
+        };
+
+        assert_eq!(
+            err.to_string(),
+            r#"RPCError(Invalid method parameter(s).) bad amount, data: {"min":1}"#
+        );
+
+        let err: Error<&str, u32> = Error::new(ErrorCode::Application(7), "borrowed", Some(3));
+
+        assert_eq!(
+            err.to_string(),
+            "RPCError(Application error(7)) borrowed, data: 3"
+        );
+        assert_eq!(err.message(), "borrowed");
+    }
+
+    #[test]
+    fn test_expecting_messages() {
+        let err = serde_json::from_value::<ErrorCode>(json!("oops")).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "invalid type: string \"oops\", expected an error code integer between -2^63 and 2^63"
+        );
+
+        let err = serde_json::from_value::<Request<String, ()>>(
+            json!({"jsonrpc": 2, "method": "hello", "params": null}),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "invalid type: integer `2`, expected a version string which MUST be exactly 2.0"
+        );
+    }
+
+    #[test]
+    fn test_constructors() {
+        assert_eq!(
+            serde_json::to_value(Request::call(1, "add", [1, 2])).unwrap(),
+            json!({"jsonrpc":"2.0", "id":1, "method":"add", "params":[1, 2]})
+        );
+
+        assert_eq!(
+            serde_json::to_value(Request::notification("ping", ())).unwrap(),
+            json!({"jsonrpc":"2.0", "method":"ping", "params":null})
+        );
+
+        assert_eq!(
+            serde_json::to_value(Response::<String, i32, ()>::success(1, 3)).unwrap(),
+            json!({"jsonrpc":"2.0", "id":1, "result":3})
+        );
+
+        let err = Response::<String, (), ()>::error(
+            2,
+            crate::Error::new(
+                ErrorCode::MethodNotFound,
+                "Method add not found".to_owned(),
+                None,
+            ),
+        );
+
+        assert_eq!(
+            serde_json::to_value(err).unwrap(),
+            json!({"jsonrpc":"2.0", "id":2, "error":{"code":-32601, "message":"Method add not found", "data":null}})
+        );
+    }
+
+    #[test]
+    fn test_invalid_response() {
+        let neither = Response::<String, i32, ()>::default();
+
+        assert_eq!(
+            serde_json::to_value(neither).unwrap_err().to_string(),
+            "Response 0 MUST contain exactly one of result and error"
+        );
+
+        let mut both = Response::<String, i32, ()>::success(1, 3);
+
+        both.error = Some(crate::Error::new(
+            ErrorCode::InternalError,
+            "failed".to_owned(),
+            None,
+        ));
+
+        assert!(serde_json::to_value(both).is_err());
+
+        // A null result is a result.
+        assert_eq!(
+            serde_json::to_value(Response::<String, _, ()>::success(1, ())).unwrap(),
+            json!({"jsonrpc":"2.0", "id":1, "result":null})
+        );
+    }
+
+    #[test]
+    fn test_error_source() {
+        let err = serde_json::from_str::<i32>("x").unwrap_err();
+
+        let rpc_error: RPCError = err.into();
+
+        let source = std::error::Error::source(&rpc_error).expect("error source");
+
+        assert!(source.is::<serde_json::Error>());
+
+        let err = std::io::Error::new(std::io::ErrorKind::BrokenPipe, "pipe closed");
+
+        let rpc_error = RPCError::from_transport(err);
+
+        assert_eq!(rpc_error.code, ErrorCode::InternalError);
+        assert_eq!(rpc_error.message, "Transport error: pipe closed");
+
+        let source = std::error::Error::source(&rpc_error).expect("error source");
+
+        assert!(source.is::<std::io::Error>());
+
+        let err = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset by peer");
+
+        let rpc_error: RPCError = err.into();
+
+        assert_eq!(rpc_error.code, ErrorCode::InternalError);
+        assert_eq!(
+            rpc_error.message,
+            "IO error(ConnectionReset): reset by peer"
+        );
+
+        let source = std::error::Error::source(&rpc_error).expect("error source");
+
+        assert_eq!(
+            source
+                .downcast_ref::<std::io::Error>()
+                .map(|err| err.kind()),
+            Some(std::io::ErrorKind::ConnectionReset)
+        );
+    }
+
+    #[test]
+    fn test_null_result() {
+        let response = serde_json::from_value::<Response<String, serde_json::Value, ()>>(
+            json!({"jsonrpc":"2.0", "id": 1, "result": null}),
+        )
+        .expect("parse null result");
+
+        assert_eq!(response.result, Some(serde_json::Value::Null));
+
+        let response = serde_json::from_value::<Response<String, serde_json::Value, ()>>(
+            json!({"jsonrpc":"2.0", "id": 1}),
+        )
+        .expect("parse missing result");
+
+        assert_eq!(response.result, None);
+    }
+
+    #[cfg(not(feature = "extensions"))]
+    #[test]
+    fn test_borrowed_response() {
+        let data = br#"{"jsonrpc":"2.0","id":1,"result":{"values":[1,2,3]}}"#;
+
+        let response = serde_json::from_slice::<BorrowedResponse>(data).expect("parse result");
+
+        assert_eq!(response.id, 1);
+        assert_eq!(
+            response.result.map(|result| result.get()),
+            Some(r#"{"values":[1,2,3]}"#)
+        );
+
+        let data = br#"{"jsonrpc":"2.0","id":2,"error":{"code":-32601,"message":"Method not found","data":[1]}}"#;
+
+        let response = serde_json::from_slice::<BorrowedResponse>(data).expect("parse error");
+
+        let error = response.error.expect("error");
+
+        assert_eq!(error.code, ErrorCode::MethodNotFound);
+        assert!(matches!(
+            error.message.into_inner(),
+            Cow::Borrowed("Method not found")
+        ));
+        assert_eq!(error.data.map(|data| data.get()), Some("[1]"));
+
+        // A null result is still a result.
+        let data = br#"{"jsonrpc":"2.0","id":3,"result":null}"#;
+
+        let response = serde_json::from_slice::<BorrowedResponse>(data).expect("parse null");
+
+        assert_eq!(response.result.map(|result| result.get()), Some("null"));
+
+        // Escaped messages are copied.
+        let data = br#"{"jsonrpc":"2.0","id":4,"error":{"code":-32000,"message":"\"quoted\"\n"}}"#;
+
+        let response = serde_json::from_slice::<BorrowedResponse>(data).expect("parse escaped");
+
+        assert_eq!(
+            response.error.expect("error").message.as_ref(),
+            "\"quoted\"\n"
+        );
+    }
 }
@@ -0,0 +1,17 @@
+//! Common types in one glob import.
+//!
+//! ```
+//! use jsonrpc_rs::prelude::*;
+//!
+//! let mut server = Server::default();
+//!
+//! server.handle("echo", |msg: String| RPCResult::Ok(Some(msg)));
+//! ```
+
+pub use crate::{Error, ErrorCode, RPCError, RPCResult, Request, Response};
+
+#[cfg(feature = "std")]
+pub use crate::{
+    channel::{RPCData, TransportChannel},
+    Client, Server,
+};
@@ -1,4 +1,8 @@
+use alloc::string::String;
+
 use crate::Error;
 
-pub type RPCResult<T> = Result<T, Error<String, serde_json::Value>>;
-pub type RPCError = Error<String, serde_json::Value>;
+/// Result of a JSONRPC call, whose error `data` is a `D`.
+pub type RPCResult<T, D = serde_json::Value> = Result<T, Error<String, D>>;
+/// Error of a JSONRPC call, whose `data` is a `D`, see [`Error::typed`].
+pub type RPCError<D = serde_json::Value> = Error<String, D>;
@@ -1,79 +1,1033 @@
+mod builder;
+pub use builder::*;
+mod cancel;
+pub use cancel::*;
+mod context;
+pub use context::*;
+#[cfg(feature = "discover")]
+mod discover;
 mod handler;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use async_lock::Semaphore;
+use futures::future::{select, Either};
 
 use handler::*;
 
+mod middleware;
+pub use middleware::{Call, CallResult, Middleware, Next};
+mod namespace;
+pub use namespace::Namespace;
+mod notifier;
+pub use notifier::Notifier;
+mod rate_limit;
+use rate_limit::{rate_limited, try_acquire_session, TokenBucket};
 mod session;
-use session::ServiceSession;
+use session::{ServiceSession, SessionHandle};
+mod stream;
+use stream::forward_stream;
+pub use stream::StreamParams;
+mod subscription;
+use subscription::SessionSubscriptions;
+pub use subscription::{SubscriptionParams, SubscriptionSink};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{channel::TransportChannel, RPCResult};
+use crate::{
+    channel::{RPCData, TransportChannel},
+    executor::spawn_abortable,
+    timer, Codec, ErrorCode, Executor, JsonCodec, Metrics, RPCError, RPCResult,
+};
+
+/// Error code of [`ErrorCode::ServerError`], returned when a handler registered by
+/// [`Server::handle_with_timeout`] runs out of time.
+///
+/// Distinct from -32001, the code of a client's own [`ErrorCode::Timeout`], so callers
+/// can tell a handler timeout from a call which timed out locally.
+pub const HANDLER_TIMEOUT: i64 = -32007;
+
+/// Error code of [`ErrorCode::ServerError`], returned when a call exceeds the rate limit
+/// set by [`Server::handle_with_rate_limit`] or [`Server::handle_with_session_rate_limit`].
+pub const RATE_LIMITED: i64 = -32003;
+
+/// Method of keepalive pings, see [`ClientConfig::keepalive`](crate::ClientConfig::keepalive).
+pub const PING_METHOD: &str = "rpc.ping";
+
+/// Method of the notification canceling an in-flight call, with the id of the call as
+/// its only param, see [`Context::cancellation`].
+pub const CANCEL_METHOD: &str = "rpc.cancel";
+
+/// Method of the built-in OpenRPC service discovery, answered by `Server::handle_discover`
+/// with the `discover` feature.
+pub const DISCOVER_METHOD: &str = "rpc.discover";
+
+/// How a session handles a failed write to its transport, see [`ServerBuilder::on_send_error`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SendErrorPolicy {
+    /// End the session, canceling its pending calls.
+    #[default]
+    Abort,
+    /// Log the error and drop the frame, the session keeps serving.
+    Continue,
+    /// Write the frame again up to `n` times, then end the session like [`SendErrorPolicy::Abort`].
+    Retry(usize),
+}
+
+/// Error returned by [`Server::try_handle`] and [`Server::try_async_handle`].
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum RegisterError {
+    /// A handler of the method is registered already.
+    #[error("Method {0} already registered")]
+    AlreadyRegistered(String),
+    /// The method belongs to the reserved `rpc.` namespace, see [`ServerBuilder::allow_reserved`].
+    #[error("Method {0} rejected, the rpc. prefix is reserved")]
+    Reserved(String),
+}
 
 /// JSONRPC server context structure.
 ///
-#[derive(Default, Clone)]
-pub struct Server {
+/// Its configuration is set by [`ServerBuilder`] and fixed once built, handlers
+/// may still be registered and removed at runtime.
+#[derive(Clone)]
+pub struct Server<K: Codec = JsonCodec> {
     tag: String,
-    methods: HandlerClonerRegister<ServerHandler>,
-    async_methods: HandlerClonerRegister<AsyncServerHandler>,
+    methods: HandlerClonerRegister<SyncMethodHandler<K>>,
+    async_methods: HandlerClonerRegister<AsyncMethodHandler<K>>,
+    lowercase: LowercaseIndex,
+    states: Extensions,
+    ordered_responses: bool,
+    lenient_version: bool,
+    compat_v1: bool,
+    case_insensitive: bool,
+    allow_reserved: bool,
+    max_frame_size: Option<usize>,
+    idle_timeout: Option<Duration>,
+    on_send_error: SendErrorPolicy,
+    coalesce_responses: Option<usize>,
+    default_handler: Option<DefaultHandler<K>>,
+    middlewares: Arc<Vec<Arc<dyn Middleware<K>>>>,
+    metrics: Option<Arc<dyn Metrics>>,
+    slow_call_threshold: Option<Duration>,
+    executor: Option<Arc<dyn Executor>>,
+    /// Sessions started by [`Server::accept`], shared by all clones of this server.
+    sessions: Arc<Mutex<HashMap<String, SessionHandle>>>,
+    #[cfg(feature = "discover")]
+    discover: discover::Discover,
+    codec: PhantomData<K>,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new("")
+    }
 }
 
 impl Server {
     pub fn new<S>(tag: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::with_codec(tag, JsonCodec)
+    }
+
+    /// Configure a new server and register its handlers, see [`ServerBuilder`].
+    pub fn builder<S>(tag: S) -> ServerBuilder
+    where
+        S: Into<String>,
+    {
+        ServerBuilder::new(tag)
+    }
+}
+
+impl<K: Codec> Server<K> {
+    /// Create a server exchanging frames encoded by `codec`.
+    pub fn with_codec<S>(tag: S, _codec: K) -> Self
     where
         S: Into<String>,
     {
         Self {
             tag: tag.into(),
-            ..Default::default()
+            methods: Default::default(),
+            async_methods: Default::default(),
+            lowercase: Default::default(),
+            states: Default::default(),
+            ordered_responses: false,
+            lenient_version: false,
+            compat_v1: false,
+            case_insensitive: false,
+            allow_reserved: false,
+            max_frame_size: None,
+            idle_timeout: None,
+            on_send_error: SendErrorPolicy::Abort,
+            coalesce_responses: None,
+            default_handler: None,
+            middlewares: Default::default(),
+            metrics: None,
+            slow_call_threshold: None,
+            executor: None,
+            sessions: Default::default(),
+            #[cfg(feature = "discover")]
+            discover: Default::default(),
+            codec: PhantomData,
+        }
+    }
+
+    /// See [`ServerBuilder::ordered_responses`].
+    pub(crate) fn ordered_responses(&mut self, ordered: bool) -> &mut Self {
+        self.ordered_responses = ordered;
+
+        self
+    }
+
+    /// See [`ServerBuilder::lenient_version`].
+    pub(crate) fn lenient_version(&mut self, lenient: bool) -> &mut Self {
+        self.lenient_version = lenient;
+
+        self
+    }
+
+    /// See [`ServerBuilder::compat_v1`].
+    pub(crate) fn compat_v1(&mut self, compat: bool) -> &mut Self {
+        self.compat_v1 = compat;
+
+        self
+    }
+
+    /// See [`ServerBuilder::case_insensitive`].
+    pub(crate) fn case_insensitive(&mut self, case_insensitive: bool) -> &mut Self {
+        self.case_insensitive = case_insensitive;
+
+        self
+    }
+
+    /// See [`ServerBuilder::allow_reserved`].
+    pub(crate) fn allow_reserved(&mut self, allow: bool) -> &mut Self {
+        self.allow_reserved = allow;
+
+        self
+    }
+
+    /// Returns true if registering `method` is rejected, see [`ServerBuilder::allow_reserved`].
+    fn is_reserved(&self, method: &str) -> bool {
+        const BUILTIN: [&str; 3] = [PING_METHOD, CANCEL_METHOD, DISCOVER_METHOD];
+
+        !self.allow_reserved && method.starts_with("rpc.") && !BUILTIN.contains(&method)
+    }
+
+    /// Returns true and logs an error if registering `method` is rejected,
+    /// see [`ServerBuilder::allow_reserved`].
+    fn skip_reserved(&self, method: &str) -> bool {
+        let reserved = self.is_reserved(method);
+
+        if reserved {
+            log::error!(
+                "{}, handler not registered",
+                RegisterError::Reserved(method.to_owned())
+            );
         }
+
+        reserved
+    }
+
+    /// See [`ServerBuilder::layer`].
+    pub(crate) fn layer<M: Middleware<K>>(&mut self, middleware: M) -> &mut Self {
+        Arc::make_mut(&mut self.middlewares).push(Arc::new(middleware));
+
+        self
     }
+
+    /// See [`ServerBuilder::metrics`].
+    pub(crate) fn metrics<M: Metrics>(&mut self, metrics: M) -> &mut Self {
+        self.metrics = Some(Arc::new(metrics));
+
+        self
+    }
+
+    /// See [`ServerBuilder::slow_call_threshold`].
+    pub(crate) fn slow_call_threshold(&mut self, threshold: Duration) -> &mut Self {
+        self.slow_call_threshold = Some(threshold);
+
+        self
+    }
+
+    /// See [`ServerBuilder::executor`].
+    pub(crate) fn executor<E: Executor>(&mut self, executor: E) -> &mut Self {
+        self.executor = Some(Arc::new(executor));
+
+        self
+    }
+
+    /// See [`ServerBuilder::max_frame_size`].
+    pub(crate) fn max_frame_size(&mut self, bytes: usize) -> &mut Self {
+        self.max_frame_size = Some(bytes);
+
+        self
+    }
+
+    /// See [`ServerBuilder::idle_timeout`].
+    pub(crate) fn idle_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.idle_timeout = Some(timeout);
+
+        self
+    }
+
+    /// See [`ServerBuilder::on_send_error`].
+    pub(crate) fn on_send_error(&mut self, policy: SendErrorPolicy) -> &mut Self {
+        self.on_send_error = policy;
+
+        self
+    }
+
+    /// See [`ServerBuilder::coalesce_responses`].
+    pub(crate) fn coalesce_responses(&mut self, max: usize) -> &mut Self {
+        self.coalesce_responses = Some(max).filter(|max| *max > 1);
+
+        self
+    }
+
+    /// Set the fallback handler for requests whose method isn't registered,
+    /// e.g. to forward them to an upstream service.
+    ///
+    /// The handler receives the method name, request id and params. Without a
+    /// fallback handler such requests are answered with [`ErrorCode::MethodNotFound`].
+    pub fn default_handler<R, F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&str, Option<usize>, serde_json::Value) -> RPCResult<Option<R>>
+            + 'static
+            + Sync
+            + Send,
+        R: Serialize,
+    {
+        self.async_default_handler(move |method, id, params| {
+            let result = f(method, id, params).and_then(|r| match r {
+                Some(r) => Ok(Some(serde_json::to_value(r)?)),
+                None => Ok(None),
+            });
+
+            futures::future::ready(result)
+        })
+    }
+
+    /// Async version of [`Server::default_handler`].
+    pub fn async_default_handler<F, FR>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&str, Option<usize>, serde_json::Value) -> FR + 'static + Sync + Send,
+        FR: std::future::Future<Output = RPCResult<Option<serde_json::Value>>> + Send + 'static,
+    {
+        self.default_handler = Some(to_default_handler::<K, _, _>(f));
+
+        self
+    }
+
+    /// See [`ServerBuilder::with_state`].
+    pub(crate) fn with_state<S>(&mut self, state: S) -> &mut Self
+    where
+        S: Send + Sync + 'static,
+    {
+        self.states.insert(Arc::new(state));
+
+        self
+    }
+
+    /// Names of all registered methods, in alphabetical order.
+    pub fn methods(&self) -> impl Iterator<Item = String> {
+        let mut names = self.methods.names();
+
+        names.append(&mut self.async_methods.names());
+
+        names.sort();
+
+        names.into_iter()
+    }
+
+    /// Returns true if `method` has a registered handler.
+    pub fn has_method(&self, method: &str) -> bool {
+        self.methods.contains(method) || self.async_methods.contains(method)
+    }
+
+    /// Registered name of `method` sent with another casing, see [`ServerBuilder::case_insensitive`].
+    pub(crate) fn resolve_method(&self, method: &str) -> Option<String> {
+        if !self.case_insensitive || self.has_method(method) {
+            return None;
+        }
+
+        let lowercase = method.to_lowercase();
+
+        if lowercase.starts_with("rpc.") {
+            return None;
+        }
+
+        self.lowercase.get(&lowercase)
+    }
+
+    /// Unregister `method`, returns true if it was registered.
+    ///
+    /// Running sessions share the method register, so the method becomes unavailable
+    /// to them as well starting with their next request.
+    pub fn remove_method(&self, method: &str) -> bool {
+        self.lowercase.remove(method);
+
+        self.methods.remove(method) | self.async_methods.remove(method)
+    }
+
+    /// Registrar of the methods of the namespace `name`, registered as `{name}.{method}`.
+    ///
+    /// ```ignore
+    /// server
+    ///     .namespace("wallet")
+    ///     .handle("balance", |account: String| Ok(Some(balance(&account))));
+    /// ```
+    pub fn namespace(&mut self, name: &str) -> Namespace<'_, K> {
+        Namespace::new(self, name.to_owned())
+    }
+
     /// Register jsonrpc server sync handler
-    pub fn handle<P, R, F>(&mut self, method: &'static str, f: F) -> &mut Self
+    ///
+    /// Registering an already registered method replaces the previous handler,
+    /// use [`Server::try_handle`] to reject duplicated registration instead.
+    /// A reserved method isn't registered, which is logged as an error, see
+    /// [`ServerBuilder::allow_reserved`].
+    ///
+    /// The `data` of a returned error is sent to the caller, convert a typed error
+    /// with [`RPCError::untyped`].
+    pub fn handle<P, R, F>(&mut self, method: &'static str, mut f: F) -> &mut Self
     where
         F: FnMut(P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
         for<'a> P: Deserialize<'a> + Serialize,
         R: Serialize + Default,
     {
-        self.methods.register_handler(method, to_handler(method, f));
+        self.handle_with_ctx(method, move |_, params| f(params))
+    }
+
+    /// Register jsonrpc server sync handler without params.
+    ///
+    /// The request `params` may be omitted, `null`, `[]` or `{}`.
+    pub fn handle0<R, F>(&mut self, method: &'static str, mut f: F) -> &mut Self
+    where
+        F: FnMut() -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        R: Serialize + Default,
+    {
+        self.handle(method, move |_: NoParams| f())
+    }
+
+    /// Register jsonrpc server sync handler, which also receives the call [`Context`].
+    pub fn handle_with_ctx<P, R, F>(&mut self, method: &'static str, f: F) -> &mut Self
+    where
+        F: FnMut(Context, P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        for<'a> P: Deserialize<'a> + Serialize,
+        R: Serialize + Default,
+    {
+        self.register_sync(method.to_owned(), f)
+    }
+
+    /// [`Server::handle_with_ctx`] of a method whose name is built at runtime.
+    pub(crate) fn register_sync<P, R, F>(&mut self, method: String, f: F) -> &mut Self
+    where
+        F: FnMut(Context, P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        for<'a> P: Deserialize<'a> + Serialize,
+        R: Serialize + Default,
+    {
+        if self.skip_reserved(&method) {
+            return self;
+        }
+
+        let replaced = self.async_methods.remove(&method)
+            | self
+                .methods
+                .register_handler(&method, to_handler::<K, _, _, _>(method.as_str().into(), f));
+
+        if replaced {
+            log::warn!("Method {} handler replaced", method);
+        }
+
+        self.lowercase.insert(&method);
+
+        self
+    }
+
+    /// Register jsonrpc server sync handler shared by all calls of `method`.
+    ///
+    /// Unlike [`Server::handle`], which clones its `FnMut` handler for every call,
+    /// the `Fn` handler is stored once and called by reference, so it needn't be
+    /// [`Clone`]. Prefer it for handlers reading shared state they capture.
+    pub fn handle_shared<P, R, F>(&mut self, method: &'static str, f: F) -> &mut Self
+    where
+        F: Fn(P) -> RPCResult<Option<R>> + 'static + Sync + Send,
+        for<'a> P: Deserialize<'a> + Serialize,
+        R: Serialize + Default,
+    {
+        if self.skip_reserved(method) {
+            return self;
+        }
+
+        let handler = to_shared_handler::<K, _, _, _>(method.into(), move |_, params| f(params));
+
+        let replaced =
+            self.async_methods.remove(method) | self.methods.register_handler(method, handler);
+
+        if replaced {
+            log::warn!("Method {} handler replaced", method);
+        }
+
+        self.lowercase.insert(method);
 
         self
     }
 
+    /// Register jsonrpc server sync handler, failing if `method` is already registered
+    /// or reserved, see [`ServerBuilder::allow_reserved`].
+    pub fn try_handle<P, R, F>(
+        &mut self,
+        method: &'static str,
+        f: F,
+    ) -> Result<&mut Self, RegisterError>
+    where
+        F: FnMut(P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        for<'a> P: Deserialize<'a> + Serialize,
+        R: Serialize + Default,
+    {
+        if self.is_reserved(method) {
+            return Err(RegisterError::Reserved(method.to_owned()));
+        }
+
+        if self.has_method(method) {
+            return Err(RegisterError::AlreadyRegistered(method.to_owned()));
+        }
+
+        Ok(self.handle(method, f))
+    }
+
     /// Register jsonrpc server async handler
     ///
     /// The register async handler be required to implement [`Clone`] trait.
     ///
-    ///
-    pub fn async_handle<P, R, F, FR>(&mut self, method: &'static str, f: F) -> &mut Self
+    /// Registering an already registered method replaces the previous handler,
+    /// use [`Server::try_async_handle`] to reject duplicated registration instead.
+    pub fn async_handle<P, R, F, FR>(&mut self, method: &'static str, mut f: F) -> &mut Self
+    where
+        F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
+        FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.async_handle_with_ctx(method, move |_, params| f(params))
+    }
+
+    /// Register jsonrpc server async handler shared by all calls of `method`,
+    /// see [`Server::handle_shared`].
+    pub fn async_handle_shared<P, R, F, FR>(&mut self, method: &'static str, f: F) -> &mut Self
+    where
+        F: Fn(P) -> FR + 'static + Sync + Send,
+        FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        if self.skip_reserved(method) {
+            return self;
+        }
+
+        let handler =
+            to_shared_async_handler::<K, _, _, _, _>(method.into(), move |_, params| f(params));
+
+        let replaced =
+            self.methods.remove(method) | self.async_methods.register_handler(method, handler);
+
+        if replaced {
+            log::warn!("Method {} handler replaced", method);
+        }
+
+        self.lowercase.insert(method);
+
+        self
+    }
+
+    /// Register jsonrpc server async handler, failing if `method` is already registered
+    /// or reserved, see [`ServerBuilder::allow_reserved`].
+    pub fn try_async_handle<P, R, F, FR>(
+        &mut self,
+        method: &'static str,
+        f: F,
+    ) -> Result<&mut Self, RegisterError>
     where
         F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
-        FR: std::future::Future<Output = RPCResult<Option<R>>> + Sync + Send + 'static,
+        FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
         for<'a> P: Deserialize<'a> + Serialize + Send,
         R: Serialize + Default,
     {
-        self.async_methods
-            .register_handler(method, to_async_handler(method, f));
+        if self.is_reserved(method) {
+            return Err(RegisterError::Reserved(method.to_owned()));
+        }
+
+        if self.has_method(method) {
+            return Err(RegisterError::AlreadyRegistered(method.to_owned()));
+        }
+
+        Ok(self.async_handle(method, f))
+    }
+
+    /// Register jsonrpc server async handler without params, see [`Server::handle0`].
+    pub fn async_handle0<R, F, FR>(&mut self, method: &'static str, mut f: F) -> &mut Self
+    where
+        F: FnMut() -> FR + 'static + Sync + Send + Clone,
+        FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        R: Serialize + Default,
+    {
+        self.async_handle(method, move |_: NoParams| f())
+    }
+
+    /// Register jsonrpc server async handler, which also receives the call [`Context`].
+    pub fn async_handle_with_ctx<P, R, F, FR>(&mut self, method: &'static str, f: F) -> &mut Self
+    where
+        F: FnMut(Context, P) -> FR + 'static + Sync + Send + Clone,
+        FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.register_async(method.to_owned(), f)
+    }
+
+    /// [`Server::async_handle_with_ctx`] of a method whose name is built at runtime.
+    pub(crate) fn register_async<P, R, F, FR>(&mut self, method: String, f: F) -> &mut Self
+    where
+        F: FnMut(Context, P) -> FR + 'static + Sync + Send + Clone,
+        FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        if self.skip_reserved(&method) {
+            return self;
+        }
+
+        let handler = to_async_handler::<K, _, _, _, _>(method.as_str().into(), f);
+
+        let replaced =
+            self.methods.remove(&method) | self.async_methods.register_handler(&method, handler);
+
+        if replaced {
+            log::warn!("Method {} handler replaced", method);
+        }
+
+        self.lowercase.insert(&method);
+
+        self
+    }
+
+    /// Register jsonrpc server streaming handler, whose call produces partial results
+    /// before its final result, see [`Client::call_stream`](crate::Client::call_stream).
+    ///
+    /// Every item of the stream returned by `f` but the last one is sent as a `method`
+    /// notification with [`StreamParams`] carrying the request id. The last item is sent
+    /// as the response, an empty stream is answered with `R::default()`. An error item
+    /// ends the call with an error response.
+    pub fn stream_handle<P, R, F, S>(&mut self, method: &'static str, mut f: F) -> &mut Self
+    where
+        F: FnMut(P) -> S + 'static + Sync + Send + Clone,
+        S: futures::Stream<Item = RPCResult<R>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default + Send + 'static,
+    {
+        self.async_handle_with_ctx(method, move |ctx, params| {
+            forward_stream::<K, _, _>(ctx, method, f(params))
+        })
+    }
+
+    /// Register a subscription, pushing notifications to the subscriber until it unsubscribes,
+    /// see [`Client::subscribe`](crate::Client::subscribe).
+    ///
+    /// A `subscribe` call invokes `f` with its params and the [`SubscriptionSink`] of the new
+    /// subscription, and is answered with the subscription id. `f` keeps the sink, e.g. in a
+    /// spawned producer task, to push values as `notification` requests with
+    /// [`SubscriptionParams`]. An `unsubscribe` call with the subscription id closes the sink
+    /// and is answered with true if the subscription was active. Subscriptions are scoped
+    /// to the session which created them.
+    pub fn handle_subscription<P, F>(
+        &mut self,
+        subscribe: &'static str,
+        unsubscribe: &'static str,
+        notification: &'static str,
+        mut f: F,
+    ) -> &mut Self
+    where
+        F: FnMut(P, SubscriptionSink<K>) -> RPCResult<()> + 'static + Clone + Sync + Send,
+        for<'a> P: Deserialize<'a> + Serialize,
+    {
+        self.handle_with_ctx(subscribe, move |ctx, params| {
+            let sink = SessionSubscriptions::subscribe::<K>(&ctx, notification);
+
+            let id = sink.id();
+
+            if let Err(err) = f(params, sink) {
+                SessionSubscriptions::unsubscribe(&ctx, id);
+
+                return Err(err);
+            }
+
+            Ok(Some(id))
+        })
+        .handle_with_ctx(unsubscribe, |ctx, id: u64| {
+            Ok(Some(SessionSubscriptions::unsubscribe(&ctx, id)))
+        })
+    }
+
+    /// Answer keepalive pings of clients, see [`PING_METHOD`].
+    pub fn handle_ping(&mut self) -> &mut Self {
+        self.handle0(PING_METHOD, || Ok(Some("pong")))
+    }
+
+    /// Answer [`DISCOVER_METHOD`] requests with an [OpenRPC](https://open-rpc.org) document
+    /// describing all registered methods.
+    ///
+    /// Param and result schemas are taken from [`Server::describe`], methods without
+    /// schemas are listed with empty schemas. A handler registered for [`DISCOVER_METHOD`]
+    /// takes precedence over the built-in one.
+    #[cfg(feature = "discover")]
+    pub fn handle_discover<S, V>(&mut self, title: S, version: V) -> &mut Self
+    where
+        S: Into<String>,
+        V: Into<String>,
+    {
+        self.discover.enable(title.into(), version.into());
 
         self
     }
 
-    pub fn accept<C: TransportChannel>(&mut self, channel: C) {
-        static INSTANCE: AtomicUsize = AtomicUsize::new(1);
+    /// Record the JSON schemas of `method` params `P` and result `R` for [`Server::handle_discover`].
+    ///
+    /// The schemas are shared by all clones of this server and may be recorded
+    /// before or after the method handler is registered.
+    #[cfg(feature = "discover")]
+    pub fn describe<P, R>(&mut self, method: &str) -> &mut Self
+    where
+        P: schemars::JsonSchema,
+        R: schemars::JsonSchema,
+    {
+        self.discover.describe::<P, R>(method);
 
-        let id = format!("{}_{}", self.tag, INSTANCE.fetch_add(1, Ordering::SeqCst));
+        self
+    }
+
+    /// Call the built-in method `method`, if any isn't overridden by a registered handler.
+    #[cfg_attr(not(feature = "discover"), allow(unused_variables))]
+    pub(crate) fn builtin_call(
+        &self,
+        method: &str,
+        id: Option<usize>,
+    ) -> Option<RPCResult<Option<RPCData>>> {
+        #[cfg(feature = "discover")]
+        if method == DISCOVER_METHOD
+            && self.discover.is_enabled()
+            && !self.async_methods.contains(method)
+        {
+            let document = self.discover.document(self.methods());
+
+            return Some(match id {
+                Some(id) => {
+                    K::encode(&crate::Response::<String, _, ()>::success(id, document)).map(Some)
+                }
+                None => Ok(None),
+            });
+        }
+
+        None
+    }
 
+    /// Register jsonrpc server sync handler, which receives the state set by [`ServerBuilder::with_state`].
+    pub fn handle_stateful<S, P, R, F>(&mut self, method: &'static str, mut f: F) -> &mut Self
+    where
+        S: Send + Sync + 'static,
+        F: FnMut(Arc<S>, P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        for<'a> P: Deserialize<'a> + Serialize,
+        R: Serialize + Default,
+    {
+        self.handle_with_ctx(method, move |ctx, params| f(state_of(&ctx)?, params))
+    }
+
+    /// Register jsonrpc server async handler, which receives the state set by [`ServerBuilder::with_state`].
+    pub fn async_handle_stateful<S, P, R, F, FR>(
+        &mut self,
+        method: &'static str,
+        mut f: F,
+    ) -> &mut Self
+    where
+        S: Send + Sync + 'static,
+        F: FnMut(Arc<S>, P) -> FR + 'static + Sync + Send + Clone,
+        FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.async_handle_with_ctx(method, move |ctx, params| {
+            let call = state_of::<S>(&ctx).map(|state| f(state, params));
+
+            async move { call?.await }
+        })
+    }
+
+    /// Register jsonrpc server async handler, abandoned when it runs longer than `timeout`.
+    ///
+    /// A timed out call is answered with an [`ErrorCode::ServerError`] with code [`HANDLER_TIMEOUT`].
+    pub fn handle_with_timeout<P, R, F, FR>(
+        &mut self,
+        method: &'static str,
+        timeout: Duration,
+        mut f: F,
+    ) -> &mut Self
+    where
+        F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
+        FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.async_handle_with_ctx(method, move |_, params| {
+            let call = Box::pin(f(params));
+
+            async move {
+                match select(call, timer::timeout(timeout)).await {
+                    Either::Left((result, _)) => result,
+                    Either::Right(_) => {
+                        log::warn!("Method {} timed out after {:?}", method, timeout);
+
+                        let message = format!("Method {} timed out", method);
+
+                        Err(RPCError::new(
+                            ErrorCode::ServerError(HANDLER_TIMEOUT, message.clone()),
+                            message,
+                            None,
+                        ))
+                    }
+                }
+            }
+        })
+    }
+
+    /// Register jsonrpc server async handler, running at most `limit` calls of `method` at once.
+    ///
+    /// Calls beyond the limit queue until a running call completes, the limit is
+    /// shared by all sessions. Other methods aren't limited. A `limit` of zero is
+    /// treated as one.
+    pub fn handle_with_concurrency<P, R, F, FR>(
+        &mut self,
+        method: &'static str,
+        limit: usize,
+        f: F,
+    ) -> &mut Self
+    where
+        F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
+        FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send + 'static,
+        R: Serialize + Default,
+    {
+        let semaphore = Arc::new(Semaphore::new(limit.max(1)));
+
+        self.async_handle_with_ctx(method, move |_, params| {
+            let semaphore = semaphore.clone();
+            let mut f = f.clone();
+
+            async move {
+                let _permit = semaphore.acquire_arc().await;
+
+                f(params).await
+            }
+        })
+    }
+
+    /// Register jsonrpc server async handler, accepting at most `per_second` calls of `method`
+    /// per second across all sessions.
+    ///
+    /// Calls are admitted by a token bucket holding up to `per_second` tokens, so idle
+    /// time allows bursts of `per_second` calls. Calls beyond the rate are answered with
+    /// an [`ErrorCode::ServerError`] error with code [`RATE_LIMITED`] without invoking `f`.
+    /// A `per_second` of zero is treated as one.
+    pub fn handle_with_rate_limit<P, R, F, FR>(
+        &mut self,
+        method: &'static str,
+        per_second: u32,
+        mut f: F,
+    ) -> &mut Self
+    where
+        F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
+        FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        let bucket = Arc::new(Mutex::new(TokenBucket::new(per_second)));
+
+        self.async_handle_with_ctx(method, move |_, params| {
+            let call = if bucket.lock().unwrap().try_acquire() {
+                Ok(f(params))
+            } else {
+                Err(rate_limited(method))
+            };
+
+            async move { call?.await }
+        })
+    }
+
+    /// Register jsonrpc server async handler, accepting at most `per_second` calls of `method`
+    /// per second from each session, see [`Server::handle_with_rate_limit`].
+    ///
+    /// Every session has its own token bucket, so one abusive client can't exhaust
+    /// the rate of the others.
+    pub fn handle_with_session_rate_limit<P, R, F, FR>(
+        &mut self,
+        method: &'static str,
+        per_second: u32,
+        mut f: F,
+    ) -> &mut Self
+    where
+        F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
+        FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.async_handle_with_ctx(method, move |ctx, params| {
+            let call = if try_acquire_session(&ctx, method, per_second) {
+                Ok(f(params))
+            } else {
+                Err(rate_limited(method))
+            };
+
+            async move { call?.await }
+        })
+    }
+
+    pub fn accept<C: TransportChannel>(&mut self, channel: C) {
         let (input, output) = channel.framed();
 
-        let mut session = ServiceSession::<C>::new(
-            id,
-            input,
-            output,
-            self.methods.clone(),
-            self.async_methods.clone(),
+        let mut session = self.session::<C>(input, output);
+
+        let registration = session.abort_registration();
+
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session.id().to_owned(), session.handle());
+
+        spawn_abortable::<C, _>(
+            self.executor.as_ref(),
+            async move { session.run().await },
+            registration,
         );
+    }
+
+    /// Number of running sessions started by [`Server::accept`].
+    ///
+    /// Sessions are counted from `accept` until their connection closed and all
+    /// their pending calls were answered.
+    pub fn session_count(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    /// Ids of the running sessions started by [`Server::accept`], see [`Context::session_id`].
+    pub fn session_ids(&self) -> Vec<String> {
+        self.sessions.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Close the session `id`, returns false if no such session is running.
+    ///
+    /// The session stops reading requests, answers its pending calls and then
+    /// drops its transport.
+    pub fn close_session(&self, id: &str) -> bool {
+        match self.sessions.lock().unwrap().get(id) {
+            Some(session) => {
+                session.close();
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Close all running sessions, see [`Server::close_session`].
+    ///
+    /// Sessions accepted afterwards aren't affected.
+    pub fn close_sessions(&self) {
+        self.sessions
+            .lock()
+            .unwrap()
+            .values()
+            .for_each(SessionHandle::close);
+    }
+
+    /// Abort the session `id` at once, returns false if no such session is running.
+    ///
+    /// Unlike [`Server::close_session`] pending calls aren't answered, their handlers
+    /// are dropped along with the transport.
+    pub fn abort_session(&self, id: &str) -> bool {
+        match self.sessions.lock().unwrap().remove(id) {
+            Some(session) => {
+                session.abort();
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Abort all running sessions, see [`Server::abort_session`].
+    pub fn abort_sessions(&self) {
+        self.sessions
+            .lock()
+            .unwrap()
+            .drain()
+            .for_each(|(_, session)| session.abort());
+    }
+
+    /// Send the notification `method` to all running sessions started by [`Server::accept`],
+    /// returns the number of sessions it was queued to.
+    ///
+    /// The notification is encoded once and handed to each session, which writes it
+    /// between its responses. Broadcasting doesn't wait for the writes: a session whose
+    /// client reads slowly buffers queued notifications in memory without bound, rate
+    /// limit broadcasts or close such sessions to keep memory in check. A session whose
+    /// transport fails to write closes and is dropped from later broadcasts.
+    pub fn broadcast<P>(&self, method: &str, params: P) -> RPCResult<usize>
+    where
+        P: Serialize,
+    {
+        let frame = K::encode(&crate::Request::notification(method, params))?;
+
+        let mut sessions = self.sessions.lock().unwrap();
 
-        C::spawn(async move { session.run().await });
+        sessions.retain(|_, session| session.send(frame.clone()));
+
+        Ok(sessions.len())
+    }
+
+    pub(crate) fn unregister_session(&self, id: &str) {
+        self.sessions.lock().unwrap().remove(id);
     }
+
+    /// Create a session serving the requests of `input`, responses are written to `output`.
+    pub(crate) fn session<C: TransportChannel>(
+        &self,
+        input: C::Input,
+        output: C::Output,
+    ) -> ServiceSession<C, K> {
+        static INSTANCE: AtomicUsize = AtomicUsize::new(1);
+
+        let id = format!("{}_{}", self.tag, INSTANCE.fetch_add(1, Ordering::SeqCst));
+
+        ServiceSession::new(id, input, output, self.clone())
+    }
+}
+
+fn state_of<S>(ctx: &Context) -> RPCResult<Arc<S>>
+where
+    S: Send + Sync + 'static,
+{
+    ctx.state::<S>().ok_or_else(|| {
+        RPCError::new(
+            ErrorCode::InternalError,
+            format!("Server state {} not found", std::any::type_name::<S>()),
+            None,
+        )
+    })
 }
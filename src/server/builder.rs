@@ -0,0 +1,538 @@
+use std::{future::Future, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Codec, Executor, JsonCodec, Metrics, RPCResult};
+
+use super::{
+    Context, Middleware, Namespace, RegisterError, SendErrorPolicy, Server, SubscriptionSink,
+};
+
+/// [`Server`] builder, see [`Server::builder`].
+///
+/// Configures a server and registers its handlers in one fluent chain, so the
+/// configuration is settled before [`ServerBuilder::build`] returns the server
+/// which accepts sessions.
+pub struct ServerBuilder<K: Codec = JsonCodec> {
+    server: Server<K>,
+}
+
+impl ServerBuilder {
+    /// Create a builder of a server tagged `tag` in session ids.
+    pub fn new<S: Into<String>>(tag: S) -> Self {
+        Self::with_codec(tag, JsonCodec)
+    }
+}
+
+impl<K: Codec> ServerBuilder<K> {
+    /// Create a builder of a server exchanging frames encoded by `codec`.
+    pub fn with_codec<S: Into<String>>(tag: S, codec: K) -> Self {
+        Self {
+            server: Server::with_codec(tag, codec),
+        }
+    }
+
+    /// Write the responses of one session strictly in request arrival order.
+    ///
+    /// By default async handlers of one session run concurrently and responses
+    /// are written as soon as each call completes, so they may be out of request order.
+    ///
+    /// In ordered mode handlers still run concurrently, but completed responses are
+    /// buffered until every request received before them was answered. This trades
+    /// head-of-line blocking for FIFO responses: one slow call delays the responses of
+    /// all later requests, which are held in memory meanwhile.
+    pub fn ordered_responses(mut self, ordered: bool) -> Self {
+        self.server.ordered_responses(ordered);
+
+        self
+    }
+
+    /// Accept requests omitting the `jsonrpc` member as version 2.0.
+    ///
+    /// By default such requests are rejected as malformed.
+    pub fn lenient_version(mut self, lenient: bool) -> Self {
+        self.server.lenient_version(lenient);
+
+        self
+    }
+
+    /// Accept JSON-RPC 1.0 requests as well, whose `jsonrpc` member is `"1.0"` or missing.
+    ///
+    /// Their responses are written in 1.0 format: both `result` and `error` are present,
+    /// one of them `null`, and `jsonrpc` is echoed only if the request carried it.
+    /// Requests with a `null` or missing id are notifications in either version.
+    /// By default only version 2.0 is accepted, this takes precedence over
+    /// [`ServerBuilder::lenient_version`].
+    pub fn compat_v1(mut self, compat: bool) -> Self {
+        self.server.compat_v1(compat);
+
+        self
+    }
+
+    /// Match method names case-insensitively, for clients sending them with inconsistent casing.
+    ///
+    /// A request whose method isn't registered as sent is dispatched to the method
+    /// registered under the same lowercase name, and its [`Context::method`] is the
+    /// registered name. Of several names differing only in case the first one registered
+    /// is dispatched to, registering the others logs a warning. JSON-RPC method names are
+    /// case-sensitive, so this is off by default. The reserved `rpc.` methods are always
+    /// matched exactly.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.server.case_insensitive(case_insensitive);
+
+        self
+    }
+
+    /// Allow registering handlers of methods beginning with `rpc.`.
+    ///
+    /// The specification reserves these names for the implementation, so by default
+    /// they aren't registered, which is logged as an error, or fail with
+    /// [`RegisterError::Reserved`] for the `try_` registrations, except for
+    /// [`PING_METHOD`](super::PING_METHOD), [`CANCEL_METHOD`](super::CANCEL_METHOD) and
+    /// [`DISCOVER_METHOD`](super::DISCOVER_METHOD), whose handlers replace the built-in ones.
+    pub fn allow_reserved(mut self, allow: bool) -> Self {
+        self.server.allow_reserved(allow);
+
+        self
+    }
+
+    /// Run `middleware` around every call of a registered handler or the
+    /// [`Server::default_handler`], see [`Middleware`].
+    ///
+    /// Middlewares run in registration order, the first one registered being the
+    /// outermost. The built-in [`CANCEL_METHOD`](super::CANCEL_METHOD) and discovery calls
+    /// bypass them. Calls of sync handlers are no longer completed inline once a middleware
+    /// is set.
+    pub fn layer<M: Middleware<K>>(mut self, middleware: M) -> Self {
+        self.server.layer(middleware);
+
+        self
+    }
+
+    /// Report handled calls and their latency to `metrics`.
+    pub fn metrics<M: Metrics>(mut self, metrics: M) -> Self {
+        self.server.metrics(metrics);
+
+        self
+    }
+
+    /// Log a warning with the method name and elapsed time for every call whose
+    /// handler took longer than `threshold`, measured from dispatch until its response
+    /// is written. Faster calls aren't logged.
+    pub fn slow_call_threshold(mut self, threshold: Duration) -> Self {
+        self.server.slow_call_threshold(threshold);
+
+        self
+    }
+
+    /// Run sessions on `executor` instead of spawning them by
+    /// [`TransportChannel::spawn`](crate::channel::TransportChannel::spawn).
+    pub fn executor<E: Executor>(mut self, executor: E) -> Self {
+        self.server.executor(executor);
+
+        self
+    }
+
+    /// Reject incoming frames larger than `bytes` before parsing them.
+    ///
+    /// Oversized frames are answered with an
+    /// [`ErrorCode::InvalidRequest`](crate::ErrorCode::InvalidRequest) error with a `null` id,
+    /// the session keeps serving later frames. The frame was already read
+    /// by the transport, which should bound its own framing to avoid buffering it at all.
+    /// Unlimited by default.
+    pub fn max_frame_size(mut self, bytes: usize) -> Self {
+        self.server.max_frame_size(bytes);
+
+        self
+    }
+
+    /// Close sessions which receive no frame for `timeout`, e.g. clients which
+    /// connected and never sent anything. Disabled by default.
+    ///
+    /// The timer restarts on every received frame. An idle session stops reading like
+    /// [`Server::close_sessions`], calls in flight are still answered before the
+    /// connection closes.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.server.idle_timeout(timeout);
+
+        self
+    }
+
+    /// Set how sessions handle a failed write of a response or notification to their
+    /// transport, by default the session ends, see [`SendErrorPolicy`].
+    ///
+    /// Whether a transport can still write after an error depends on the transport,
+    /// [`SendErrorPolicy::Continue`] and [`SendErrorPolicy::Retry`] suit transports
+    /// whose write errors are transient.
+    pub fn on_send_error(mut self, policy: SendErrorPolicy) -> Self {
+        self.server.on_send_error(policy);
+
+        self
+    }
+
+    /// Coalesce responses ready at the same time into one batch array of up to `max`
+    /// responses, written as a single frame.
+    ///
+    /// Responses are collected while further requests or completed calls are
+    /// immediately available, reducing small writes under bursts of requests. A lone
+    /// response is still written as is. Off by default, as clients must accept batch
+    /// responses to requests sent individually; [`Client`](crate::Client) does.
+    /// Notifications written by the server are never coalesced, responses queued
+    /// before one are written first. A `max` below 2 turns coalescing off.
+    pub fn coalesce_responses(mut self, max: usize) -> Self {
+        self.server.coalesce_responses(max);
+
+        self
+    }
+
+    /// Store a shared state instance, accessible from stateful handlers and [`Context::state`].
+    ///
+    /// The state is shared by all sessions, one instance per type `S`.
+    pub fn with_state<S>(mut self, state: S) -> Self
+    where
+        S: Send + Sync + 'static,
+    {
+        self.server.with_state(state);
+
+        self
+    }
+
+    /// See [`Server::default_handler`].
+    pub fn default_handler<R, F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str, Option<usize>, serde_json::Value) -> RPCResult<Option<R>>
+            + 'static
+            + Sync
+            + Send,
+        R: Serialize,
+    {
+        self.server.default_handler(f);
+
+        self
+    }
+
+    /// See [`Server::async_default_handler`].
+    pub fn async_default_handler<F, FR>(mut self, f: F) -> Self
+    where
+        F: Fn(&str, Option<usize>, serde_json::Value) -> FR + 'static + Sync + Send,
+        FR: Future<Output = RPCResult<Option<serde_json::Value>>> + Send + 'static,
+    {
+        self.server.async_default_handler(f);
+
+        self
+    }
+
+    /// Register the methods of the namespace `name` with `f`, see [`Server::namespace`].
+    pub fn namespace<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(&mut Namespace<'_, K>),
+    {
+        f(&mut self.server.namespace(name));
+
+        self
+    }
+
+    /// See [`Server::handle`].
+    pub fn handle<P, R, F>(mut self, method: &'static str, f: F) -> Self
+    where
+        F: FnMut(P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        for<'a> P: Deserialize<'a> + Serialize,
+        R: Serialize + Default,
+    {
+        self.server.handle(method, f);
+
+        self
+    }
+
+    /// See [`Server::handle_shared`].
+    pub fn handle_shared<P, R, F>(mut self, method: &'static str, f: F) -> Self
+    where
+        F: Fn(P) -> RPCResult<Option<R>> + 'static + Sync + Send,
+        for<'a> P: Deserialize<'a> + Serialize,
+        R: Serialize + Default,
+    {
+        self.server.handle_shared(method, f);
+
+        self
+    }
+
+    /// See [`Server::handle0`].
+    pub fn handle0<R, F>(mut self, method: &'static str, f: F) -> Self
+    where
+        F: FnMut() -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        R: Serialize + Default,
+    {
+        self.server.handle0(method, f);
+
+        self
+    }
+
+    /// See [`Server::handle_with_ctx`].
+    pub fn handle_with_ctx<P, R, F>(mut self, method: &'static str, f: F) -> Self
+    where
+        F: FnMut(Context, P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        for<'a> P: Deserialize<'a> + Serialize,
+        R: Serialize + Default,
+    {
+        self.server.handle_with_ctx(method, f);
+
+        self
+    }
+
+    /// See [`Server::handle_stateful`].
+    pub fn handle_stateful<S, P, R, F>(mut self, method: &'static str, f: F) -> Self
+    where
+        S: Send + Sync + 'static,
+        F: FnMut(std::sync::Arc<S>, P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        for<'a> P: Deserialize<'a> + Serialize,
+        R: Serialize + Default,
+    {
+        self.server.handle_stateful(method, f);
+
+        self
+    }
+
+    /// See [`Server::try_handle`].
+    pub fn try_handle<P, R, F>(mut self, method: &'static str, f: F) -> Result<Self, RegisterError>
+    where
+        F: FnMut(P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        for<'a> P: Deserialize<'a> + Serialize,
+        R: Serialize + Default,
+    {
+        self.server.try_handle(method, f)?;
+
+        Ok(self)
+    }
+
+    /// See [`Server::async_handle`].
+    pub fn async_handle<P, R, F, FR>(mut self, method: &'static str, f: F) -> Self
+    where
+        F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.server.async_handle(method, f);
+
+        self
+    }
+
+    /// See [`Server::async_handle_shared`].
+    pub fn async_handle_shared<P, R, F, FR>(mut self, method: &'static str, f: F) -> Self
+    where
+        F: Fn(P) -> FR + 'static + Sync + Send,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.server.async_handle_shared(method, f);
+
+        self
+    }
+
+    /// See [`Server::try_async_handle`].
+    pub fn try_async_handle<P, R, F, FR>(
+        mut self,
+        method: &'static str,
+        f: F,
+    ) -> Result<Self, RegisterError>
+    where
+        F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.server.try_async_handle(method, f)?;
+
+        Ok(self)
+    }
+
+    /// See [`Server::async_handle0`].
+    pub fn async_handle0<R, F, FR>(mut self, method: &'static str, f: F) -> Self
+    where
+        F: FnMut() -> FR + 'static + Sync + Send + Clone,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        R: Serialize + Default,
+    {
+        self.server.async_handle0(method, f);
+
+        self
+    }
+
+    /// See [`Server::async_handle_with_ctx`].
+    pub fn async_handle_with_ctx<P, R, F, FR>(mut self, method: &'static str, f: F) -> Self
+    where
+        F: FnMut(Context, P) -> FR + 'static + Sync + Send + Clone,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.server.async_handle_with_ctx(method, f);
+
+        self
+    }
+
+    /// See [`Server::async_handle_stateful`].
+    pub fn async_handle_stateful<S, P, R, F, FR>(mut self, method: &'static str, f: F) -> Self
+    where
+        S: Send + Sync + 'static,
+        F: FnMut(std::sync::Arc<S>, P) -> FR + 'static + Sync + Send + Clone,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.server.async_handle_stateful(method, f);
+
+        self
+    }
+
+    /// See [`Server::handle_with_timeout`].
+    pub fn handle_with_timeout<P, R, F, FR>(
+        mut self,
+        method: &'static str,
+        timeout: Duration,
+        f: F,
+    ) -> Self
+    where
+        F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.server.handle_with_timeout(method, timeout, f);
+
+        self
+    }
+
+    /// See [`Server::handle_with_concurrency`].
+    pub fn handle_with_concurrency<P, R, F, FR>(
+        mut self,
+        method: &'static str,
+        limit: usize,
+        f: F,
+    ) -> Self
+    where
+        F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send + 'static,
+        R: Serialize + Default,
+    {
+        self.server.handle_with_concurrency(method, limit, f);
+
+        self
+    }
+
+    /// See [`Server::handle_with_rate_limit`].
+    pub fn handle_with_rate_limit<P, R, F, FR>(
+        mut self,
+        method: &'static str,
+        per_second: u32,
+        f: F,
+    ) -> Self
+    where
+        F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.server.handle_with_rate_limit(method, per_second, f);
+
+        self
+    }
+
+    /// See [`Server::handle_with_session_rate_limit`].
+    pub fn handle_with_session_rate_limit<P, R, F, FR>(
+        mut self,
+        method: &'static str,
+        per_second: u32,
+        f: F,
+    ) -> Self
+    where
+        F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.server
+            .handle_with_session_rate_limit(method, per_second, f);
+
+        self
+    }
+
+    /// See [`Server::stream_handle`].
+    pub fn stream_handle<P, R, F, S>(mut self, method: &'static str, f: F) -> Self
+    where
+        F: FnMut(P) -> S + 'static + Sync + Send + Clone,
+        S: futures::Stream<Item = RPCResult<R>> + Send + 'static,
+        for<'a> P: Deserialize<'a> + Serialize + Send,
+        R: Serialize + Default + Send + 'static,
+    {
+        self.server.stream_handle(method, f);
+
+        self
+    }
+
+    /// See [`Server::handle_subscription`].
+    pub fn handle_subscription<P, F>(
+        mut self,
+        subscribe: &'static str,
+        unsubscribe: &'static str,
+        notification: &'static str,
+        f: F,
+    ) -> Self
+    where
+        F: FnMut(P, SubscriptionSink<K>) -> RPCResult<()> + 'static + Clone + Sync + Send,
+        for<'a> P: Deserialize<'a> + Serialize,
+    {
+        self.server
+            .handle_subscription(subscribe, unsubscribe, notification, f);
+
+        self
+    }
+
+    /// See [`Server::handle_ping`].
+    pub fn handle_ping(mut self) -> Self {
+        self.server.handle_ping();
+
+        self
+    }
+
+    /// See [`Server::handle_discover`].
+    #[cfg(feature = "discover")]
+    pub fn handle_discover<S, V>(mut self, title: S, version: V) -> Self
+    where
+        S: Into<String>,
+        V: Into<String>,
+    {
+        self.server.handle_discover(title, version);
+
+        self
+    }
+
+    /// See [`Server::describe`].
+    #[cfg(feature = "discover")]
+    pub fn describe<P, R>(mut self, method: &str) -> Self
+    where
+        P: schemars::JsonSchema,
+        R: schemars::JsonSchema,
+    {
+        self.server.describe::<P, R>(method);
+
+        self
+    }
+
+    /// Register handlers with `f`, e.g. a macro generated `register_rpc`.
+    pub fn register<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut Server<K>),
+    {
+        f(&mut self.server);
+
+        self
+    }
+
+    /// Returns the configured server.
+    pub fn build(self) -> Server<K> {
+        self.server
+    }
+}
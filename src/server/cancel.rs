@@ -0,0 +1,74 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use event_listener::Event;
+use futures::future::select;
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    event: Event,
+}
+
+impl Inner {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Cooperative cancellation signal of a handler call, see [`Context::cancellation`](super::Context::cancellation).
+///
+/// A token is canceled by [`CancellationToken::cancel`], or together with its parent
+/// when it was created by [`CancellationToken::child_token`].
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+    parent: Option<Arc<Inner>>,
+}
+
+impl CancellationToken {
+    /// Create a token which isn't canceled yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a token canceled together with this one, or on its own.
+    pub fn child_token(&self) -> Self {
+        Self {
+            inner: Default::default(),
+            parent: Some(self.inner.clone()),
+        }
+    }
+
+    /// Cancel this token and its child tokens, waking all tasks waiting for it.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+
+        self.inner.event.notify(usize::MAX);
+    }
+
+    /// Returns true once this token or its parent was canceled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled() || self.parent.as_ref().is_some_and(|p| p.is_cancelled())
+    }
+
+    /// Wait until this token or its parent is canceled.
+    pub async fn cancelled(&self) {
+        while !self.is_cancelled() {
+            // Listen before checking again, so a cancel in between isn't missed.
+            let listener = self.inner.event.listen();
+            let parent = self.parent.as_ref().map(|parent| parent.event.listen());
+
+            if self.is_cancelled() {
+                return;
+            }
+
+            match parent {
+                Some(parent) => _ = select(listener, parent).await,
+                None => listener.await,
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use super::{session::SessionHandle, CancellationToken, Notifier};
+
+/// Typed key/value store shared by all requests of one server session.
+///
+/// Values are keyed by their type, so each type can be stored at most once.
+#[derive(Clone, Default)]
+pub struct Extensions {
+    map: Arc<Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>>,
+}
+
+impl Extensions {
+    /// Insert a value, returning the previously stored value of the same type.
+    pub fn insert<T>(&self, value: T) -> Option<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map
+            .lock()
+            .unwrap()
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|prev| prev.downcast().ok().map(|prev| *prev))
+    }
+
+    /// Get a clone of the stored value of type `T`.
+    pub fn get<T>(&self) -> Option<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.map
+            .lock()
+            .unwrap()
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+            .cloned()
+    }
+
+    /// Get a clone of the stored value of type `T`, first storing the one created by `f` if
+    /// there's none, all under the lock so concurrent callers share the same value.
+    pub fn get_or_insert_with<T, F>(&self, f: F) -> T
+    where
+        T: Clone + Send + Sync + 'static,
+        F: FnOnce() -> T,
+    {
+        self.map
+            .lock()
+            .unwrap()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(f()))
+            .downcast_ref::<T>()
+            .cloned()
+            .expect("Inner error, extension of another type")
+    }
+
+    /// Remove and return the stored value of type `T`.
+    pub fn remove<T>(&self) -> Option<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map
+            .lock()
+            .unwrap()
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok().map(|value| *value))
+    }
+
+    /// Returns true if a value of type `T` is stored.
+    pub fn contains<T>(&self) -> bool
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map.lock().unwrap().contains_key(&TypeId::of::<T>())
+    }
+}
+
+/// Handler invocation context, see [`crate::Server::handle_with_ctx`].
+#[derive(Clone)]
+pub struct Context {
+    session_id: Arc<str>,
+    id: Option<usize>,
+    method: Arc<str>,
+    extensions: Extensions,
+    states: Extensions,
+    session: SessionHandle,
+    cancellation: CancellationToken,
+    #[cfg(feature = "traceparent")]
+    traceparent: Option<Arc<str>>,
+}
+
+impl Context {
+    pub(crate) fn new(
+        session_id: Arc<str>,
+        id: Option<usize>,
+        method: Arc<str>,
+        extensions: Extensions,
+        states: Extensions,
+        session: SessionHandle,
+        cancellation: CancellationToken,
+    ) -> Self {
+        Self {
+            session_id,
+            id,
+            method,
+            extensions,
+            states,
+            session,
+            cancellation,
+            #[cfg(feature = "traceparent")]
+            traceparent: None,
+        }
+    }
+
+    #[cfg(feature = "traceparent")]
+    pub(crate) fn with_traceparent(mut self, traceparent: Option<&str>) -> Self {
+        self.traceparent = traceparent.map(Arc::from);
+
+        self
+    }
+
+    /// Id of the server session which received this request.
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// JSONRPC request id, [`None`] for notifications.
+    pub fn id(&self) -> Option<usize> {
+        self.id
+    }
+
+    /// Name of the invoked method, the requested one for a [`crate::Server::default_handler`].
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// [W3C trace context](https://www.w3.org/TR/trace-context/#traceparent-header) sent
+    /// by the caller, see [`ClientConfig::trace_context`](crate::ClientConfig::trace_context).
+    ///
+    /// Extract it with a propagator of the tracing backend, e.g. opentelemetry's
+    /// `TraceContextPropagator`, to make the handler's span a child of the caller's span.
+    #[cfg(feature = "traceparent")]
+    pub fn traceparent(&self) -> Option<&str> {
+        self.traceparent.as_deref()
+    }
+
+    /// Session scoped extensions, shared by all requests of the same connection.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Canceled once the connection of the session closed, or the client sent a
+    /// [`CANCEL_METHOD`](super::CANCEL_METHOD) notification for this request.
+    ///
+    /// Long-running handlers should check it, or race [`CancellationToken::cancelled`]
+    /// against their work, to stop early. The response of a canceled call is still sent.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Handle of the session which received this request.
+    pub(crate) fn session(&self) -> &SessionHandle {
+        &self.session
+    }
+
+    /// Handle pushing notifications to the client of this session, frames are
+    /// encoded with the codec of the server.
+    pub fn notifier(&self) -> Notifier {
+        Notifier::new(self.session.clone())
+    }
+
+    /// Server shared state registered by [`crate::ServerBuilder::with_state`].
+    pub fn state<S>(&self) -> Option<Arc<S>>
+    where
+        S: Send + Sync + 'static,
+    {
+        self.states.get::<Arc<S>>()
+    }
+}
@@ -0,0 +1,77 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+
+/// OpenRPC specification version of the documents returned by [`DISCOVER_METHOD`](super::DISCOVER_METHOD).
+const OPENRPC_VERSION: &str = "1.2.6";
+
+/// JSON schemas of the params and result of one method.
+#[derive(Clone)]
+struct MethodSchema {
+    params: Value,
+    result: Value,
+}
+
+/// Method schemas shared by all clones of one server.
+#[derive(Clone, Default)]
+pub(crate) struct Discover {
+    info: Option<Arc<(String, String)>>,
+    schemas: Arc<Mutex<HashMap<String, MethodSchema>>>,
+}
+
+impl Discover {
+    pub(crate) fn enable(&mut self, title: String, version: String) {
+        self.info = Some(Arc::new((title, version)));
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.info.is_some()
+    }
+
+    /// Record the schemas of `method` params `P` and result `R`.
+    pub(crate) fn describe<P: JsonSchema, R: JsonSchema>(&self, method: &str) {
+        let schema = MethodSchema {
+            params: schemars::schema_for!(P).to_value(),
+            result: schemars::schema_for!(R).to_value(),
+        };
+
+        self.schemas
+            .lock()
+            .unwrap()
+            .insert(method.to_owned(), schema);
+    }
+
+    /// Assemble the OpenRPC document of `methods`.
+    ///
+    /// Methods without recorded schemas are described with empty schemas, accepting any value.
+    pub(crate) fn document(&self, methods: impl Iterator<Item = String>) -> Value {
+        let (title, version) = self.info.as_deref().cloned().unwrap_or_default();
+
+        let schemas = self.schemas.lock().unwrap();
+
+        let methods = methods
+            .map(|name| {
+                let (params, result) = match schemas.get(&name) {
+                    Some(schema) => (schema.params.clone(), schema.result.clone()),
+                    None => (json!({}), json!({})),
+                };
+
+                json!({
+                    "name": name,
+                    "params": [{ "name": "params", "schema": params }],
+                    "result": { "name": "result", "schema": result },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "openrpc": OPENRPC_VERSION,
+            "info": { "title": title, "version": version },
+            "methods": methods,
+        })
+    }
+}
@@ -4,19 +4,75 @@ use std::{
 };
 
 use futures::future::BoxFuture;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 
-use crate::{channel::RPCData, ErrorCode, RPCError, RPCResult, Response};
+use crate::{channel::RPCData, Codec, ErrorCode, RPCError, RPCResult, Response};
 
-pub type ServerHandler = Box<
-    dyn FnMut(Option<usize>, serde_json::Value) -> RPCResult<Option<RPCData>>
+use super::Context;
+
+/// Undecoded params of requests served by codec `K`, `None` when the request omits them.
+pub type HandlerParams<K> = Option<<K as Codec>::Params>;
+
+pub type ServerHandler<K> =
+    Box<dyn FnMut(Context, HandlerParams<K>) -> RPCResult<Option<RPCData>> + Sync + Send + 'static>;
+
+pub type AsyncServerHandler<K> = Box<
+    dyn FnMut(Context, HandlerParams<K>) -> BoxFuture<'static, RPCResult<Option<RPCData>>>
         + Sync
         + Send
         + 'static,
 >;
 
-pub type AsyncServerHandler = Box<
-    dyn FnMut(Option<usize>, serde_json::Value) -> BoxFuture<'static, RPCResult<Option<RPCData>>>
+/// [`ServerHandler`] shared by all calls of its method instead of cloned per call.
+pub type SharedServerHandler<K> =
+    Arc<dyn Fn(Context, HandlerParams<K>) -> RPCResult<Option<RPCData>> + Sync + Send + 'static>;
+
+/// [`AsyncServerHandler`] shared by all calls of its method instead of cloned per call.
+pub type SharedAsyncServerHandler<K> = Arc<
+    dyn Fn(Context, HandlerParams<K>) -> BoxFuture<'static, RPCResult<Option<RPCData>>>
+        + Sync
+        + Send
+        + 'static,
+>;
+
+/// Handler of one sync call, either cloned from the registered `FnMut` handler or the
+/// registered `Fn` handler shared by all calls.
+pub enum SyncMethodHandler<K: Codec> {
+    Cloned(ServerHandler<K>),
+    Shared(SharedServerHandler<K>),
+}
+
+/// Handler of one async call, see [`SyncMethodHandler`].
+pub enum AsyncMethodHandler<K: Codec> {
+    Cloned(AsyncServerHandler<K>),
+    Shared(SharedAsyncServerHandler<K>),
+}
+
+impl<K: Codec> SyncMethodHandler<K> {
+    pub(crate) fn call(self, ctx: Context, params: HandlerParams<K>) -> RPCResult<Option<RPCData>> {
+        match self {
+            Self::Cloned(mut handler) => handler(ctx, params),
+            Self::Shared(handler) => handler(ctx, params),
+        }
+    }
+}
+
+impl<K: Codec> AsyncMethodHandler<K> {
+    pub(crate) fn call(
+        self,
+        ctx: Context,
+        params: HandlerParams<K>,
+    ) -> BoxFuture<'static, RPCResult<Option<RPCData>>> {
+        match self {
+            Self::Cloned(mut handler) => handler(ctx, params),
+            Self::Shared(handler) => handler(ctx, params),
+        }
+    }
+}
+
+/// Fallback handler invoked with the method name for requests matching no registered method.
+pub type DefaultHandler<K> = Arc<
+    dyn Fn(&str, Context, HandlerParams<K>) -> BoxFuture<'static, RPCResult<Option<RPCData>>>
         + Sync
         + Send
         + 'static,
@@ -54,133 +110,402 @@ impl<Handler> HandlerClonerRegister<Handler> {
             .map(|h| h())
     }
 
+    /// Register method handler, returns true if a previous handler was replaced.
     pub(crate) fn register_handler(
         &self,
         method_name: &str,
         handler_cloner: HandlerCloner<Handler>,
-    ) {
+    ) -> bool {
         self.cloners
             .lock()
             .unwrap()
-            .insert(method_name.to_string(), handler_cloner);
+            .insert(method_name.to_string(), handler_cloner)
+            .is_some()
+    }
+
+    /// Remove method handler, returns true if it was registered.
+    pub(crate) fn remove(&self, method_name: &str) -> bool {
+        self.cloners.lock().unwrap().remove(method_name).is_some()
+    }
+
+    pub(crate) fn contains(&self, method_name: &str) -> bool {
+        self.cloners.lock().unwrap().contains_key(method_name)
+    }
+
+    /// Names of all registered methods.
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.cloners.lock().unwrap().keys().cloned().collect()
     }
 }
 
-pub(crate) fn to_handler<P, R, F>(method: &'static str, mut f: F) -> HandlerCloner<ServerHandler>
-where
-    F: FnMut(P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
-    for<'a> P: Deserialize<'a> + Serialize,
-    R: Serialize + Default,
-{
-    let handler = move |id, mut value: serde_json::Value| {
-        log::trace!("try call method `{}` with params {}", method, value);
+/// Registered method names by their lowercase form, see [`ServerBuilder::case_insensitive`](super::ServerBuilder::case_insensitive).
+#[derive(Clone, Default)]
+pub(crate) struct LowercaseIndex(Arc<Mutex<HashMap<String, Vec<String>>>>);
 
-        if value.is_array() {
-            if value.as_array().unwrap().len() == 1 {
-                value = value.as_array().unwrap()[0].clone();
-            }
+impl LowercaseIndex {
+    /// Index a registered method, names sharing a lowercase form are kept in registration order.
+    pub(crate) fn insert(&self, method_name: &str) {
+        let mut index = self.0.lock().unwrap();
+
+        let names = index.entry(method_name.to_lowercase()).or_default();
+
+        if names.iter().any(|name| name == method_name) {
+            return;
         }
 
-        let request = serde_json::from_value(value.clone()).map_err(|e| {
-            log::error!(
-                "parse method({}) params error: {}\r\t origin: {}",
-                method,
-                e,
-                value
+        if let Some(first) = names.first() {
+            log::warn!(
+                "Method {} differs from method {} only in case, case-insensitive calls resolve to {}",
+                method_name,
+                first,
+                first
             );
-            RPCError {
-                code: ErrorCode::InvalidParams,
-                message: format!("{}", e),
-                data: None,
+        }
+
+        names.push(method_name.to_owned());
+    }
+
+    /// Unindex a removed method.
+    pub(crate) fn remove(&self, method_name: &str) {
+        let mut index = self.0.lock().unwrap();
+
+        let lowercase = method_name.to_lowercase();
+
+        if let Some(names) = index.get_mut(&lowercase) {
+            names.retain(|name| name != method_name);
+
+            if names.is_empty() {
+                index.remove(&lowercase);
             }
-        })?;
+        }
+    }
+
+    /// First registered name whose lowercase form is `lowercase`.
+    pub(crate) fn get(&self, lowercase: &str) -> Option<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .get(lowercase)
+            .and_then(|names| names.first())
+            .cloned()
+    }
+}
+
+/// Deserialize method params into `P`.
+///
+/// Params are deserialized straight from their undecoded form, only params not
+/// matching `P` go through [`parse_value_params`].
+pub(crate) fn parse_params<K, P>(method: &str, params: &HandlerParams<K>) -> RPCResult<P>
+where
+    K: Codec,
+    for<'a> P: Deserialize<'a>,
+{
+    let value = match params {
+        Some(params) => match K::decode_params::<P>(params) {
+            Ok(params) => return Ok(params),
+            Err(_) => K::decode_params::<serde_json::Value>(params)?,
+        },
+        None => serde_json::Value::Null,
+    };
+
+    parse_value_params(method, &value)
+}
+
+/// Deserialize method params into `P`.
+///
+/// Missing or `null` params are also accepted as an empty positional list.
+/// When `params` doesn't match `P` but is a one-element array, the lone element is
+/// tried instead, so a single-argument method accepts both `"x"` and `["x"]`, while
+/// methods whose `P` is itself an array type still receive the array unchanged.
+fn parse_value_params<P>(method: &str, value: &serde_json::Value) -> RPCResult<P>
+where
+    for<'a> P: Deserialize<'a>,
+{
+    let err = match P::deserialize(value) {
+        Ok(params) => return Ok(params),
+        Err(err) => err,
+    };
+
+    if value.is_null() {
+        if let Ok(params) = P::deserialize(&serde_json::Value::Array(vec![])) {
+            return Ok(params);
+        }
+    }
+
+    if value.as_array().map(|params| params.len()) == Some(1) {
+        if let Ok((params,)) = <(P,)>::deserialize(value) {
+            return Ok(params);
+        }
+    }
+
+    log::error!(
+        "parse method({}) params error: {}\r\t origin: {}",
+        method,
+        err,
+        value
+    );
+
+    Err(RPCError::new(
+        ErrorCode::InvalidParams,
+        format!("{}", err),
+        None,
+    ))
+}
+
+/// Params of methods without arguments, accepts missing, `null`, `[]` or `{}` params.
+#[derive(Serialize)]
+pub(crate) struct NoParams;
+
+impl<'de> Deserialize<'de> for NoParams {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(NoParamsVisitor)
+    }
+}
+
+struct NoParamsVisitor;
 
-        let response = f(request)?;
+impl<'de> de::Visitor<'de> for NoParamsVisitor {
+    type Value = NoParams;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("no params")
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(NoParams)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(NoParams)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        match seq.next_element::<de::IgnoredAny>()? {
+            Some(_) => Err(de::Error::invalid_length(1, &self)),
+            None => Ok(NoParams),
+        }
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        match map.next_key::<de::IgnoredAny>()? {
+            Some(_) => Err(de::Error::invalid_length(1, &self)),
+            None => Ok(NoParams),
+        }
+    }
+}
+
+/// Parse the params of a call of `method`, invoke `f` and encode its result.
+fn call_handler<K, P, R, F>(
+    method: &str,
+    ctx: Context,
+    params: HandlerParams<K>,
+    f: F,
+) -> RPCResult<Option<RPCData>>
+where
+    K: Codec,
+    F: FnOnce(Context, P) -> RPCResult<Option<R>>,
+    for<'a> P: Deserialize<'a>,
+    R: Serialize,
+{
+    log::trace!("try call method `{}` with params {:?}", method, params);
+
+    let request = parse_params::<K, P>(method, &params)?;
+
+    let id = ctx.id();
+
+    let response = f(ctx, request)?;
+
+    if let Some(id) = id {
+        if let Some(r) = response {
+            let resp = Response::<String, R, ()>::success(id, r);
+
+            let result = K::encode(&resp).map_err(|e| {
+                log::error!(
+                    "parse method({}) response error: {}\r\t origin: {:?}",
+                    method,
+                    e,
+                    params
+                );
+                RPCError::new(ErrorCode::InternalError, "Internal error".to_owned(), None)
+            })?;
+
+            return Ok(Some(result));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Async [`call_handler`].
+fn call_async_handler<K, P, R, F, FR>(
+    method: Arc<str>,
+    ctx: Context,
+    params: HandlerParams<K>,
+    f: F,
+) -> BoxFuture<'static, RPCResult<Option<RPCData>>>
+where
+    K: Codec,
+    F: FnOnce(Context, P) -> FR + Send + 'static,
+    FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
+    for<'a> P: Deserialize<'a> + Send,
+    R: Serialize,
+{
+    Box::pin(async move {
+        log::trace!("try call method `{}` with params {:?}", method, params);
+
+        let request = parse_params::<K, P>(&method, &params)?;
+
+        let id = ctx.id();
+
+        let response = f(ctx, request).await?;
 
         if let Some(id) = id {
             if let Some(r) = response {
-                let resp = Response::<String, R, ()> {
-                    id,
-                    result: Some(r),
-                    ..Default::default()
-                };
-
-                let result = serde_json::to_vec(&resp).map_err(|e| {
-                    log::error!(
-                        "parse method({}) response error: {}\r\t origin: {}",
-                        method,
-                        e,
-                        value
-                    );
-                    RPCError {
-                        code: ErrorCode::InternalError,
-                        message: "Internal error".to_owned(),
-                        data: None,
-                    }
+                let resp = Response::<String, R, ()>::success(id, r);
+
+                let result = K::encode(&resp).map_err(|_| {
+                    RPCError::new(ErrorCode::InternalError, "Internal error".to_owned(), None)
                 })?;
 
-                return Ok(Some(result.into()));
+                return Ok(Some(result));
             }
         }
 
-        Ok(None)
+        Ok::<Option<RPCData>, RPCError>(None)
+    })
+}
+
+pub(crate) fn to_handler<K, P, R, F>(
+    method: Arc<str>,
+    mut f: F,
+) -> HandlerCloner<SyncMethodHandler<K>>
+where
+    K: Codec,
+    F: FnMut(Context, P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+    for<'a> P: Deserialize<'a> + Serialize,
+    R: Serialize + Default,
+{
+    let handler = move |ctx: Context, params: HandlerParams<K>| {
+        call_handler::<K, P, R, _>(&method, ctx, params, &mut f)
     };
 
-    Box::new(move || Box::new(handler.clone()))
+    Box::new(move || SyncMethodHandler::Cloned(Box::new(handler.clone())))
+}
+
+/// Handler shared by all calls of `method`, see [`crate::Server::handle_shared`].
+pub(crate) fn to_shared_handler<K, P, R, F>(
+    method: Arc<str>,
+    f: F,
+) -> HandlerCloner<SyncMethodHandler<K>>
+where
+    K: Codec,
+    F: Fn(Context, P) -> RPCResult<Option<R>> + 'static + Sync + Send,
+    for<'a> P: Deserialize<'a> + Serialize,
+    R: Serialize + Default,
+{
+    let handler: SharedServerHandler<K> =
+        Arc::new(move |ctx, params| call_handler::<K, P, R, _>(&method, ctx, params, &f));
+
+    Box::new(move || SyncMethodHandler::Shared(handler.clone()))
 }
 
-pub(crate) fn to_async_handler<P, R, F, FR>(
-    method: &'static str,
+pub(crate) fn to_async_handler<K, P, R, F, FR>(
+    method: Arc<str>,
     f: F,
-) -> HandlerCloner<AsyncServerHandler>
+) -> HandlerCloner<AsyncMethodHandler<K>>
 where
-    F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
-    FR: std::future::Future<Output = RPCResult<Option<R>>> + Sync + Send + 'static,
+    K: Codec,
+    F: FnMut(Context, P) -> FR + 'static + Sync + Send + Clone,
+    FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
     for<'a> P: Deserialize<'a> + Serialize + Send,
     R: Serialize + Default,
 {
-    let handler =
-        move |id, mut value: serde_json::Value| -> BoxFuture<'static, RPCResult<Option<RPCData>>> {
-            let mut f_call = f.clone();
-            let method_name = method.clone();
-            Box::pin(async move {
-                log::trace!("try call method `{}` with params {}", method_name, value);
+    let handler = move |ctx: Context,
+                        params: HandlerParams<K>|
+          -> BoxFuture<'static, RPCResult<Option<RPCData>>> {
+        let f_call = f.clone();
 
-                if value.is_array() {
-                    if value.as_array().unwrap().len() == 1 {
-                        value = value.as_array().unwrap()[0].clone();
-                    }
-                }
+        call_async_handler::<K, P, R, _, _>(method.clone(), ctx, params, f_call)
+    };
 
-                let request = serde_json::from_value(value).map_err(|e| RPCError {
-                    code: ErrorCode::InvalidParams,
-                    message: format!("{}", e),
-                    data: None,
-                })?;
+    Box::new(move || AsyncMethodHandler::Cloned(Box::new(handler.clone())))
+}
 
-                let response = f_call(request).await?;
+/// Async handler shared by all calls of `method`, see [`crate::Server::async_handle_shared`].
+pub(crate) fn to_shared_async_handler<K, P, R, F, FR>(
+    method: Arc<str>,
+    f: F,
+) -> HandlerCloner<AsyncMethodHandler<K>>
+where
+    K: Codec,
+    F: Fn(Context, P) -> FR + 'static + Sync + Send,
+    FR: std::future::Future<Output = RPCResult<Option<R>>> + Send + 'static,
+    for<'a> P: Deserialize<'a> + Serialize + Send,
+    R: Serialize + Default,
+{
+    let f = Arc::new(f);
+
+    let handler: SharedAsyncServerHandler<K> = Arc::new(move |ctx, params| {
+        let f = f.clone();
+
+        call_async_handler::<K, P, R, _, _>(method.clone(), ctx, params, move |ctx, params| {
+            f(ctx, params)
+        })
+    });
+
+    Box::new(move || AsyncMethodHandler::Shared(handler.clone()))
+}
 
-                if let Some(id) = id {
-                    if let Some(r) = response {
-                        let resp = Response::<String, R, ()> {
-                            id,
-                            result: Some(r),
-                            ..Default::default()
-                        };
+pub(crate) fn to_default_handler<K, F, FR>(f: F) -> DefaultHandler<K>
+where
+    K: Codec,
+    F: Fn(&str, Option<usize>, serde_json::Value) -> FR + 'static + Sync + Send,
+    FR: std::future::Future<Output = RPCResult<Option<serde_json::Value>>> + Send + 'static,
+{
+    Arc::new(
+        move |method: &str,
+              ctx: Context,
+              params: HandlerParams<K>|
+              -> BoxFuture<'static, RPCResult<Option<RPCData>>> {
+            log::trace!(
+                "try call default handler for `{}` with params {:?}",
+                method,
+                params
+            );
+
+            let id = ctx.id();
 
-                        let result = serde_json::to_vec(&resp).map_err(|_| RPCError {
-                            code: ErrorCode::InternalError,
-                            message: "Internal error".to_owned(),
-                            data: None,
-                        })?;
+            let call = params
+                .as_ref()
+                .map_or(Ok(serde_json::Value::Null), K::decode_params)
+                .map(|value| f(method, id, value));
 
-                        return Ok(Some(result.into()));
-                    }
+            Box::pin(async move {
+                let response = call?.await?;
+
+                if let (Some(id), Some(r)) = (id, response) {
+                    let resp = Response::<String, serde_json::Value, ()>::success(id, r);
+
+                    return Ok(Some(K::encode(&resp)?));
                 }
 
-                Ok::<Option<RPCData>, RPCError>(None)
+                Ok(None)
             })
-        };
-
-    Box::new(move || Box::new(handler.clone()))
+        },
+    )
 }
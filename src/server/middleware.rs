@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use serde::Deserialize;
+
+use crate::{channel::RPCData, Codec, RPCResult};
+
+use super::{
+    handler::{parse_params, HandlerParams},
+    Context,
+};
+
+/// Result of a call passed along the middleware chain: the encoded response, `None`
+/// for notifications, or the error the session answers the call with.
+pub type CallResult = RPCResult<Option<RPCData>>;
+
+/// Middleware running around every handler invocation of a session, see [`ServerBuilder::layer`](super::ServerBuilder::layer).
+///
+/// [`Middleware::around`] sees the call before dispatch and may short-circuit it by
+/// returning without running `next`, e.g. rejecting an unauthorized call with a custom
+/// error, or run `next` and inspect or transform its result. A successful result is the
+/// encoded response, decode it with the server's [`Codec`] as a [`Response`](crate::Response).
+pub trait Middleware<K: Codec>: Send + Sync + 'static {
+    fn around(&self, call: Call<K>, next: Next<K>) -> BoxFuture<'static, CallResult>;
+}
+
+impl<K, F> Middleware<K> for F
+where
+    K: Codec,
+    F: Fn(Call<K>, Next<K>) -> BoxFuture<'static, CallResult> + Send + Sync + 'static,
+{
+    fn around(&self, call: Call<K>, next: Next<K>) -> BoxFuture<'static, CallResult> {
+        self(call, next)
+    }
+}
+
+/// One handler invocation passed along the middleware chain.
+pub struct Call<K: Codec> {
+    pub(crate) ctx: Context,
+    pub(crate) params: HandlerParams<K>,
+}
+
+impl<K: Codec> Call<K> {
+    /// Context the handler is invoked with.
+    pub fn context(&self) -> &Context {
+        &self.ctx
+    }
+
+    /// Id of the call, `None` for notifications.
+    pub fn id(&self) -> Option<usize> {
+        self.ctx.id()
+    }
+
+    /// Name of the called method.
+    pub fn method(&self) -> &str {
+        self.ctx.method()
+    }
+
+    /// Deserialize the params into `P`, the same way handlers do.
+    pub fn params<P>(&self) -> RPCResult<P>
+    where
+        for<'a> P: Deserialize<'a>,
+    {
+        parse_params::<K, P>(self.ctx.method(), &self.params)
+    }
+}
+
+/// Invocation of the handler at the end of the chain.
+pub(crate) type Endpoint<K> = Box<dyn FnOnce(Call<K>) -> BoxFuture<'static, CallResult> + Send>;
+
+/// Rest of the middleware chain, ending with the handler.
+pub struct Next<K: Codec> {
+    middlewares: Arc<Vec<Arc<dyn Middleware<K>>>>,
+    index: usize,
+    endpoint: Endpoint<K>,
+}
+
+impl<K: Codec> Next<K> {
+    pub(crate) fn new(
+        middlewares: Arc<Vec<Arc<dyn Middleware<K>>>>,
+        endpoint: Endpoint<K>,
+    ) -> Self {
+        Self {
+            middlewares,
+            index: 0,
+            endpoint,
+        }
+    }
+
+    /// Pass `call` to the next middleware, or the handler once the chain is through.
+    pub fn run(self, call: Call<K>) -> BoxFuture<'static, CallResult> {
+        match self.middlewares.clone().get(self.index) {
+            Some(middleware) => middleware.around(
+                call,
+                Self {
+                    index: self.index + 1,
+                    ..self
+                },
+            ),
+            None => (self.endpoint)(call),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Codec, RPCResult};
+
+use super::{handler::NoParams, Context, Server};
+
+/// Registrar of the methods of one namespace, see [`Server::namespace`].
+///
+/// Every method is registered on the server as `{namespace}.{method}`.
+pub struct Namespace<'a, K: Codec> {
+    server: &'a mut Server<K>,
+    prefix: String,
+}
+
+impl<'a, K: Codec> Namespace<'a, K> {
+    pub(crate) fn new(server: &'a mut Server<K>, prefix: String) -> Self {
+        Self { server, prefix }
+    }
+
+    /// Full name of `method` in this namespace.
+    fn method(&self, method: &str) -> String {
+        format!("{}.{}", self.prefix, method)
+    }
+
+    /// Nested namespace, whose methods are registered as `{namespace}.{name}.{method}`.
+    pub fn namespace(&mut self, name: &str) -> Namespace<'_, K> {
+        let prefix = format!("{}.{}", self.prefix, name);
+
+        Namespace::new(self.server, prefix)
+    }
+
+    /// See [`Server::handle`].
+    pub fn handle<P, R, F>(&mut self, method: &str, mut f: F) -> &mut Self
+    where
+        F: FnMut(P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        for<'b> P: Deserialize<'b> + Serialize,
+        R: Serialize + Default,
+    {
+        self.server
+            .register_sync(self.method(method), move |_, params| f(params));
+
+        self
+    }
+
+    /// See [`Server::handle0`].
+    pub fn handle0<R, F>(&mut self, method: &str, mut f: F) -> &mut Self
+    where
+        F: FnMut() -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        R: Serialize + Default,
+    {
+        self.server
+            .register_sync(self.method(method), move |_, _: NoParams| f());
+
+        self
+    }
+
+    /// See [`Server::handle_with_ctx`].
+    pub fn handle_with_ctx<P, R, F>(&mut self, method: &str, f: F) -> &mut Self
+    where
+        F: FnMut(Context, P) -> RPCResult<Option<R>> + 'static + Clone + Sync + Send,
+        for<'b> P: Deserialize<'b> + Serialize,
+        R: Serialize + Default,
+    {
+        self.server.register_sync(self.method(method), f);
+
+        self
+    }
+
+    /// See [`Server::async_handle`].
+    pub fn async_handle<P, R, F, FR>(&mut self, method: &str, mut f: F) -> &mut Self
+    where
+        F: FnMut(P) -> FR + 'static + Sync + Send + Clone,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'b> P: Deserialize<'b> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.server
+            .register_async(self.method(method), move |_, params| f(params));
+
+        self
+    }
+
+    /// See [`Server::async_handle0`].
+    pub fn async_handle0<R, F, FR>(&mut self, method: &str, mut f: F) -> &mut Self
+    where
+        F: FnMut() -> FR + 'static + Sync + Send + Clone,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        R: Serialize + Default,
+    {
+        self.server
+            .register_async(self.method(method), move |_, _: NoParams| f());
+
+        self
+    }
+
+    /// See [`Server::async_handle_with_ctx`].
+    pub fn async_handle_with_ctx<P, R, F, FR>(&mut self, method: &str, f: F) -> &mut Self
+    where
+        F: FnMut(Context, P) -> FR + 'static + Sync + Send + Clone,
+        FR: Future<Output = RPCResult<Option<R>>> + Send + 'static,
+        for<'b> P: Deserialize<'b> + Serialize + Send,
+        R: Serialize + Default,
+    {
+        self.server.register_async(self.method(method), f);
+
+        self
+    }
+}
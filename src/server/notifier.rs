@@ -0,0 +1,46 @@
+use serde::Serialize;
+
+use crate::{ErrorCode, RPCError, RPCResult};
+
+use super::session::SessionHandle;
+
+/// Pushes notifications to the client of one session while a call executes,
+/// see [`Context::notifier`](super::Context::notifier).
+///
+/// Notifications are encoded with the codec of the session and queued to it, which
+/// writes each frame whole between its responses. Those queued by a call are written
+/// before the call's response.
+#[derive(Clone)]
+pub struct Notifier {
+    session: SessionHandle,
+}
+
+impl Notifier {
+    pub(crate) fn new(session: SessionHandle) -> Self {
+        Self { session }
+    }
+
+    /// Send the notification `method` to the client.
+    ///
+    /// Fails with [`ErrorCode::ConnectionClosed`] once the session closed. Like
+    /// [`Server::broadcast`](super::Server::broadcast) the notification is queued
+    /// without waiting for the write.
+    pub fn notify<P>(&self, method: &str, params: P) -> RPCResult<()>
+    where
+        P: Serialize,
+    {
+        let frame = self
+            .session
+            .encode_notification(method, &serde_json::to_value(params)?)?;
+
+        if !self.session.send(frame) {
+            return Err(RPCError::new(
+                ErrorCode::ConnectionClosed,
+                format!("Session closed, notification {} dropped", method),
+                None,
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::{ErrorCode, RPCError};
+
+use super::{Context, RATE_LIMITED};
+
+/// Token bucket admitting `rate` calls per second on average, in bursts of up to `rate` calls.
+pub(crate) struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(per_second: u32) -> Self {
+        let rate = f64::from(per_second.max(1));
+
+        Self {
+            rate,
+            tokens: rate,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Take one token, returns false if the bucket is empty.
+    pub(crate) fn try_acquire(&mut self) -> bool {
+        let now = Instant::now();
+
+        self.tokens = (self.tokens + now.duration_since(self.refilled).as_secs_f64() * self.rate)
+            .min(self.rate);
+        self.refilled = now;
+
+        if self.tokens < 1.0 {
+            return false;
+        }
+
+        self.tokens -= 1.0;
+
+        true
+    }
+}
+
+/// Token buckets of one session keyed by method, stored in the session [`Extensions`](super::Extensions)
+/// so they are dropped with the session.
+#[derive(Clone, Default)]
+struct SessionBuckets(Arc<Mutex<HashMap<&'static str, TokenBucket>>>);
+
+/// Take one token of the `method` bucket of the session of `ctx`.
+pub(crate) fn try_acquire_session(ctx: &Context, method: &'static str, per_second: u32) -> bool {
+    let buckets = ctx.extensions().get_or_insert_with(SessionBuckets::default);
+
+    let mut buckets = buckets.0.lock().unwrap();
+
+    buckets
+        .entry(method)
+        .or_insert_with(|| TokenBucket::new(per_second))
+        .try_acquire()
+}
+
+/// Error of a call rejected by a rate limit.
+pub(crate) fn rate_limited(method: &str) -> RPCError {
+    log::warn!("Method {} rate limited", method);
+
+    let message = format!("Method {} rate limited", method);
+
+    RPCError::new(
+        ErrorCode::ServerError(RATE_LIMITED, message.clone()),
+        message,
+        None,
+    )
+}
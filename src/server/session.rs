@@ -1,78 +1,726 @@
-use futures::{SinkExt, TryStreamExt};
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::Arc,
+    task::Poll,
+    time::Instant,
+};
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    future::{poll_fn, AbortHandle, AbortRegistration, BoxFuture},
+    stream::FuturesUnordered,
+    FutureExt, SinkExt, StreamExt, TryStreamExt,
+};
+
+use serde::{de::IgnoredAny, Serialize};
 
 use crate::{
     channel::{RPCData, TransportChannel},
-    map_error, Error, ErrorCode, RPCResult, Request, Response,
+    timer::{timeout, Timeout},
+    trace::{handler_span, session_span, Instrument},
+    Codec, CompatRequest, CowStr, Error, ErrorCode, LegacyResponse, LegacyVersion, LenientRequest,
+    RPCError, RPCResult, Request, Response, Version,
 };
 
-use super::handler::*;
+use super::{
+    handler::{parse_params, HandlerParams},
+    middleware::{Call, Endpoint, Next},
+    CancellationToken, Context, Extensions, SendErrorPolicy, Server, CANCEL_METHOD,
+};
+
+/// Result of one handler call.
+struct Completion {
+    id: Option<usize>,
+    method: String,
+    /// Instant the request was received, only taken with [`ServerBuilder::metrics`] or
+    /// [`ServerBuilder::slow_call_threshold`] set.
+    received: Option<Instant>,
+    /// Version of a JSON-RPC 1.0 request, see [`ServerBuilder::compat_v1`].
+    legacy: Option<LegacyVersion>,
+    result: RPCResult<Option<RPCData>>,
+}
+
+/// Async handler call, or in ordered mode any call, see [`ServiceSession::dispatch`].
+type PendingCall = BoxFuture<'static, Completion>;
+
+enum SessionEvent {
+    Frame(Option<RPCData>),
+    /// No frame arrived within [`ServerBuilder::idle_timeout`].
+    Idle,
+    Completed(usize, Completion),
+    Command(SessionCommand),
+}
+
+/// Request of the server to a running session, see [`SessionHandle`].
+pub(crate) enum SessionCommand {
+    /// Write a frame initiated by the server, e.g. a broadcast notification.
+    Send(RPCData),
+    /// Stop reading requests, the session ends once pending calls are answered.
+    Close,
+}
+
+/// Encodes a notification with the codec of the session.
+type EncodeNotification = fn(&str, &serde_json::Value) -> RPCResult<RPCData>;
+
+fn encode_notification<K: Codec>(method: &str, params: &serde_json::Value) -> RPCResult<RPCData> {
+    K::encode(&Request::notification(method, params))
+}
 
-pub struct ServiceSession<C: TransportChannel> {
-    id: String,
+/// Handle of a running session, kept in the session registry of [`Server`].
+#[derive(Clone)]
+pub(crate) struct SessionHandle {
+    commands: UnboundedSender<SessionCommand>,
+    task: AbortHandle,
+    encode_notification: EncodeNotification,
+}
+
+impl SessionHandle {
+    /// Encode the notification `method` with the codec of the session.
+    pub(crate) fn encode_notification(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> RPCResult<RPCData> {
+        (self.encode_notification)(method, params)
+    }
+
+    /// Queue `frame` to be written by the session, returns false if the session ended.
+    pub(crate) fn send(&self, frame: RPCData) -> bool {
+        self.commands
+            .unbounded_send(SessionCommand::Send(frame))
+            .is_ok()
+    }
+
+    /// Ask the session to close, see [`SessionCommand::Close`].
+    pub(crate) fn close(&self) {
+        _ = self.commands.unbounded_send(SessionCommand::Close);
+    }
+
+    /// Abort the task running the session, dropping its pending calls and its transport.
+    pub(crate) fn abort(&self) {
+        self.task.abort();
+    }
+}
+
+pub struct ServiceSession<C: TransportChannel, K: Codec> {
+    id: Arc<str>,
+    extensions: Extensions,
     input: C::Input,
     output: C::Output,
-    methods: HandlerClonerRegister<ServerHandler>,
-    async_methods: HandlerClonerRegister<AsyncServerHandler>,
+    server: Server<K>,
+    /// Responses queued for one coalesced write.
+    batch: Vec<RPCData>,
+    handle: SessionHandle,
+    commands: UnboundedReceiver<SessionCommand>,
+    /// Canceled once the connection closed, parent of the tokens of all calls.
+    cancellation: CancellationToken,
+    /// Tokens of the async calls in flight, by request id.
+    inflight: HashMap<usize, CancellationToken>,
+    /// Completion held back until the frames its call queued are written.
+    completed: Option<(usize, Completion)>,
+    /// Registration of [`SessionHandle::abort`], taken by the task running the session.
+    registration: Option<AbortRegistration>,
+    /// Timer of [`ServerBuilder::idle_timeout`], restarted on every received frame.
+    idle: Option<Timeout>,
+    /// Set once a close command was taken while writing queued frames.
+    closing: bool,
 }
 
-impl<C: TransportChannel> ServiceSession<C> {
-    pub(crate) fn new(
-        id: String,
-        input: C::Input,
-        output: C::Output,
-        methods: HandlerClonerRegister<ServerHandler>,
-        async_methods: HandlerClonerRegister<AsyncServerHandler>,
-    ) -> Self {
+impl<C: TransportChannel, K: Codec> ServiceSession<C, K> {
+    pub(crate) fn new(id: String, input: C::Input, output: C::Output, server: Server<K>) -> Self {
+        let (commands, commands_receiver) = mpsc::unbounded();
+
+        let (task, registration) = AbortHandle::new_pair();
+
+        let idle = server.idle_timeout.map(timeout);
+
         Self {
-            id,
+            id: id.into(),
+            extensions: Extensions::default(),
             input,
             output,
-            methods,
-            async_methods,
+            server,
+            batch: vec![],
+            handle: SessionHandle {
+                commands,
+                task,
+                encode_notification: encode_notification::<K>,
+            },
+            commands: commands_receiver,
+            cancellation: CancellationToken::new(),
+            inflight: HashMap::new(),
+            completed: None,
+            registration: Some(registration),
+            idle,
+            closing: false,
         }
     }
+
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub(crate) fn handle(&self) -> SessionHandle {
+        self.handle.clone()
+    }
+
+    /// Registration making the task which runs the session abortable by its handle.
+    pub(crate) fn abort_registration(&mut self) -> AbortRegistration {
+        self.registration
+            .take()
+            .expect("Abort registration of a session taken twice")
+    }
+
     pub async fn run(&mut self) -> RPCResult<()> {
-        while let Some(next) = self.input.try_next().await.map_err(map_error)? {
-            let request = serde_json::from_slice::<Request<&str, serde_json::Value>>(&next)?;
-
-            if let Some(mut handler) = self.methods.clone_from(request.method) {
-                self.handle_resp(
-                    request.id,
-                    request.method,
-                    handler(request.id, request.params),
-                )
+        let span = session_span(&self.id);
+
+        self.serve().instrument(span).await
+    }
+
+    async fn serve(&mut self) -> RPCResult<()> {
+        let mut pending = FuturesUnordered::<BoxFuture<'static, (usize, Completion)>>::new();
+
+        // Completed calls waiting for earlier ones in ordered mode, keyed by sequence number.
+        let mut reorder = BTreeMap::new();
+        let mut next_seq = 0;
+        let mut next_response = 0;
+
+        loop {
+            // Coalesced responses are written once no further event is ready.
+            let event = if self.batch.is_empty() {
+                self.next_event(&mut pending).await?
+            } else if let Some(event) = self.next_event(&mut pending).now_or_never() {
+                event?
+            } else {
+                self.flush().await?;
+
+                self.next_event(&mut pending).await?
+            };
+
+            match event {
+                SessionEvent::Frame(Some(next)) => {
+                    if let Some(call) = self.dispatch(&next).await? {
+                        let seq = next_seq;
+
+                        next_seq += 1;
+
+                        pending.push(Box::pin(call.map(move |completion| (seq, completion))));
+                    }
+                }
+                SessionEvent::Frame(None) => {
+                    // Nobody is left to receive the responses of pending calls.
+                    self.cancellation.cancel();
+
+                    break;
+                }
+                SessionEvent::Idle => {
+                    log::info!("Server session {} idle, closing", self.id);
+
+                    break;
+                }
+                SessionEvent::Command(SessionCommand::Close) => break,
+                SessionEvent::Command(SessionCommand::Send(frame)) => {
+                    self.write_notification(frame).await?
+                }
+                SessionEvent::Completed(seq, completion) => {
+                    self.complete(seq, completion, &mut reorder, &mut next_response)
+                        .await?;
+                }
+            }
+        }
+
+        if let Some((seq, completion)) = self.completed.take() {
+            self.complete(seq, completion, &mut reorder, &mut next_response)
                 .await?;
-            } else if let Some(mut handler) = self.async_methods.clone_from(request.method) {
-                self.handle_resp(
-                    request.id,
-                    request.method,
-                    handler(request.id, request.params).await,
-                )
+        }
+
+        while let Some((seq, completion)) = pending.next().await {
+            // Frames queued by the call go out before its response, as in the loop above.
+            self.write_queued().await?;
+
+            self.complete(seq, completion, &mut reorder, &mut next_response)
                 .await?;
-            }
         }
 
+        self.flush().await?;
+
         log::info!("Server session {} stop.", self.id);
 
         Ok(())
     }
 
+    /// Wait for the next server command, incoming frame or completed call.
+    async fn next_event(
+        &mut self,
+        pending: &mut FuturesUnordered<BoxFuture<'static, (usize, Completion)>>,
+    ) -> RPCResult<SessionEvent> {
+        poll_fn(|cx| {
+            if std::mem::take(&mut self.closing) {
+                return Poll::Ready(Ok(SessionEvent::Command(SessionCommand::Close)));
+            }
+
+            if let Poll::Ready(Some(command)) = self.commands.poll_next_unpin(cx) {
+                return Poll::Ready(Ok(SessionEvent::Command(command)));
+            }
+
+            if let Some((seq, completion)) = self.completed.take() {
+                return Poll::Ready(Ok(SessionEvent::Completed(seq, completion)));
+            }
+
+            if let Poll::Ready(next) = self.input.try_poll_next_unpin(cx) {
+                if let Some(idle) = self.server.idle_timeout {
+                    self.idle = Some(timeout(idle));
+                }
+
+                return Poll::Ready(
+                    next.transpose()
+                        .map(SessionEvent::Frame)
+                        .map_err(RPCError::from_transport),
+                );
+            }
+
+            if let Some(Poll::Ready(_)) = self.idle.as_mut().map(|idle| idle.poll_unpin(cx)) {
+                return Poll::Ready(Ok(SessionEvent::Idle));
+            }
+
+            match pending.poll_next_unpin(cx) {
+                Poll::Ready(Some((seq, completion))) => {
+                    // Frames queued by the call, e.g. partial results, go out before its response.
+                    if let Poll::Ready(Some(command)) = self.commands.poll_next_unpin(cx) {
+                        self.completed = Some((seq, completion));
+
+                        return Poll::Ready(Ok(SessionEvent::Command(command)));
+                    }
+
+                    Poll::Ready(Ok(SessionEvent::Completed(seq, completion)))
+                }
+                _ => Poll::Pending,
+            }
+        })
+        .await
+    }
+
+    /// Write the frames queued to the session so far, e.g. the notifications of a call
+    /// completed inline, which go out before its response.
+    async fn write_queued(&mut self) -> RPCResult<()> {
+        while let Ok(command) = self.commands.try_recv() {
+            match command {
+                SessionCommand::Send(frame) => self.write_notification(frame).await?,
+                SessionCommand::Close => self.closing = true,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write one response frame, with [`ServerBuilder::coalesce_responses`] it's queued
+    /// into the next batch instead.
+    async fn write(&mut self, frame: RPCData) -> RPCResult<()> {
+        let Some(max) = self.server.coalesce_responses else {
+            return self.send(frame).await;
+        };
+
+        self.batch.push(frame);
+
+        if self.batch.len() >= max {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a frame initiated by the server, e.g. a notification, which is never
+    /// coalesced, as a batch mixing it with responses isn't a valid response batch.
+    async fn write_notification(&mut self, frame: RPCData) -> RPCResult<()> {
+        self.flush().await?;
+
+        self.send(frame).await
+    }
+
+    /// Write the queued responses as one batch frame.
+    async fn flush(&mut self) -> RPCResult<()> {
+        let frame = match self.batch.len() {
+            0 => return Ok(()),
+            1 => self.batch.pop().unwrap(),
+            _ => {
+                let frame = K::encode_batch(&self.batch);
+
+                self.batch.clear();
+
+                frame
+            }
+        };
+
+        self.send(frame).await
+    }
+
+    /// Write one frame to the transport, handling failures by [`ServerBuilder::on_send_error`].
+    async fn send(&mut self, frame: RPCData) -> RPCResult<()> {
+        let mut retries = 0;
+
+        loop {
+            let err = match self.output.send(frame.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(err) => RPCError::from_transport(err),
+            };
+
+            match self.server.on_send_error {
+                SendErrorPolicy::Continue => {
+                    log::warn!("Server session {} dropped frame, {}", self.id, err);
+
+                    return Ok(());
+                }
+                SendErrorPolicy::Retry(max) if retries < max => {
+                    retries += 1;
+
+                    log::warn!(
+                        "Server session {} write failed, retry {} of {}, {}",
+                        self.id,
+                        retries,
+                        max,
+                        err
+                    );
+                }
+                _ => return Err(err),
+            }
+        }
+    }
+
+    /// Write the response of a completed call.
+    ///
+    /// In ordered mode the response is buffered until all calls received before it
+    /// completed, then flushed together with the buffered responses following it.
+    async fn complete(
+        &mut self,
+        seq: usize,
+        completion: Completion,
+        reorder: &mut BTreeMap<usize, Completion>,
+        next_response: &mut usize,
+    ) -> RPCResult<()> {
+        if !self.server.ordered_responses {
+            return self.handle_completion(completion).await;
+        }
+
+        reorder.insert(seq, completion);
+
+        while let Some(completion) = reorder.remove(next_response) {
+            *next_response += 1;
+
+            self.handle_completion(completion).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn handle_completion(&mut self, completion: Completion) -> RPCResult<()> {
+        let Completion {
+            id,
+            method,
+            received,
+            legacy,
+            result,
+        } = completion;
+
+        if let Some(id) = id {
+            self.inflight.remove(&id);
+        }
+
+        self.handle_resp(id, &method, received, legacy, result)
+            .await
+    }
+
+    /// Write the response of a call completed inline, in ordered mode it's returned
+    /// as a completed call to be queued behind earlier calls instead.
+    async fn respond(
+        &mut self,
+        id: Option<usize>,
+        method: &str,
+        received: Option<Instant>,
+        legacy: Option<LegacyVersion>,
+        result: RPCResult<Option<RPCData>>,
+    ) -> RPCResult<Option<PendingCall>> {
+        if self.server.ordered_responses {
+            let completion = Completion {
+                id,
+                method: method.to_owned(),
+                received,
+                legacy,
+                result,
+            };
+
+            return Ok(Some(Box::pin(futures::future::ready(completion))));
+        }
+
+        self.write_queued().await?;
+
+        self.handle_resp(id, method, received, legacy, result)
+            .await?;
+
+        Ok(None)
+    }
+
+    /// Invoke the handler of one incoming frame.
+    ///
+    /// Sync handlers are completed inline, async handler calls are returned to be
+    /// polled concurrently. In ordered mode every call is returned, so its response
+    /// can be written in arrival order.
+    async fn dispatch(&mut self, next: &RPCData) -> RPCResult<Option<PendingCall>> {
+        if let Some(max) = self.server.max_frame_size.filter(|max| next.len() > *max) {
+            log::warn!(
+                "Server session {} dropped frame of {} bytes, exceeding max frame size {}",
+                self.id,
+                next.len(),
+                max
+            );
+
+            let resp = Self::new_null_id_error_resp(RPCError::new(
+                ErrorCode::InvalidRequest,
+                format!("Frame exceeds max frame size of {} bytes", max),
+                None,
+            ));
+
+            self.write(resp).await?;
+
+            return Ok(None);
+        }
+
+        // `params` MAY be omitted, which is handled the same as `null`.
+        let decoded = if self.server.compat_v1 {
+            K::decode::<CompatRequest<CowStr, HandlerParams<K>>>(next).map(CompatRequest::split)
+        } else if self.server.lenient_version {
+            K::decode::<LenientRequest<CowStr, HandlerParams<K>>>(next)
+                .map(|request| (request.into(), None))
+        } else {
+            K::decode::<Request<CowStr, HandlerParams<K>>>(next).map(|request| (request, None))
+        };
+
+        let (request, legacy) = match decoded {
+            Ok(decoded) => decoded,
+            Err(err) => {
+                log::warn!(
+                    "Server session {} received malformed request, {}",
+                    self.id,
+                    err
+                );
+
+                // Well-formed data which isn't a request object is an invalid request.
+                let code = if K::decode::<IgnoredAny>(next).is_ok() {
+                    ErrorCode::InvalidRequest
+                } else {
+                    ErrorCode::ParseError
+                };
+
+                let resp = Self::new_null_id_error_resp(RPCError::new(code, err.message, None));
+
+                self.write(resp).await?;
+
+                return Ok(None);
+            }
+        };
+
+        let params = request.params;
+
+        let resolved = self.server.resolve_method(request.method.as_ref());
+
+        let method = resolved.as_deref().unwrap_or(request.method.as_ref());
+
+        let received = (self.server.metrics.is_some() || self.server.slow_call_threshold.is_some())
+            .then(|| {
+                if let Some(metrics) = &self.server.metrics {
+                    metrics.on_request(method);
+                }
+
+                Instant::now()
+            });
+
+        #[cfg(feature = "traceparent")]
+        let traceparent = request.traceparent.as_ref().map(AsRef::as_ref);
+        #[cfg(not(feature = "traceparent"))]
+        let traceparent = None;
+
+        let span = handler_span(method, request.id, traceparent);
+
+        if method == CANCEL_METHOD && !self.server.has_method(CANCEL_METHOD) {
+            let result = self.cancel(request.id, &params);
+
+            return self
+                .respond(request.id, method, received, legacy, result)
+                .await;
+        }
+
+        let cancellation = self.cancellation.child_token();
+
+        let ctx = self.context(request.id, method, cancellation.clone());
+
+        #[cfg(feature = "traceparent")]
+        let ctx = ctx.with_traceparent(traceparent);
+
+        let endpoint: Endpoint<K> = if let Some(handler) = self.server.methods.clone_from(method) {
+            if self.server.middlewares.is_empty() {
+                let result = span
+                    .in_scope(|| catch_unwind(AssertUnwindSafe(|| handler.call(ctx, params))))
+                    .unwrap_or_else(|payload| Err(panic_error(method, payload)));
+
+                return self
+                    .respond(request.id, method, received, legacy, result)
+                    .await;
+            }
+
+            Box::new(move |call: Call<K>| {
+                let method = call.ctx.method().to_owned();
+
+                let result = catch_unwind(AssertUnwindSafe(|| handler.call(call.ctx, call.params)))
+                    .unwrap_or_else(|payload| Err(panic_error(&method, payload)));
+
+                Box::pin(futures::future::ready(result))
+            })
+        } else if let Some(result) = self.server.builtin_call(method, request.id) {
+            return self
+                .respond(request.id, method, received, legacy, result)
+                .await;
+        } else if let Some(handler) = self.server.async_methods.clone_from(method) {
+            Box::new(move |call: Call<K>| handler.call(call.ctx, call.params))
+        } else if let Some(handler) = self.server.default_handler.clone() {
+            Box::new(move |call: Call<K>| {
+                let method = call.ctx.method().to_owned();
+
+                handler(&method, call.ctx, call.params)
+            })
+        } else {
+            log::warn!("Method {} not found", method);
+
+            let err = RPCError::new(
+                ErrorCode::MethodNotFound,
+                format!("Method {} not found", method),
+                None,
+            );
+
+            return self
+                .respond(request.id, method, received, legacy, Err(err))
+                .await;
+        };
+
+        let call = Call { ctx, params };
+
+        let call = if self.server.middlewares.is_empty() {
+            endpoint(call)
+        } else {
+            span.in_scope(|| Next::new(self.server.middlewares.clone(), endpoint).run(call))
+        };
+
+        let panicked = method.to_owned();
+
+        let call = AssertUnwindSafe(call.instrument(span))
+            .catch_unwind()
+            .map(move |result| {
+                result.unwrap_or_else(|payload| Err(panic_error(&panicked, payload)))
+            });
+
+        let id = request.id;
+        let method = method.to_owned();
+
+        if let Some(id) = id {
+            self.inflight.insert(id, cancellation);
+        }
+
+        Ok(Some(Box::pin(async move {
+            Completion {
+                id,
+                method,
+                received,
+                legacy,
+                result: call.await,
+            }
+        })))
+    }
+
+    fn context(&self, id: Option<usize>, method: &str, cancellation: CancellationToken) -> Context {
+        Context::new(
+            self.id.clone(),
+            id,
+            method.into(),
+            self.extensions.clone(),
+            self.server.states.clone(),
+            self.handle.clone(),
+            cancellation,
+        )
+    }
+
+    /// Cancel the in-flight call whose id is the only param of a [`CANCEL_METHOD`] request.
+    ///
+    /// A cancel call, rather than notification, is answered with true if the call was in flight.
+    fn cancel(&self, id: Option<usize>, params: &HandlerParams<K>) -> RPCResult<Option<RPCData>> {
+        let canceled = parse_params::<K, usize>(CANCEL_METHOD, params)?;
+
+        let found = match self.inflight.get(&canceled) {
+            Some(cancellation) => {
+                cancellation.cancel();
+
+                true
+            }
+            None => false,
+        };
+
+        id.map(|id| K::encode(&Response::<String, _, ()>::success(id, found)))
+            .transpose()
+    }
+
     async fn handle_resp(
         &mut self,
         id: Option<usize>,
         method: &str,
+        received: Option<Instant>,
+        legacy: Option<LegacyVersion>,
         result: RPCResult<Option<RPCData>>,
     ) -> RPCResult<()> {
+        if let Some(received) = received {
+            let elapsed = received.elapsed();
+
+            if let Some(metrics) = &self.server.metrics {
+                metrics.on_response(method, elapsed, result.is_err());
+            }
+
+            if self
+                .server
+                .slow_call_threshold
+                .is_some_and(|threshold| elapsed > threshold)
+            {
+                log::warn!(
+                    "Server session {} method {} slow, took {:?}",
+                    self.id,
+                    method,
+                    elapsed
+                );
+            }
+        }
+
         match result {
             Ok(Some(response)) => {
-                self.output.send(response).await.map_err(map_error)?;
+                let response = match legacy {
+                    Some(version) => Self::legacy_resp(version, &response)?,
+                    None => response,
+                };
+
+                self.write(response).await?;
             }
-            Err(code) => {
+            Err(err) => {
                 if let Some(id) = id {
-                    let resp = Self::new_error_resp(id, code.code, Some(code.message));
-                    self.output.send(resp).await.map_err(map_error)?;
+                    let resp = match legacy {
+                        Some(version) => K::encode(&LegacyResponse::<(), _>::new(
+                            version,
+                            id,
+                            None,
+                            Some(response_error(err)),
+                        ))
+                        .expect("Inner error, serialize jsonrpc response"),
+                        None => Self::new_error_resp(id, err),
+                    };
+
+                    self.write(resp).await?;
                 } else {
-                    log::trace!("Method {} call return error, {}", method, code);
+                    log::trace!("Method {} call return error, {}", method, err);
                 }
             }
             _ => {}
@@ -81,19 +729,76 @@ impl<C: TransportChannel> ServiceSession<C> {
         Ok(())
     }
 
-    fn new_error_resp(id: usize, code: ErrorCode, message: Option<String>) -> RPCData {
-        let response = Response::<String, (), ()> {
-            id,
-            error: Some(Error {
-                code: code.clone(),
-                message: message.unwrap_or(code.to_string()),
-                data: None,
-            }),
-            ..Default::default()
+    fn new_error_resp(id: usize, err: RPCError) -> RPCData {
+        let response = Response::<String, (), serde_json::Value>::error(id, response_error(err));
+
+        K::encode(&response).expect("Inner error, serialize jsonrpc response")
+    }
+
+    /// Re-encode the 2.0 response written by a handler as a 1.0 response.
+    fn legacy_resp(version: LegacyVersion, response: &RPCData) -> RPCResult<RPCData> {
+        let response: Response<String, serde_json::Value, serde_json::Value> = K::decode(response)?;
+
+        K::encode(&LegacyResponse::new(
+            version,
+            response.id,
+            response.result,
+            response.error,
+        ))
+    }
+
+    /// Error response to a request whose id couldn't be determined.
+    fn new_null_id_error_resp(err: RPCError) -> RPCData {
+        let response = NullIdResponse {
+            id: (),
+            jsonrpc: Version,
+            error: response_error(err),
         };
 
-        serde_json::to_vec(&response)
-            .expect("Inner error, serialize jsonrpc response")
-            .into()
+        K::encode(&response).expect("Inner error, serialize jsonrpc response")
     }
 }
+
+impl<C: TransportChannel, K: Codec> Drop for ServiceSession<C, K> {
+    fn drop(&mut self) {
+        self.cancellation.cancel();
+
+        self.server.unregister_session(&self.id);
+    }
+}
+
+/// Error [`Response`] with a `null` id.
+#[derive(Serialize)]
+struct NullIdResponse {
+    id: (),
+    jsonrpc: Version,
+    error: Error<String, serde_json::Value>,
+}
+
+/// Strip the local error source, defaulting an empty message to the code description.
+fn response_error(err: RPCError) -> Error<String, serde_json::Value> {
+    let message = if err.message.is_empty() {
+        err.code.to_string()
+    } else {
+        err.message
+    };
+
+    Error::new(err.code, message, err.data)
+}
+
+/// Convert a panicking handler call into an [`ErrorCode::InternalError`].
+fn panic_error(method: &str, payload: Box<dyn Any + Send>) -> RPCError {
+    let reason = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(|s| s.as_str()))
+        .unwrap_or("unknown");
+
+    log::error!("Method {} handler panicked: {}", method, reason);
+
+    RPCError::new(
+        ErrorCode::InternalError,
+        format!("Method {} handler panicked", method),
+        None,
+    )
+}
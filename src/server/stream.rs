@@ -0,0 +1,100 @@
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{deserialize_map, Codec, ErrorCode, RPCError, RPCResult, Request};
+
+use super::Context;
+
+/// Params of the notifications carrying the partial results of a streaming call,
+/// see [`Server::stream_handle`](super::Server::stream_handle).
+///
+/// Only deserialized from an object, like [`SubscriptionParams`](super::SubscriptionParams).
+#[derive(Debug, Serialize, PartialEq)]
+pub struct StreamParams<T> {
+    /// Request id of the streaming call.
+    pub id: usize,
+    /// Partial result.
+    pub result: T,
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for StreamParams<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename = "StreamParams")]
+        struct Members<T> {
+            id: usize,
+            result: T,
+        }
+
+        let Members { id, result } = deserialize_map(deserializer)?;
+
+        Ok(Self { id, result })
+    }
+}
+
+/// Send every item of `stream` but the last one as a [`StreamParams`] notification
+/// of `method`, returns the last item as the call result.
+pub(crate) async fn forward_stream<K, S, R>(
+    ctx: Context,
+    method: &'static str,
+    stream: S,
+) -> RPCResult<Option<R>>
+where
+    K: Codec,
+    S: Stream<Item = RPCResult<R>>,
+    R: Serialize + Default,
+{
+    let mut stream = Box::pin(stream);
+
+    let mut last = None;
+
+    while let Some(item) = stream.next().await {
+        // The previous item is partial, the error ends the call after it.
+        let (partial, err) = match item {
+            Ok(item) => (last.replace(item), None),
+            Err(err) => (last.take(), Some(err)),
+        };
+
+        if let Some(partial) = partial {
+            send_partial::<K, _>(&ctx, method, partial)?;
+        }
+
+        if let Some(err) = err {
+            return Err(err);
+        }
+    }
+
+    Ok(Some(last.unwrap_or_default()))
+}
+
+fn send_partial<K, R>(ctx: &Context, method: &'static str, partial: R) -> RPCResult<()>
+where
+    K: Codec,
+    R: Serialize,
+{
+    // Partial results of notifications have nobody to receive them.
+    let Some(id) = ctx.id() else {
+        return Ok(());
+    };
+
+    let params = StreamParams {
+        id,
+        result: partial,
+    };
+
+    if !ctx
+        .session()
+        .send(K::encode(&Request::notification(method, params))?)
+    {
+        return Err(RPCError::new(
+            ErrorCode::ConnectionClosed,
+            format!("Method {} stream closed", method),
+            None,
+        ));
+    }
+
+    Ok(())
+}
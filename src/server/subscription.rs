@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{deserialize_map, Codec, ErrorCode, JsonCodec, RPCError, RPCResult, Request};
+
+use super::{session::SessionHandle, Context};
+
+/// Params of the notifications pushed through a [`SubscriptionSink`].
+///
+/// Only deserialized from an object, positional `[id, value]` params of unrelated
+/// notifications aren't taken for subscription notifications.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SubscriptionParams<T> {
+    /// Subscription id, returned by the subscribe call.
+    pub subscription: u64,
+    /// Pushed value.
+    pub result: T,
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for SubscriptionParams<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename = "SubscriptionParams")]
+        struct Members<T> {
+            subscription: u64,
+            result: T,
+        }
+
+        let Members {
+            subscription,
+            result,
+        } = deserialize_map(deserializer)?;
+
+        Ok(Self {
+            subscription,
+            result,
+        })
+    }
+}
+
+/// Server side of one subscription, see [`Server::handle_subscription`](super::Server::handle_subscription).
+///
+/// Every value pushed by [`SubscriptionSink::notify`] is sent to the subscriber as a
+/// notification carrying the subscription id.
+pub struct SubscriptionSink<K: Codec = JsonCodec> {
+    id: u64,
+    method: &'static str,
+    session: SessionHandle,
+    active: Arc<AtomicBool>,
+    codec: PhantomData<K>,
+}
+
+impl<K: Codec> Clone for SubscriptionSink<K> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            method: self.method,
+            session: self.session.clone(),
+            active: self.active.clone(),
+            codec: PhantomData,
+        }
+    }
+}
+
+impl<K: Codec> SubscriptionSink<K> {
+    /// Subscription id, returned to the subscriber.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns true once the client unsubscribed, the value producer should stop.
+    ///
+    /// A closed session is only detected by the next [`SubscriptionSink::notify`].
+    pub fn is_closed(&self) -> bool {
+        !self.active.load(Ordering::SeqCst)
+    }
+
+    /// Push `result` to the subscriber.
+    ///
+    /// Fails with [`ErrorCode::ConnectionClosed`] once the client unsubscribed
+    /// or its session closed. Like [`Server::broadcast`](super::Server::broadcast)
+    /// the notification is queued to the session without waiting for the write.
+    pub fn notify<T>(&self, result: T) -> RPCResult<()>
+    where
+        T: Serialize,
+    {
+        if self.is_closed() {
+            return Err(self.closed_error());
+        }
+
+        let params = SubscriptionParams {
+            subscription: self.id,
+            result,
+        };
+
+        let frame = K::encode(&Request::notification(self.method, params))?;
+
+        if !self.session.send(frame) {
+            self.active.store(false, Ordering::SeqCst);
+
+            return Err(self.closed_error());
+        }
+
+        Ok(())
+    }
+
+    fn closed_error(&self) -> RPCError {
+        RPCError::new(
+            ErrorCode::ConnectionClosed,
+            format!("Subscription {} closed", self.id),
+            None,
+        )
+    }
+}
+
+/// Active subscriptions of one session, stored in the session [`Extensions`](super::Extensions).
+#[derive(Clone, Default)]
+pub(crate) struct SessionSubscriptions(Arc<Mutex<HashMap<u64, Arc<AtomicBool>>>>);
+
+impl SessionSubscriptions {
+    fn of(ctx: &Context) -> Self {
+        ctx.extensions().get_or_insert_with(Self::default)
+    }
+
+    /// Create the sink of a new subscription of the session of `ctx`.
+    pub(crate) fn subscribe<K: Codec>(ctx: &Context, method: &'static str) -> SubscriptionSink<K> {
+        static ID: AtomicU64 = AtomicU64::new(1);
+
+        let sink = SubscriptionSink {
+            id: ID.fetch_add(1, Ordering::SeqCst),
+            method,
+            session: ctx.session().clone(),
+            active: Arc::new(AtomicBool::new(true)),
+            codec: PhantomData,
+        };
+
+        Self::of(ctx)
+            .0
+            .lock()
+            .unwrap()
+            .insert(sink.id, sink.active.clone());
+
+        sink
+    }
+
+    /// Close the subscription `id` of the session of `ctx`, returns false if there is none.
+    pub(crate) fn unsubscribe(ctx: &Context, id: u64) -> bool {
+        match Self::of(ctx).0.lock().unwrap().remove(&id) {
+            Some(active) => {
+                active.store(false, Ordering::SeqCst);
+
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Timers of calls, handlers and connection upkeep.
+//!
+//! All timeouts of this crate run on the hashed time wheel of
+//! [`async-timer-rs`](https://docs.rs/async-timer-rs). Use [`timeout`] to create a
+//! timer for [`Client::call_with_timer`](crate::Client::call_with_timer), or implement
+//! [`Timer`] to drive calls by a timer of another runtime.
+//!
+//! By default timers run on [`global_timer_executor`], a wheel of 3600 slots advancing
+//! every 100ms. Call [`configure`] before the first timer is created for a finer
+//! granularity or more slots.
+
+use std::{sync::OnceLock, time::Duration};
+
+pub use async_timer_rs::{
+    hashed::{global_timer_executor, Timeout, TimerExecutor},
+    Timer,
+};
+
+/// Tick of [`global_timer_executor`].
+const DEFAULT_TICK: Duration = Duration::from_millis(100);
+
+/// Time wheel of the timers of this crate and its tick.
+static EXECUTOR: OnceLock<(TimerExecutor, Duration)> = OnceLock::new();
+
+fn executor() -> &'static (TimerExecutor, Duration) {
+    EXECUTOR.get_or_init(|| (global_timer_executor().clone(), DEFAULT_TICK))
+}
+
+/// Run the timers of this crate on a time wheel of `slots` slots advancing every `tick`,
+/// returns false if a timer was already created, the wheel can't change then.
+///
+/// The tick is the granularity of all timeouts, in whole milliseconds and at least 1ms.
+/// Timeouts longer than `slots * tick` take extra rounds of the wheel, more slots mean
+/// fewer timers to check on each tick. Timers created by [`Timeout::new`] keep running
+/// on [`global_timer_executor`].
+pub fn configure(tick: Duration, slots: u64) -> bool {
+    let tick = Duration::from_millis((tick.as_millis() as u64).max(1));
+
+    let mut configured = false;
+
+    EXECUTOR.get_or_init(|| {
+        configured = true;
+
+        (TimerExecutor::new(slots.max(1), tick), tick)
+    });
+
+    configured
+}
+
+/// Create a timer firing once `duration` elapsed, rounded up to whole ticks.
+pub fn timeout(duration: Duration) -> Timeout {
+    let (executor, tick) = executor();
+
+    let ticks = duration.as_nanos().div_ceil(tick.as_nanos()).max(1);
+
+    executor.timeout(tick.saturating_mul(ticks.try_into().unwrap_or(u32::MAX)))
+}
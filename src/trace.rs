@@ -0,0 +1,60 @@
+//! Spans of the `tracing` feature, compiled to no-ops without it.
+
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::{Instrument, Span};
+
+/// Span of a client call, from sending the request to receiving its response.
+#[cfg(feature = "tracing")]
+pub(crate) fn call_span(method: &str, id: usize) -> Span {
+    tracing::debug_span!("rpc.call", method, id)
+}
+
+/// Span of a server session.
+#[cfg(feature = "tracing")]
+pub(crate) fn session_span(session: &str) -> Span {
+    tracing::info_span!("rpc.session", session)
+}
+
+/// Span of a server handler call, `id` is `None` for notifications.
+///
+/// `traceparent` is the caller's trace context, if sent.
+#[cfg(feature = "tracing")]
+pub(crate) fn handler_span(method: &str, id: Option<usize>, traceparent: Option<&str>) -> Span {
+    tracing::debug_span!("rpc.handle", method, id, traceparent)
+}
+
+#[cfg(not(feature = "tracing"))]
+#[derive(Clone)]
+pub(crate) struct Span;
+
+#[cfg(not(feature = "tracing"))]
+impl Span {
+    pub(crate) fn in_scope<F: FnOnce() -> T, T>(&self, f: F) -> T {
+        f()
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) trait Instrument: Sized {
+    fn instrument(self, _span: Span) -> Self {
+        self
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl<T: std::future::Future> Instrument for T {}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn call_span(_method: &str, _id: usize) -> Span {
+    Span
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn session_span(_session: &str) -> Span {
+    Span
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn handler_span(_method: &str, _id: Option<usize>, _traceparent: Option<&str>) -> Span {
+    Span
+}
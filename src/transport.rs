@@ -0,0 +1,4 @@
+//! Builtin [`TransportChannel`](crate::channel::TransportChannel) implementations.
+
+#[cfg(any(feature = "http-client", feature = "http-server"))]
+pub mod http;
@@ -0,0 +1,11 @@
+//! JSONRPC over HTTP POST, one JSON request per HTTP request.
+
+#[cfg(feature = "http-client")]
+mod client;
+#[cfg(feature = "http-client")]
+pub use client::*;
+
+#[cfg(feature = "http-server")]
+mod server;
+#[cfg(feature = "http-server")]
+pub use server::*;
@@ -0,0 +1,166 @@
+use std::{convert::Infallible, future::Future};
+
+use futures::{
+    channel::mpsc::{self, Receiver, SendError, Sender},
+    stream::BoxStream,
+    SinkExt, StreamExt,
+};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE},
+    IntoUrl, Url,
+};
+use serde::Deserialize;
+
+use crate::{
+    channel::{RPCData, TransportChannel},
+    map_error, ErrorCode, RPCError, RPCResult, Response,
+};
+
+/// HTTP client transport, POSTs every outbound frame to one URL and feeds
+/// the response body back as one inbound frame.
+///
+/// Requests are sent concurrently. Notifications expect an empty response body,
+/// a non-2xx response fails its call with [`ErrorCode::ServerError`].
+///
+/// Background tasks are spawned onto the current tokio runtime.
+#[derive(Clone)]
+pub struct HttpChannel {
+    client: reqwest::Client,
+    url: Url,
+    headers: HeaderMap,
+}
+
+impl HttpChannel {
+    /// Create a transport posting to `url`.
+    pub fn new<U: IntoUrl>(url: U) -> RPCResult<Self> {
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url: url.into_url().map_err(map_error)?,
+            headers: HeaderMap::new(),
+        })
+    }
+
+    /// Send requests through `client`, e.g. to configure proxies, TLS or timeouts.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+
+        self
+    }
+
+    /// Add a header sent with every request, e.g. `Authorization`.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+
+        self
+    }
+
+    async fn post_loop(
+        self,
+        mut requests: Receiver<RPCData>,
+        responses: Sender<RPCData>,
+    ) -> RPCResult<()> {
+        while let Some(frame) = requests.next().await {
+            Self::spawn(self.clone().post(frame, responses.clone()));
+        }
+
+        log::info!("http transport {} post_loop stop.", self.url);
+
+        Ok(())
+    }
+
+    async fn post(self, frame: RPCData, mut responses: Sender<RPCData>) -> RPCResult<()> {
+        let id = serde_json::from_slice::<RequestId>(&frame)
+            .ok()
+            .and_then(|request| request.id);
+
+        let response = self
+            .client
+            .post(self.url.clone())
+            .headers(self.headers.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(frame)
+            .send()
+            .await;
+
+        let result = match response {
+            Ok(response) if response.status().is_success() => {
+                response.bytes().await.map_err(transport_error)
+            }
+            Ok(response) => {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+
+                Err(RPCError::server_error(
+                    -32000,
+                    format!("HTTP status {}", status),
+                    serde_json::json!({ "status": status.as_u16(), "body": body }),
+                ))
+            }
+            Err(err) => Err(transport_error(err)),
+        };
+
+        let frame = match (result, id) {
+            // Notifications are answered with an empty body.
+            (Ok(body), _) if body.is_empty() => return Ok(()),
+            (Ok(body), _) => body,
+            (Err(err), Some(id)) => {
+                log::error!("http transport call {} error, {}", id, err);
+
+                serde_json::to_vec(&Response::<String, (), serde_json::Value>::error(id, err))?
+                    .into()
+            }
+            (Err(err), None) => {
+                log::error!("http transport notification error, {}", err);
+
+                return Ok(());
+            }
+        };
+
+        responses.send(frame).await.map_err(map_error)
+    }
+}
+
+impl TransportChannel for HttpChannel {
+    type SinkError = SendError;
+
+    type StreamError = Infallible;
+
+    type Input = BoxStream<'static, Result<RPCData, Infallible>>;
+
+    type Output = Sender<RPCData>;
+
+    fn spawn<Fut>(future: Fut)
+    where
+        Fut: Future<Output = RPCResult<()>> + Send + 'static,
+    {
+        tokio::spawn(async move {
+            if let Err(err) = future.await {
+                log::error!("http transport task error, {}", err);
+            }
+        });
+    }
+
+    fn framed(self) -> (Self::Input, Self::Output) {
+        let (output, requests) = mpsc::channel(100);
+        let (responses, input) = mpsc::channel(100);
+
+        Self::spawn(self.post_loop(requests, responses));
+
+        (input.map(Ok).boxed(), output)
+    }
+}
+
+/// The `id` member of an outbound request.
+#[derive(Deserialize)]
+struct RequestId {
+    id: Option<usize>,
+}
+
+fn transport_error(err: reqwest::Error) -> RPCError {
+    RPCError::new(
+        ErrorCode::InternalError,
+        format!("HTTP transport error: {}", err),
+        None,
+    )
+    .with_source(err)
+}
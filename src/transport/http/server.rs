@@ -0,0 +1,193 @@
+use std::{convert::Infallible, future::Future};
+
+use futures::{
+    channel::mpsc::{self, SendError, UnboundedSender},
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    body::Incoming,
+    header::{HeaderValue, CONTENT_TYPE},
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use serde_json::value::RawValue;
+use tokio::net::{TcpListener, ToSocketAddrs};
+
+use crate::{
+    channel::{RPCData, TransportChannel},
+    map_error, ErrorCode, RPCResult, Server,
+};
+
+/// Serve the methods of `server` over HTTP POST on `addr`.
+///
+/// Every HTTP request runs in its own session, batch requests are answered with
+/// one array of responses and requests without any response with `204 No Content`.
+pub async fn serve<A: ToSocketAddrs>(addr: A, server: Server) -> RPCResult<()> {
+    let listener = TcpListener::bind(addr).await.map_err(map_error)?;
+
+    serve_on(listener, server).await
+}
+
+/// Serve the methods of `server` over HTTP POST on an already bound `listener`, see [`serve`].
+pub async fn serve_on(listener: TcpListener, server: Server) -> RPCResult<()> {
+    loop {
+        let (stream, remote) = listener.accept().await.map_err(map_error)?;
+
+        let server = server.clone();
+
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle(server.clone(), request));
+
+            if let Err(err) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                log::error!("http connection {} error, {}", remote, err);
+            }
+        });
+    }
+}
+
+async fn handle(
+    mut server: Server,
+    request: Request<Incoming>,
+) -> Result<Response<Full<RPCData>>, Infallible> {
+    if request.method() != Method::POST {
+        return Ok(reply(StatusCode::METHOD_NOT_ALLOWED, RPCData::new()));
+    }
+
+    let body = match request.into_body().collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(err) => {
+            log::error!("read http request body error, {}", err);
+
+            return Ok(reply(StatusCode::BAD_REQUEST, RPCData::new()));
+        }
+    };
+
+    let batch = body.iter().find(|c| !c.is_ascii_whitespace()) == Some(&b'[');
+
+    let frames = if batch {
+        match serde_json::from_slice::<Vec<Box<RawValue>>>(&body) {
+            Ok(frames) if frames.is_empty() => {
+                return Ok(error_reply(ErrorCode::InvalidRequest, "Empty batch"));
+            }
+            Ok(frames) => frames
+                .into_iter()
+                .map(|frame| RPCData::from(Box::<str>::from(frame).into_string()))
+                .collect(),
+            Err(err) => return Ok(error_reply(ErrorCode::ParseError, &err.to_string())),
+        }
+    } else {
+        vec![body]
+    };
+
+    let (output, responses) = mpsc::unbounded();
+
+    // Responses are already joined into one body below.
+    server.coalesce_responses(0);
+
+    let (input, output) =
+        HttpSession(stream::iter(frames.into_iter().map(Ok)).boxed(), output).framed();
+
+    let mut session = server.session::<HttpSession>(input, output);
+
+    let result = session.run().await;
+
+    drop(session);
+
+    let responses = responses.collect::<Vec<_>>().await;
+
+    if let Err(err) = result {
+        log::error!("http request error, {}", err);
+
+        if responses.is_empty() {
+            return Ok(error_reply(err.code, &err.message));
+        }
+    }
+
+    let body = match responses.len() {
+        0 => return Ok(reply(StatusCode::NO_CONTENT, RPCData::new())),
+        1 if !batch => responses.into_iter().next().unwrap(),
+        _ => {
+            let mut body = b"[".to_vec();
+
+            for (i, response) in responses.iter().enumerate() {
+                if i > 0 {
+                    body.push(b',');
+                }
+
+                body.extend_from_slice(response);
+            }
+
+            body.push(b']');
+
+            body.into()
+        }
+    };
+
+    Ok(reply(StatusCode::OK, body))
+}
+
+fn reply(status: StatusCode, body: RPCData) -> Response<Full<RPCData>> {
+    let empty = body.is_empty();
+
+    let mut response = Response::new(Full::new(body));
+
+    *response.status_mut() = status;
+
+    if !empty {
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    }
+
+    response
+}
+
+/// Error response to a request whose id couldn't be determined.
+fn error_reply(code: ErrorCode, message: &str) -> Response<Full<RPCData>> {
+    let status = match code {
+        ErrorCode::InvalidRequest => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": { "code": code, "message": message },
+    });
+
+    reply(status, body.to_string().into())
+}
+
+/// In-memory channel of the session serving one HTTP request.
+struct HttpSession(
+    BoxStream<'static, Result<RPCData, Infallible>>,
+    UnboundedSender<RPCData>,
+);
+
+impl TransportChannel for HttpSession {
+    type SinkError = SendError;
+
+    type StreamError = Infallible;
+
+    type Input = BoxStream<'static, Result<RPCData, Infallible>>;
+
+    type Output = UnboundedSender<RPCData>;
+
+    fn spawn<Fut>(future: Fut)
+    where
+        Fut: Future<Output = RPCResult<()>> + Send + 'static,
+    {
+        tokio::spawn(future);
+    }
+
+    fn framed(self) -> (Self::Input, Self::Output) {
+        (self.0, self.1)
+    }
+}
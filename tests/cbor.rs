@@ -0,0 +1,144 @@
+#![cfg(feature = "cbor")]
+
+use futures::{SinkExt, StreamExt};
+use jsonrpc_rs::{
+    CborCodec, Client, ClientConfig, Codec, Error, ErrorCode, RPCError, RPCResult, Request,
+    Response, Server,
+};
+use serde_json::json;
+
+mod common;
+use common::*;
+
+#[async_std::test]
+async fn cbor_pingpong() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::with_codec("cbor", CborCodec);
+
+    server
+        .async_handle("echo", |msg: String| async { Ok(Some(msg)) })
+        .handle("sum", |values: Vec<i64>| {
+            Ok(Some(values.iter().sum::<i64>()))
+        })
+        .handle("fail", |code: i64| -> RPCResult<Option<()>> {
+            Err(RPCError::new(
+                ErrorCode::ServerError(code, "failed".to_owned()),
+                "failed".to_owned(),
+                None,
+            ))
+        });
+
+    server.accept(server_transport);
+
+    let mut client =
+        Client::with_codec("Test", client_transport, CborCodec, ClientConfig::default());
+
+    let echo: String = client.call("echo", "hello").await?;
+
+    assert_eq!(echo, "hello");
+
+    let sum: i64 = client.call("sum", vec![-1, 2, 3]).await?;
+
+    assert_eq!(sum, 4);
+
+    let err = client.call::<_, ()>("missing", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::MethodNotFound);
+
+    let err = client.call::<_, ()>("fail", -32010).await.unwrap_err();
+
+    assert!(matches!(err.code, ErrorCode::ServerError(-32010, _)));
+    assert_eq!(err.message, "failed");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn cbor_frames() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::with_codec("cbor", CborCodec);
+
+    server.handle("echo", |msg: String| Ok(Some(msg)));
+
+    server.accept(server_transport);
+
+    let request = Request::call(1, "echo", ["hello"]);
+
+    output.send(CborCodec::encode(&request)?).await?;
+
+    let data = input.next().await.unwrap()?;
+
+    // Frames aren't JSON text.
+    assert!(serde_json::from_slice::<serde_json::Value>(&data).is_err());
+
+    let response: Response<String, serde_json::Value, serde_json::Value> =
+        CborCodec::decode(&data)?;
+
+    assert_eq!(response.id, 1);
+    assert_eq!(response.result, Some(json!("hello")));
+
+    // The version of a request is checked under CBOR too.
+    let request = json!({"jsonrpc": "1.0", "id": 2, "method": "echo", "params": ["hello"]});
+
+    assert!(
+        CborCodec::decode::<Request<String, serde_json::Value>>(&CborCodec::encode(&request)?)
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cbor_error_codes() -> RPCResult<()> {
+    for code in [
+        ErrorCode::ParseError,
+        ErrorCode::InvalidRequest,
+        ErrorCode::MethodNotFound,
+        ErrorCode::InvalidParams,
+        ErrorCode::InternalError,
+        ErrorCode::ServerError(-32001, "".to_owned()),
+        ErrorCode::ServerError(-32002, "".to_owned()),
+        ErrorCode::ServerError(-32050, "".to_owned()),
+        ErrorCode::Application(-1),
+        ErrorCode::Application(404),
+    ] {
+        let response = Response::<String, (), _>::error(
+            1,
+            Error::new(code, "error".to_owned(), Some(json!({"detail": 1}))),
+        );
+
+        let decoded: Response<String, (), serde_json::Value> =
+            CborCodec::decode(&CborCodec::encode(&response)?)?;
+
+        assert_eq!(decoded, response);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cbor_batch() -> RPCResult<()> {
+    let frames = (0..300)
+        .map(|id| CborCodec::encode(&Response::<String, _, ()>::success(id, id)))
+        .collect::<RPCResult<Vec<_>>>()?;
+
+    for len in [2, 24, 300] {
+        let batch = CborCodec::encode_batch(&frames[..len]);
+
+        let responses: Vec<Response<String, usize, ()>> = CborCodec::decode(&batch)?;
+
+        assert_eq!(responses.len(), len);
+        assert!(responses
+            .iter()
+            .enumerate()
+            .all(|(id, response)| response.id == id && response.result == Some(id)));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,1436 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use async_timer_rs::{hashed::Timeout, Timer};
+use futures::{channel::mpsc, FutureExt, SinkExt, StreamExt};
+use jsonrpc_rs::{
+    channel::RPCData, Client, ClientConfig, ClientPool, ErrorCode, FailoverClient,
+    InflightOverflow, RPCError, RPCResult, ReconnectingClient, RetryPolicy, Server,
+    HANDLER_TIMEOUT,
+};
+use serde_json::json;
+
+mod common;
+use common::*;
+
+#[async_std::test]
+async fn skip_malformed_response() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), client_transport) = transport_pair();
+
+    let mut client = Client::new("Test", client_transport);
+
+    let mut first = client.send("first", ()).await?;
+    let mut second = client.send("second", ()).await?;
+
+    input.next().await.unwrap()?;
+    input.next().await.unwrap()?;
+
+    // Invalid error object, but the id is recoverable.
+    output
+        .send(r#"{"jsonrpc":"2.0","id":1,"error":5}"#.into())
+        .await?;
+    // Garbage is skipped.
+    output.send("garbage".into()).await?;
+
+    output
+        .send(r#"{"jsonrpc":"2.0","id":2,"result":"ok"}"#.into())
+        .await?;
+
+    let err = first.recv::<String>().await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::ParseError);
+
+    let result: String = second.recv().await?;
+
+    assert_eq!(result, "ok");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn connection_closed() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, output), client_transport) = transport_pair();
+
+    let mut client = Client::new("Test", client_transport);
+
+    // A call timing out while the connection is up.
+    let err = client
+        .call_with_timer::<_, _, String>("slow", (), Timeout::new(Duration::from_millis(50)))
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::Timeout);
+
+    let mut pending = client.send("pending", ()).await?;
+
+    input.next().await.unwrap()?;
+    input.next().await.unwrap()?;
+
+    // The server hangs up.
+    drop(input);
+    drop(output);
+
+    let err = pending.recv::<String>().await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::ConnectionClosed);
+    // An orderly close has no transport error.
+    assert!(err.source().is_none());
+
+    let err = client.call::<_, String>("next", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::ConnectionClosed);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn connection_failed() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (mut frames, client_input) = mpsc::channel::<RPCResult<RPCData>>(20);
+    let (client_output, mut requests) = mpsc::channel(20);
+
+    let mut client = Client::new(
+        "Test",
+        MPSCTransportChannel(client_input.boxed(), client_output),
+    );
+
+    let mut pending = client.send("pending", ()).await?;
+
+    requests.next().await.unwrap();
+
+    frames
+        .send(Err(RPCError::new(
+            ErrorCode::InternalError,
+            "connection reset".to_owned(),
+            None,
+        )))
+        .await?;
+
+    let err = pending.recv::<String>().await.unwrap_err();
+
+    // The transport error reaches the pending calls.
+    assert_eq!(err.code, ErrorCode::ConnectionClosed);
+    assert!(err.message.contains("connection reset"));
+    assert!(err
+        .source()
+        .is_some_and(|source| source.to_string().contains("connection reset")));
+
+    assert!(client.is_closed());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn close() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, _output), client_transport) = transport_pair();
+
+    let mut client = Client::new("Test", client_transport);
+
+    let mut pending = client.send("pending", ()).await?;
+
+    input.next().await.unwrap()?;
+
+    client.clone().close();
+
+    let err = pending.recv::<String>().await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::ConnectionClosed);
+    assert!(client.is_closed());
+
+    // The aborted send loop dropped the transport, though the server didn't hang up.
+    assert!(input.next().await.is_none());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn send_failed() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(input, _output), client_transport) = transport_pair();
+
+    // The transport can't write anymore.
+    drop(input);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let err = client.call::<_, String>("call", ()).await.unwrap_err();
+
+    // The write error of the transport reaches the caller.
+    assert_eq!(err.code, ErrorCode::InternalError);
+    assert!(err.message.contains("receiver is gone"));
+    assert!(err
+        .source()
+        .is_some_and(|source| source.to_string().contains("receiver is gone")));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn strict_malformed_response() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), client_transport) = transport_pair();
+
+    let mut client = Client::with_config(
+        "Test",
+        client_transport,
+        ClientConfig::default().strict(true),
+    );
+
+    let mut call = client.send("call", ()).await?;
+
+    input.next().await.unwrap()?;
+
+    output.send("garbage".into()).await?;
+
+    assert!(call.recv::<String>().await.is_err());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn response_without_result() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), client_transport) = transport_pair();
+
+    let mut client = Client::new("Test", client_transport);
+
+    let mut first = client.send("first", ()).await?;
+    let mut second = client.send("second", ()).await?;
+
+    input.next().await.unwrap()?;
+    input.next().await.unwrap()?;
+
+    output.send(r#"{"jsonrpc":"2.0","id":1}"#.into()).await?;
+
+    output
+        .send(r#"{"jsonrpc":"2.0","id":2,"result":null}"#.into())
+        .await?;
+
+    let err = first.recv::<()>().await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::InvalidRequest);
+
+    second.recv::<()>().await?;
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn orphan_response() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), client_transport) = transport_pair();
+
+    let orphans = Arc::new(Mutex::new(vec![]));
+
+    let config = ClientConfig::default().on_orphan_response({
+        let orphans = orphans.clone();
+        move |id, result| orphans.lock().unwrap().push((id, result.unwrap()))
+    });
+
+    let mut client = Client::with_config("Test", client_transport, config);
+
+    let mut call = client.send("call", ()).await?;
+
+    input.next().await.unwrap()?;
+
+    output
+        .send(r#"{"jsonrpc":"2.0","id":1,"result":"ok"}"#.into())
+        .await?;
+    // Duplicated response
+    output
+        .send(r#"{"jsonrpc":"2.0","id":1,"result":"again"}"#.into())
+        .await?;
+    // Unknown id
+    output
+        .send(r#"{"jsonrpc":"2.0","id":100,"result":"bogus"}"#.into())
+        .await?;
+
+    let result: String = call.recv().await?;
+
+    assert_eq!(result, "ok");
+
+    // Round trip another call so the orphans above were processed.
+    let mut call = client.send("call", ()).await?;
+
+    input.next().await.unwrap()?;
+
+    output
+        .send(r#"{"jsonrpc":"2.0","id":2,"result":"ok"}"#.into())
+        .await?;
+
+    call.recv::<String>().await?;
+
+    assert_eq!(
+        *orphans.lock().unwrap(),
+        vec![(1, "again".into()), (100, "bogus".into())]
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn notifications() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(_input, mut output), client_transport) = transport_pair();
+
+    let mut client = Client::new("Test", client_transport);
+
+    let mut first = client.notifications();
+    let mut second = client.notifications();
+    let dropped = client.notifications();
+
+    drop(dropped);
+
+    output
+        .send(r#"{"jsonrpc":"2.0","method":"tick","params":[1]}"#.into())
+        .await?;
+    // A request rather than a notification.
+    output
+        .send(r#"{"jsonrpc":"2.0","id":7,"method":"tick","params":[2]}"#.into())
+        .await?;
+    output
+        .send(r#"{"jsonrpc":"2.0","method":"status"}"#.into())
+        .await?;
+
+    for stream in [&mut first, &mut second] {
+        assert_eq!(stream.next().await, Some(("tick".to_owned(), json!([1]))));
+        assert_eq!(
+            stream.next().await,
+            Some(("status".to_owned(), serde_json::Value::Null))
+        );
+    }
+
+    client.close();
+
+    assert_eq!(first.next().await, None);
+    assert_eq!(second.next().await, None);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn call_with_deadline() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (_server_transport, client_transport) = transport_pair();
+
+    let mut client = Client::new("Test", client_transport);
+
+    let deadline = Instant::now() + Duration::from_millis(300);
+
+    let err = client
+        .call_with_deadline::<_, ()>("never", (), deadline)
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::Timeout);
+
+    // The deadline already passed, fail without sending.
+    let err = client
+        .call_with_deadline::<_, ()>("never", (), deadline)
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::Timeout);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn default_timeout() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), client_transport) = transport_pair();
+
+    let mut client = Client::with_config(
+        "Test",
+        client_transport,
+        ClientConfig::default().timeout(Duration::from_millis(100)),
+    );
+
+    let err = client.call::<_, String>("never", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::Timeout);
+
+    input.next().await.unwrap()?;
+
+    // A per call timer overrides the default timeout.
+    let mut responser = client
+        .send_with_timer("slow", (), Timeout::new(Duration::from_secs(2)))
+        .await?;
+
+    let request: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    async_std::task::sleep(Duration::from_millis(300)).await;
+
+    output
+        .send(
+            json!({"jsonrpc":"2.0","id":request["id"],"result":"slow"})
+                .to_string()
+                .into(),
+        )
+        .await?;
+
+    assert_eq!(responser.recv::<String>().await?, "slow");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn client_builder() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .async_handle0("slow", || async {
+            Timeout::new(Duration::from_secs(1)).await;
+            Ok(Some(()))
+        });
+
+    server.accept(server_transport);
+
+    let mut client = Client::builder("Test")
+        .capacity(1)
+        .max_inflight(4)
+        .timeout(Duration::from_millis(100))
+        .build(client_transport);
+
+    assert_eq!(client.call::<_, String>("echo", "hello").await?, "hello");
+
+    let err = client.call::<_, ()>("slow", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::Timeout);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn max_inflight() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), client_transport) = transport_pair();
+
+    let mut client = Client::with_config(
+        "Test",
+        client_transport,
+        ClientConfig::default().max_inflight(1),
+    );
+
+    let mut first = client.send("first", ()).await?;
+
+    assert_eq!(client.inflight_count(), 1);
+
+    let mut blocked = client.clone();
+
+    let second =
+        async_std::task::spawn(
+            async move { blocked.send("second", ()).await?.recv::<String>().await },
+        );
+
+    let request: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(request["method"], "first");
+
+    // The second call waits until the first one completed.
+    assert!(
+        async_std::future::timeout(Duration::from_millis(200), input.next())
+            .await
+            .is_err()
+    );
+
+    output
+        .send(
+            json!({"jsonrpc":"2.0","id":request["id"],"result":"one"})
+                .to_string()
+                .into(),
+        )
+        .await?;
+
+    let one: String = first.recv().await?;
+
+    assert_eq!(one, "one");
+
+    let request: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(request["method"], "second");
+
+    output
+        .send(
+            json!({"jsonrpc":"2.0","id":request["id"],"result":"two"})
+                .to_string()
+                .into(),
+        )
+        .await?;
+
+    assert_eq!(second.await?, "two");
+    assert_eq!(client.inflight_count(), 0);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn reject_inflight_overflow() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), client_transport) = transport_pair();
+
+    let mut client = Client::builder("Test")
+        .max_inflight(1)
+        .inflight_overflow(InflightOverflow::Reject)
+        .build(client_transport);
+
+    let mut first = client.send("first", ()).await?;
+
+    let err = client.call::<_, String>("second", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::TooManyInflight);
+    assert_eq!(client.inflight_count(), 1);
+
+    let request: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(request["method"], "first");
+
+    output
+        .send(
+            json!({"jsonrpc":"2.0","id":request["id"],"result":"one"})
+                .to_string()
+                .into(),
+        )
+        .await?;
+
+    assert_eq!(first.recv::<String>().await?, "one");
+
+    // The slot is free again once the pending call completed.
+    let _second = client.send("second", ()).await?;
+
+    assert_eq!(client.inflight_count(), 1);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn max_request_id() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), client_transport) = transport_pair();
+
+    let mut client = Client::builder("Test")
+        .max_request_id(3)
+        .build(client_transport);
+
+    let mut calls = vec![];
+
+    for _ in 0..3 {
+        calls.push(client.send("call", ()).await?);
+
+        input.next().await.unwrap()?;
+    }
+
+    assert_eq!(
+        calls.iter().map(|call| call.id()).collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+
+    // Every id is pending.
+    let err = client.call::<_, String>("call", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::RequestIdsExhausted);
+
+    output
+        .send(r#"{"jsonrpc":"2.0","id":2,"result":"ok"}"#.into())
+        .await?;
+
+    assert_eq!(calls.remove(1).recv::<String>().await?, "ok");
+
+    // Ids wrap around, skipping the ones still pending.
+    let mut call = client.send("call", ()).await?;
+
+    assert_eq!(call.id(), 2);
+
+    input.next().await.unwrap()?;
+
+    output
+        .send(r#"{"jsonrpc":"2.0","id":2,"result":"again"}"#.into())
+        .await?;
+
+    assert_eq!(call.recv::<String>().await?, "again");
+
+    drop(call);
+
+    for mut call in calls.drain(..) {
+        output
+            .send(
+                json!({"jsonrpc":"2.0","id":call.id(),"result":"done"})
+                    .to_string()
+                    .into(),
+            )
+            .await?;
+
+        assert_eq!(call.recv::<String>().await?, "done");
+    }
+
+    // Once all ids are free, they are handed out on from the last one, wrapping after the max.
+    for _ in 0..3 {
+        calls.push(client.send("call", ()).await?);
+
+        input.next().await.unwrap()?;
+    }
+
+    assert_eq!(
+        calls.iter().map(|call| call.id()).collect::<Vec<_>>(),
+        [3, 1, 2]
+    );
+
+    let err = client.call::<_, String>("call", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::RequestIdsExhausted);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn try_recv() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), client_transport) = transport_pair();
+
+    let mut client = Client::new("Test", client_transport);
+
+    let mut responser = client.send("echo", ()).await?;
+
+    assert_eq!(responser.try_recv::<String>()?, None);
+
+    let request: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    output
+        .send(
+            json!({"jsonrpc":"2.0","id":request["id"],"result":"pong"})
+                .to_string()
+                .into(),
+        )
+        .await?;
+
+    let deadline = Instant::now() + Duration::from_secs(1);
+
+    let result = loop {
+        if let Some(result) = responser.try_recv::<String>()? {
+            break result;
+        }
+
+        assert!(Instant::now() < deadline, "response not received");
+
+        async_std::task::sleep(Duration::from_millis(10)).await;
+    };
+
+    assert_eq!(result, "pong");
+
+    // The response is only returned once.
+    assert!(responser.try_recv::<String>().is_err());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn batch_response() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), client_transport) = transport_pair();
+
+    let mut client = Client::new("Test", client_transport);
+
+    let mut first = client.send("first", ()).await?;
+    let mut second = client.send("second", ()).await?;
+
+    let mut ids = vec![];
+
+    for _ in 0..2 {
+        let request: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+        ids.push(request["id"].clone());
+    }
+
+    output
+        .send(
+            json!([
+                {"jsonrpc":"2.0","id":ids[1],"result":"two"},
+                {"jsonrpc":"2.0","id":ids[0],"result":"one"},
+            ])
+            .to_string()
+            .into(),
+        )
+        .await?;
+
+    assert_eq!(first.recv::<String>().await?, "one");
+    assert_eq!(second.recv::<String>().await?, "two");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn lenient_version() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), client_transport) = transport_pair();
+
+    let mut client = Client::with_config(
+        "Test",
+        client_transport,
+        ClientConfig::default().lenient_version(true),
+    );
+
+    let mut call = client.send("call", ()).await?;
+
+    input.next().await.unwrap()?;
+
+    output.send(r#"{"id":1,"result":"ok"}"#.into()).await?;
+
+    let result: String = call.recv().await?;
+
+    assert_eq!(result, "ok");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn reconnecting_client() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let servers = Arc::new(Mutex::new(vec![]));
+
+    let client = ReconnectingClient::new("Test", {
+        let servers = servers.clone();
+
+        move || {
+            let (server_transport, client_transport) = transport_pair();
+
+            servers.lock().unwrap().push(server_transport);
+
+            async move { Ok(client_transport) }
+        }
+    });
+
+    let mut call = client.client().await?.send("first", ()).await?;
+
+    let MPSCTransportChannel(mut input, output) = servers.lock().unwrap().pop().unwrap();
+
+    input.next().await.unwrap()?;
+
+    // Disconnect
+    drop(output);
+
+    let err = call.recv::<()>().await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::ConnectionClosed);
+
+    let mut call = client.client().await?.send("second", ()).await?;
+
+    let MPSCTransportChannel(mut input, mut output) = servers.lock().unwrap().pop().unwrap();
+
+    let request: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(request["method"], "second");
+
+    let response = json!({"jsonrpc":"2.0", "id":request["id"], "result":"ok"});
+
+    output.send(response.to_string().into()).await?;
+
+    assert_eq!(call.recv::<String>().await?, "ok");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn keepalive() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let config =
+        ClientConfig::default().keepalive(Duration::from_millis(100), Duration::from_millis(200));
+
+    // Answered pings keep the client open.
+    let (server_transport, client_transport) = transport_pair();
+
+    Server::default().handle_ping().accept(server_transport);
+
+    let client = Client::with_config("Test", client_transport, config.clone());
+
+    async_std::task::sleep(Duration::from_millis(600)).await;
+
+    assert!(!client.is_closed());
+
+    // A silent peer closes the client.
+    let (_server_transport, client_transport) = transport_pair();
+
+    let mut client = Client::with_config("Test", client_transport, config);
+
+    let mut call = client.send("call", ()).await?;
+
+    let err = call.recv::<()>().await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::ConnectionClosed);
+    assert!(client.is_closed());
+
+    Ok(())
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn blocking_client() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let notified = Arc::new(Mutex::new(vec![]));
+
+    let mut server = Server::default();
+
+    let sink = notified.clone();
+
+    server
+        .async_handle("echo", |msg: String| async move { Ok(Some(msg)) })
+        .handle("log", move |msg: String| {
+            sink.lock().unwrap().push(msg);
+
+            Ok(None::<()>)
+        });
+
+    server.accept(server_transport);
+
+    let mut client = jsonrpc_rs::BlockingClient::new("Test", client_transport);
+
+    client.notify_blocking("log", "hello")?;
+
+    let echo: String = client.call_blocking("echo", "world")?;
+
+    assert_eq!(echo, "world");
+    assert_eq!(*notified.lock().unwrap(), vec!["hello".to_owned()]);
+
+    let err = client.call_blocking::<_, ()>("missing", ()).unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::MethodNotFound);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn typed_error_data() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Balance {
+        available: u64,
+        requested: u64,
+    }
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.handle("withdraw", |requested: u64| -> RPCResult<Option<()>> {
+        let err: RPCError<Balance> = RPCError::new(
+            ErrorCode::Application(1),
+            "Insufficient balance".to_owned(),
+            Some(Balance {
+                available: 10,
+                requested,
+            }),
+        );
+
+        Err(err.untyped())
+    });
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let err = client
+        .call_with_error_data::<_, (), Balance>("withdraw", 20)
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::Application(1));
+    assert_eq!(
+        err.data,
+        Some(Balance {
+            available: 10,
+            requested: 20
+        })
+    );
+
+    // Data of another shape is dropped, keeping the deserialize error as source.
+    let err = client
+        .call_with_error_data::<_, (), String>("withdraw", 20)
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.data, None);
+    assert!(err.source().is_some());
+
+    Ok(())
+}
+
+#[derive(Clone, Default)]
+struct SinkWaits(Arc<Mutex<usize>>);
+
+impl jsonrpc_rs::Metrics for SinkWaits {
+    fn on_sink_wait(&self, _elapsed: Duration) {
+        *self.0.lock().unwrap() += 1;
+    }
+}
+
+#[async_std::test]
+async fn high_watermark() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, _output), client_transport) = transport_pair();
+
+    let sink_waits = SinkWaits::default();
+
+    let mut client = Client::builder("Test")
+        .capacity(10)
+        .high_watermark(3)
+        .metrics(sink_waits.clone())
+        .build(client_transport);
+
+    // Nobody reads the transport, so once it's full frames queue up in the client.
+    loop {
+        if let Err(err) = client.notification("log", "hello").await {
+            assert_eq!(err.code, ErrorCode::Backpressure);
+
+            // Unless the send loop just fell behind, it's blocked on the full transport.
+            Timeout::new(Duration::from_millis(50)).await;
+
+            if client.queued_count() == 3 {
+                break;
+            }
+        }
+    }
+
+    let err = client.call::<_, ()>("echo", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::Backpressure);
+    assert_eq!(client.queued_count(), 3);
+    assert!(*sink_waits.0.lock().unwrap() > 0);
+
+    // Reading the transport drains the queue.
+    while client.queued_count() > 0 {
+        input.next().await.unwrap()?;
+    }
+
+    client.notification("log", "hello").await?;
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn interceptors() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, _output), client_transport) = transport_pair();
+
+    let order = Arc::new(Mutex::new(vec![]));
+
+    let first = order.clone();
+
+    let mut client = Client::builder("Test")
+        .interceptor(move |request| {
+            first.lock().unwrap().push(1);
+
+            if request.method == "forbidden" {
+                return Err(RPCError::new(
+                    ErrorCode::InvalidRequest,
+                    "Forbidden".to_owned(),
+                    None,
+                ));
+            }
+
+            request.params = json!({ "token": "secret", "args": request.params.take() });
+
+            Ok(())
+        })
+        .build(client_transport);
+
+    let second = order.clone();
+
+    client = client.with_interceptor(move |request| {
+        second.lock().unwrap().push(2);
+
+        request.method = format!("v2.{}", request.method);
+
+        // The id belongs to the client.
+        request.id = Some(1000);
+
+        Ok(())
+    });
+
+    let responser = client.send("echo", [1]).await?;
+
+    let frame: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?).unwrap();
+
+    assert_eq!(frame["id"], json!(responser.id()));
+    assert_eq!(frame["method"], "v2.echo");
+    assert_eq!(frame["params"], json!({ "token": "secret", "args": [1] }));
+
+    client.notification("log", "hello").await?;
+
+    let frame: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?).unwrap();
+
+    assert_eq!(frame.get("id"), None);
+    assert_eq!(frame["method"], "v2.log");
+
+    assert_eq!(*order.lock().unwrap(), [1, 2, 1, 2]);
+
+    // A failing interceptor fails the call without sending it.
+    let err = client.call::<_, ()>("forbidden", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::InvalidRequest);
+    assert_eq!(*order.lock().unwrap(), [1, 2, 1, 2, 1]);
+    assert!(input.next().now_or_never().is_none());
+
+    // Params which can't be handed to the interceptors keep the serialize error.
+    let invalid = std::collections::HashMap::from([(vec![1], 1)]);
+
+    let err = client.call::<_, ()>("echo", invalid).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::ParseError);
+    assert!(err
+        .source()
+        .is_some_and(|source| source.is::<serde_json::Error>()));
+    assert!(input.next().now_or_never().is_none());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn call_with_retry() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), client_transport) = transport_pair();
+
+    let mut client = Client::with_config(
+        "Test",
+        client_transport,
+        ClientConfig::default().timeout(Duration::from_millis(100)),
+    );
+
+    let policy =
+        RetryPolicy::default().backoff(Duration::from_millis(10), Duration::from_millis(10));
+
+    let server = async_std::task::spawn(async move {
+        let mut ids = vec![];
+
+        // Drop the first attempt, answer the second.
+        for _ in 0..2 {
+            let request: serde_json::Value =
+                serde_json::from_slice(&input.next().await.unwrap().unwrap()).unwrap();
+
+            ids.push(request["id"].clone());
+        }
+
+        output
+            .send(
+                json!({"jsonrpc":"2.0","id":ids[1],"result":"ok"})
+                    .to_string()
+                    .into(),
+            )
+            .await
+            .unwrap();
+
+        // Not retryable.
+        let request: serde_json::Value =
+            serde_json::from_slice(&input.next().await.unwrap().unwrap()).unwrap();
+
+        output
+            .send(
+                json!({"jsonrpc":"2.0","id":request["id"],"error":{"code":-32601,"message":"missing"}})
+                    .to_string()
+                    .into(),
+            )
+            .await
+            .unwrap();
+
+        // A server side timeout, the handler may have run.
+        let request: serde_json::Value =
+            serde_json::from_slice(&input.next().await.unwrap().unwrap()).unwrap();
+
+        output
+            .send(
+                json!({"jsonrpc":"2.0","id":request["id"],"error":{"code":HANDLER_TIMEOUT,"message":"timed out"}})
+                    .to_string()
+                    .into(),
+            )
+            .await
+            .unwrap();
+
+        (ids, input, output)
+    });
+
+    let result: String = client.call_with_retry("flaky", (), &policy).await?;
+
+    assert_eq!(result, "ok");
+
+    let err = client
+        .call_with_retry::<_, String>("missing", (), &policy)
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::MethodNotFound);
+
+    let err = client
+        .call_with_retry::<_, String>("slow", (), &policy)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err.code,
+        ErrorCode::ServerError(HANDLER_TIMEOUT, _)
+    ));
+
+    let (ids, mut input, _output) = server.await;
+
+    // Every attempt has a fresh id.
+    assert_ne!(ids[0], ids[1]);
+
+    // Out of attempts, the last error is returned.
+    let err = client
+        .call_with_retry::<_, String>("never", (), &policy.max_attempts(2))
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::Timeout);
+
+    input.next().await.unwrap()?;
+    input.next().await.unwrap()?;
+
+    assert!(
+        async_std::future::timeout(Duration::from_millis(200), input.next())
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn client_pool() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let servers = Arc::new(Mutex::new(vec![]));
+
+    let pool = ClientPool::new("Test", 2, {
+        let servers = servers.clone();
+
+        move || {
+            let (server_transport, client_transport) = transport_pair();
+
+            servers.lock().unwrap().push(server_transport);
+
+            async move { Ok(client_transport) }
+        }
+    });
+
+    async fn answer(transport: &mut MPSCTransportChannel) -> RPCResult<()> {
+        let request: serde_json::Value =
+            serde_json::from_slice(&transport.0.next().await.unwrap()?)?;
+
+        transport
+            .1
+            .send(
+                json!({"jsonrpc":"2.0","id":request["id"],"result":request["method"]})
+                    .to_string()
+                    .into(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    // The first client is busy, the second call opens another connection.
+    let first = pool.call::<_, String>("first", ());
+    let second = pool.call::<_, String>("second", ());
+
+    let answers = async {
+        while servers.lock().unwrap().len() < 2 {
+            async_std::task::sleep(Duration::from_millis(10)).await;
+        }
+
+        let mut transports = servers.lock().unwrap().drain(..).collect::<Vec<_>>();
+
+        for transport in &mut transports {
+            answer(transport).await?;
+        }
+
+        RPCResult::Ok(transports)
+    };
+
+    let (first, second, transports) = futures::join!(first, second, answers);
+
+    assert_eq!(first?, "first");
+    assert_eq!(second?, "second");
+
+    let mut transports = transports?;
+
+    assert_eq!(pool.len().await, 2);
+
+    // Idle clients are reused.
+    let (third, _) = futures::join!(
+        pool.call::<_, String>("third", ()),
+        answer(&mut transports[0])
+    );
+
+    assert_eq!(third?, "third");
+    assert!(servers.lock().unwrap().is_empty());
+
+    // Closed clients are recycled.
+    drop(transports);
+
+    while !pool.is_empty().await {
+        async_std::task::sleep(Duration::from_millis(10)).await;
+    }
+
+    let answers = async {
+        while servers.lock().unwrap().is_empty() {
+            async_std::task::sleep(Duration::from_millis(10)).await;
+        }
+
+        let mut transport = servers.lock().unwrap().pop().unwrap();
+
+        answer(&mut transport).await?;
+
+        RPCResult::Ok(transport)
+    };
+
+    let (fourth, _transport) = futures::join!(pool.call::<_, String>("fourth", ()), answers);
+
+    assert_eq!(fourth?, "fourth");
+    assert_eq!(pool.len().await, 1);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn client_pool_slow_connect() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let servers = Arc::new(Mutex::new(vec![]));
+
+    // Only the first connection succeeds, the next ones never complete.
+    let pool = ClientPool::new("Test", 2, {
+        let servers = servers.clone();
+        let connects = Arc::new(AtomicUsize::new(0));
+
+        move || {
+            let connected = (connects.fetch_add(1, Ordering::SeqCst) == 0).then(|| {
+                let (server_transport, client_transport) = transport_pair();
+
+                servers.lock().unwrap().push(server_transport);
+
+                client_transport
+            });
+
+            async move {
+                match connected {
+                    Some(transport) => Ok(transport),
+                    None => futures::future::pending().await,
+                }
+            }
+        }
+    });
+
+    let (mut transport, first) = {
+        let pool = pool.clone();
+
+        let first =
+            async_std::task::spawn(async move { pool.call::<_, String>("first", ()).await });
+
+        while servers.lock().unwrap().is_empty() {
+            async_std::task::sleep(Duration::from_millis(10)).await;
+        }
+
+        (servers.lock().unwrap().pop().unwrap(), first)
+    };
+
+    let request: serde_json::Value = serde_json::from_slice(&transport.0.next().await.unwrap()?)?;
+
+    assert_eq!(request["method"], "first");
+
+    // The first client is busy, this call waits for a connection that never completes.
+    let hanging = {
+        let pool = pool.clone();
+
+        async_std::task::spawn(async move { pool.call::<_, String>("hanging", ()).await })
+    };
+
+    async_std::task::sleep(Duration::from_millis(100)).await;
+
+    transport
+        .1
+        .send(
+            json!({"jsonrpc":"2.0","id":request["id"],"result":"first"})
+                .to_string()
+                .into(),
+        )
+        .await?;
+
+    assert_eq!(first.await?, "first");
+
+    // The open client still serves calls meanwhile.
+    let second = async {
+        let request: serde_json::Value =
+            serde_json::from_slice(&transport.0.next().await.unwrap()?)?;
+
+        transport
+            .1
+            .send(
+                json!({"jsonrpc":"2.0","id":request["id"],"result":request["method"]})
+                    .to_string()
+                    .into(),
+            )
+            .await?;
+
+        RPCResult::Ok(())
+    };
+
+    let (third, answered) = async_std::future::timeout(
+        Duration::from_millis(500),
+        futures::future::join(pool.call::<_, String>("second", ()), second),
+    )
+    .await
+    .expect("call on the open client");
+
+    answered?;
+
+    assert_eq!(third?, "second");
+    assert_eq!(pool.len().await, 1);
+
+    drop(hanging.cancel());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn failover() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (MPSCTransportChannel(mut input, mut output), primary_transport) = transport_pair();
+    let (server_transport, backup_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.handle("echo", |msg: String| Ok(Some(format!("backup {}", msg))));
+
+    server.accept(server_transport);
+
+    let config = ClientConfig::default().timeout(Duration::from_millis(100));
+
+    let client = FailoverClient::new([
+        Client::with_config("Primary", primary_transport, config.clone()),
+        Client::with_config("Backup", backup_transport, config),
+    ])
+    .cooldown(Duration::from_millis(300));
+
+    // The primary times out, the call fails over to the backup.
+    let result: String = client.call("echo", "hello").await?;
+
+    assert_eq!(result, "backup hello");
+    assert!(client.is_down(0));
+
+    input.next().await.unwrap()?;
+
+    // The primary is skipped during its cooldown.
+    let result: String = client.call("echo", "hello").await?;
+
+    assert_eq!(result, "backup hello");
+
+    assert!(
+        async_std::future::timeout(Duration::from_millis(100), input.next())
+            .await
+            .is_err()
+    );
+
+    async_std::task::sleep(Duration::from_millis(300)).await;
+
+    assert!(!client.is_down(0));
+
+    let (result, answered) = futures::join!(client.call::<_, String>("echo", "hello"), async {
+        let request: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+        output
+            .send(
+                json!({"jsonrpc":"2.0","id":request["id"],"result":"primary hello"})
+                    .to_string()
+                    .into(),
+            )
+            .await?;
+
+        RPCResult::Ok(())
+    });
+
+    answered?;
+
+    assert_eq!(result?, "primary hello");
+
+    // Other errors are returned as is.
+    let (err, answered) = futures::join!(client.call::<_, String>("missing", ()), async {
+        let request: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+        output
+            .send(
+                json!({"jsonrpc":"2.0","id":request["id"],"error":{"code":-32601,"message":"missing"}})
+                    .to_string()
+                    .into(),
+            )
+            .await?;
+
+        RPCResult::Ok(())
+    });
+
+    answered?;
+
+    assert_eq!(err.unwrap_err().code, ErrorCode::MethodNotFound);
+    assert!(!client.is_down(0));
+
+    // So are the peer's errors sharing the codes of local timeouts and closed connections.
+    for code in [-32001, -32002] {
+        let (err, answered) = futures::join!(client.call::<_, String>("echo", "hello"), async {
+            let request: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+            output
+                .send(
+                    json!({"jsonrpc":"2.0","id":request["id"],"error":{"code":code,"message":"remote"}})
+                        .to_string()
+                        .into(),
+                )
+                .await?;
+
+            RPCResult::Ok(())
+        });
+
+        answered?;
+
+        assert!(matches!(err.unwrap_err().code, ErrorCode::ServerError(c, _) if c == code));
+        assert!(!client.is_down(0));
+    }
+
+    Ok(())
+}
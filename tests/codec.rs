@@ -0,0 +1,203 @@
+#[cfg(feature = "msgpack")]
+use futures::{SinkExt, StreamExt};
+#[cfg(feature = "msgpack")]
+use jsonrpc_rs::{Client, ClientConfig, ErrorCode, MsgpackCodec, Server};
+use jsonrpc_rs::{Codec, JsonCodec, RPCResult, Request, Response};
+use serde_json::json;
+
+#[cfg(feature = "msgpack")]
+mod common;
+#[cfg(feature = "msgpack")]
+use common::*;
+
+#[test]
+fn json_buffer_reuse() -> RPCResult<()> {
+    // Frames split off the encode buffer stay intact, including ones outgrowing it.
+    let frames = (0..1000)
+        .map(|id| JsonCodec::encode(&Request::call(id, "echo", ["x".repeat(id * 10)])))
+        .collect::<RPCResult<Vec<_>>>()?;
+
+    for (id, frame) in frames.iter().enumerate() {
+        let request: Request<String, Vec<String>> = JsonCodec::decode(frame)?;
+
+        assert_eq!(request.id, Some(id));
+        assert_eq!(request.params, ["x".repeat(id * 10)]);
+    }
+
+    drop(frames);
+
+    // A failed encoding leaves nothing behind for the next frame.
+    let invalid = std::collections::HashMap::from([(vec![1], 1)]);
+
+    assert!(JsonCodec::encode(&invalid).is_err());
+
+    let frame = JsonCodec::encode(&Response::<String, _, ()>::success(1, "hello"))?;
+
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&frame)?,
+        json!({"jsonrpc": "2.0", "id": 1, "result": "hello"})
+    );
+
+    Ok(())
+}
+
+#[cfg(not(feature = "extensions"))]
+#[test]
+fn json_decode_response() -> RPCResult<()> {
+    let frame = JsonCodec::encode(&Response::<String, _, ()>::success(
+        1,
+        json!({"values": [1, 2]}),
+    ))?;
+
+    let response = JsonCodec::decode_response(&frame)?;
+
+    assert_eq!(response.id, 1);
+
+    let result = response.result.expect("result");
+
+    assert_eq!(result.get(), r#"{"values":[1,2]}"#);
+
+    let frame = json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "error": {"code": -32010, "message": "\"escaped\" message"}
+    })
+    .to_string();
+
+    let response = JsonCodec::decode_response(frame.as_bytes())?;
+
+    assert_eq!(
+        response.error.expect("error").message.as_ref(),
+        "\"escaped\" message"
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "msgpack")]
+#[async_std::test]
+async fn msgpack_pingpong() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::with_codec("msgpack", MsgpackCodec);
+
+    server
+        .async_handle("echo", |msg: String| async { Ok(Some(msg)) })
+        .handle("sum", |values: Vec<i64>| {
+            Ok(Some(values.iter().sum::<i64>()))
+        });
+
+    server.accept(server_transport);
+
+    let mut client = Client::with_codec(
+        "Test",
+        client_transport,
+        MsgpackCodec,
+        ClientConfig::default(),
+    );
+
+    let echo: String = client.call("echo", "hello").await?;
+
+    assert_eq!(echo, "hello");
+
+    let sum: i64 = client.call("sum", vec![-1, 2, 3]).await?;
+
+    assert_eq!(sum, 4);
+
+    let err = client.call::<_, ()>("missing", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::MethodNotFound);
+
+    Ok(())
+}
+
+#[cfg(feature = "msgpack")]
+#[async_std::test]
+async fn msgpack_frames() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::with_codec("msgpack", MsgpackCodec);
+
+    server.handle("echo", |msg: String| Ok(Some(msg)));
+
+    server.accept(server_transport);
+
+    let request = Request::call(1, "echo", ["hello"]);
+
+    output.send(MsgpackCodec::encode(&request)?).await?;
+
+    let data = input.next().await.unwrap()?;
+
+    // Frames aren't JSON text.
+    assert!(serde_json::from_slice::<serde_json::Value>(&data).is_err());
+
+    let response: Response<String, serde_json::Value, serde_json::Value> =
+        MsgpackCodec::decode(&data)?;
+
+    assert_eq!(response.id, 1);
+    assert_eq!(response.result, Some(json!("hello")));
+
+    Ok(())
+}
+
+#[cfg(feature = "msgpack")]
+#[async_std::test]
+async fn msgpack_notifier() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::with_codec("msgpack", MsgpackCodec);
+
+    server.handle_with_ctx("work", |ctx, ()| {
+        ctx.notifier().notify("progress", [1])?;
+
+        Ok(Some("done"))
+    });
+
+    server.accept(server_transport);
+
+    output
+        .send(MsgpackCodec::encode(&Request::call(1, "work", ()))?)
+        .await?;
+
+    // Notifications are encoded with the codec of the session.
+    let notification: Request<String, serde_json::Value> =
+        MsgpackCodec::decode(&input.next().await.unwrap()?)?;
+
+    assert_eq!(notification.method, "progress");
+    assert_eq!(notification.params, json!([1]));
+
+    let response: Response<String, serde_json::Value, serde_json::Value> =
+        MsgpackCodec::decode(&input.next().await.unwrap()?)?;
+
+    assert_eq!(response.result, Some(json!("done")));
+
+    Ok(())
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn msgpack_batch() -> RPCResult<()> {
+    let frames = (0..20)
+        .map(|id| MsgpackCodec::encode(&Response::<String, _, ()>::success(id, id)))
+        .collect::<RPCResult<Vec<_>>>()?;
+
+    for len in [2, 20] {
+        let batch = MsgpackCodec::encode_batch(&frames[..len]);
+
+        let responses: Vec<Response<String, usize, ()>> = MsgpackCodec::decode(&batch)?;
+
+        assert_eq!(responses.len(), len);
+        assert!(responses
+            .iter()
+            .enumerate()
+            .all(|(id, response)| response.id == id && response.result == Some(id)));
+    }
+
+    Ok(())
+}
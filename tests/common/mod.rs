@@ -0,0 +1,58 @@
+#![allow(dead_code)]
+
+use futures::{
+    channel::mpsc::{self, SendError, Sender},
+    executor::ThreadPool,
+    stream::BoxStream,
+    task::SpawnExt,
+    StreamExt,
+};
+use jsonrpc_rs::{
+    channel::{RPCData, TransportChannel},
+    RPCError, RPCResult,
+};
+use once_cell::sync::OnceCell;
+
+pub struct MPSCTransportChannel(
+    pub BoxStream<'static, RPCResult<RPCData>>,
+    pub Sender<RPCData>,
+);
+
+impl TransportChannel for MPSCTransportChannel {
+    type StreamError = RPCError;
+
+    type SinkError = SendError;
+
+    type Input = BoxStream<'static, RPCResult<RPCData>>;
+
+    type Output = Sender<RPCData>;
+
+    fn spawn<Fut>(future: Fut)
+    where
+        Fut: futures::Future<Output = RPCResult<()>> + Send + 'static,
+    {
+        static INSTANCE: OnceCell<ThreadPool> = OnceCell::new();
+
+        let executor = INSTANCE.get_or_init(|| ThreadPool::new().unwrap());
+
+        _ = executor.spawn(async move {
+            _ = future.await;
+        });
+    }
+
+    fn framed(self) -> (Self::Input, Self::Output) {
+        (self.0, self.1)
+    }
+}
+
+/// Create connected (server, client) memory transports.
+pub fn transport_pair() -> (MPSCTransportChannel, MPSCTransportChannel) {
+    let (server_output, client_input) = mpsc::channel(20);
+
+    let (client_output, server_input) = mpsc::channel(20);
+
+    (
+        MPSCTransportChannel(server_input.map(Ok).boxed(), server_output),
+        MPSCTransportChannel(client_input.map(Ok).boxed(), client_output),
+    )
+}
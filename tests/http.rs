@@ -0,0 +1,166 @@
+#![cfg(feature = "http-client")]
+
+use jsonrpc_rs::{transport::http::HttpChannel, Client, ErrorCode, RPCResult};
+use serde_json::json;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// Minimal HTTP/1.1 JSONRPC endpoint, answering one request per connection.
+async fn serve(listener: TcpListener) {
+    loop {
+        let (stream, _) = listener.accept().await.unwrap();
+
+        tokio::spawn(handle(stream));
+    }
+}
+
+async fn handle(mut stream: TcpStream) {
+    let mut data = vec![];
+    let mut buf = [0u8; 1024];
+
+    let (head, body) = loop {
+        let read = stream.read(&mut buf).await.unwrap();
+        data.extend_from_slice(&buf[..read]);
+
+        let text = String::from_utf8_lossy(&data).to_string();
+
+        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+            let length = head
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length: "))
+                .map_or(0, |length| length.parse().unwrap());
+
+            if body.len() >= length {
+                break (head.to_owned(), body.to_owned());
+            }
+        }
+    };
+
+    assert!(head.contains("authorization: secret"));
+
+    let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    let (status, body) = match (request["method"].as_str(), request.get("id")) {
+        (Some("fail"), _) => ("500 Internal Server Error", "boom".to_owned()),
+        (_, None) => ("204 No Content", String::new()),
+        (_, Some(id)) => (
+            "200 OK",
+            json!({"jsonrpc":"2.0", "id":id, "result":request["params"]}).to_string(),
+        ),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+
+    stream.write_all(response.as_bytes()).await.unwrap();
+}
+
+#[tokio::test]
+async fn http_client() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(serve(listener));
+
+    let transport =
+        HttpChannel::new(url)?.header("authorization".parse().unwrap(), "secret".parse().unwrap());
+
+    let mut client = Client::new("Test", transport);
+
+    let echo: String = client.call("echo", "hello").await?;
+
+    assert_eq!(echo, "hello");
+
+    client.notification("event", "hello").await?;
+
+    let err = client.call::<_, ()>("fail", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::ServerError(-32000, "".to_owned()));
+    assert_eq!(err.data, Some(json!({"status": 500, "body": "boom"})));
+
+    Ok(())
+}
+
+#[cfg(feature = "http-server")]
+#[tokio::test]
+async fn http_server() -> RPCResult<()> {
+    use jsonrpc_rs::{transport::http::serve_on, Server};
+
+    _ = pretty_env_logger::try_init();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let mut server = Server::default();
+
+    server
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .handle("event", |_: String| Ok(None::<()>));
+
+    tokio::spawn(serve_on(listener, server));
+
+    let mut client = Client::new("Test", HttpChannel::new(url.as_str())?);
+
+    let echo: String = client.call("echo", "hello").await?;
+
+    assert_eq!(echo, "hello");
+
+    let err = client.call::<_, ()>("missing", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::MethodNotFound);
+
+    let http = reqwest::Client::new();
+
+    let response = http
+        .post(url.as_str())
+        .body(r#"{"jsonrpc":"2.0","method":"event","params":["hello"]}"#)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 204);
+
+    let response = http
+        .post(url.as_str())
+        .body(
+            r#"[
+                {"jsonrpc":"2.0","id":1,"method":"echo","params":["a"]},
+                {"jsonrpc":"2.0","method":"event","params":["b"]},
+                {"jsonrpc":"2.0","id":2,"method":"echo","params":["c"]}
+            ]"#,
+        )
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let mut responses: Vec<serde_json::Value> =
+        serde_json::from_slice(&response.bytes().await.unwrap())?;
+
+    responses.sort_by_key(|response| response["id"].as_u64());
+
+    assert_eq!(
+        responses,
+        vec![
+            json!({"jsonrpc":"2.0", "id":1, "result":"a"}),
+            json!({"jsonrpc":"2.0", "id":2, "result":"c"}),
+        ]
+    );
+
+    let response = http.post(url.as_str()).body("[]").send().await.unwrap();
+
+    assert_eq!(response.status(), 400);
+
+    Ok(())
+}
@@ -0,0 +1,181 @@
+#![cfg(feature = "macros")]
+
+use jsonrpc_rs::{rpc, rpc_client, Client, ErrorCode, RPCError, RPCResult, RpcParams, Server};
+use serde::Serialize;
+use serde_json::json;
+
+mod common;
+use common::*;
+
+#[derive(Debug, PartialEq, Serialize, RpcParams)]
+struct Transfer {
+    from: String,
+    #[serde(rename = "recipient")]
+    to: String,
+    amount: u64,
+    memo: Option<String>,
+}
+
+#[test]
+fn positional_or_named_params() -> RPCResult<()> {
+    let expected = Transfer {
+        from: "alice".to_owned(),
+        to: "bob".to_owned(),
+        amount: 10,
+        memo: None,
+    };
+
+    let named: Transfer =
+        serde_json::from_value(json!({ "from": "alice", "recipient": "bob", "amount": 10 }))?;
+
+    assert_eq!(named, expected);
+
+    let positional: Transfer = serde_json::from_value(json!(["alice", "bob", 10]))?;
+
+    assert_eq!(positional, expected);
+
+    let positional: Transfer = serde_json::from_value(json!(["alice", "bob", 10, "rent"]))?;
+
+    assert_eq!(positional.memo.as_deref(), Some("rent"));
+
+    assert!(serde_json::from_value::<Transfer>(json!(["alice", "bob"])).is_err());
+    assert!(serde_json::from_value::<Transfer>(json!(["alice", "bob", 10, null, 1])).is_err());
+    assert!(serde_json::from_value::<Transfer>(json!({ "from": "alice", "amount": 10 })).is_err());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn rpc_params_handler() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.handle("transfer", |transfer: Transfer| Ok(Some(transfer.amount)));
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let amount: u64 = client
+        .call(
+            "transfer",
+            json!({ "from": "alice", "recipient": "bob", "amount": 3 }),
+        )
+        .await?;
+
+    assert_eq!(amount, 3);
+
+    let amount: u64 = client.call("transfer", ("alice", "bob", 5)).await?;
+
+    assert_eq!(amount, 5);
+
+    Ok(())
+}
+
+#[rpc]
+pub trait Calculator {
+    async fn add(&self, a: i32, b: i32) -> RPCResult<i32>;
+
+    #[rpc(name = "calc.negate")]
+    async fn negate(&self, value: i32) -> RPCResult<i32>;
+
+    async fn zero(&self) -> RPCResult<i32>;
+
+    async fn div(&self, a: i32, b: i32) -> RPCResult<i32> {
+        a.checked_div(b).ok_or_else(|| {
+            RPCError::new(
+                ErrorCode::InvalidParams,
+                "Division by zero".to_owned(),
+                None,
+            )
+        })
+    }
+}
+
+struct Calc;
+
+impl Calculator for Calc {
+    async fn add(&self, a: i32, b: i32) -> RPCResult<i32> {
+        Ok(a + b)
+    }
+
+    async fn negate(&self, value: i32) -> RPCResult<i32> {
+        Ok(-value)
+    }
+
+    async fn zero(&self) -> RPCResult<i32> {
+        Ok(0)
+    }
+}
+
+#[async_std::test]
+async fn rpc_trait() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    Calc.register_rpc(&mut server);
+
+    assert_eq!(
+        server.methods().collect::<Vec<_>>(),
+        vec!["add", "calc.negate", "div", "zero"]
+    );
+    assert_eq!(calculator_methods::NEGATE, "calc.negate");
+
+    server.accept(server_transport);
+
+    let mut calculator = CalculatorClient::new(Client::new("Test", client_transport));
+
+    assert_eq!(calculator.add(1, 2).await?, 3);
+    assert_eq!(calculator.negate(4).await?, -4);
+    assert_eq!(calculator.zero().await?, 0);
+    assert_eq!(calculator.div(6, 3).await?, 2);
+
+    let err = calculator.div(1, 0).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::InvalidParams);
+
+    Ok(())
+}
+
+rpc_client! {
+    /// Client of the echo service.
+    pub struct EchoClient {
+        async fn echo(msg: String) -> RPCResult<String>;
+
+        #[rpc(name = "echo.concat")]
+        async fn concat(a: String, b: String) -> RPCResult<String>;
+    }
+}
+
+#[async_std::test]
+async fn typed_client() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server
+        .handle(echo_client_methods::ECHO, |(msg,): (String,)| Ok(Some(msg)))
+        .handle(echo_client_methods::CONCAT, |(a, b): (String, String)| {
+            Ok(Some(a + &b))
+        });
+
+    server.accept(server_transport);
+
+    let mut client = EchoClient::new(Client::new("Test", client_transport));
+
+    assert_eq!(client.echo("hello".to_owned()).await?, "hello");
+    assert_eq!(
+        client.concat("foo".to_owned(), "bar".to_owned()).await?,
+        "foobar"
+    );
+
+    Ok(())
+}
@@ -1,7 +1,6 @@
 use std::time::Duration;
 
 use async_std::task::spawn;
-use async_timer_rs::{hashed::Timeout, Timer};
 use futures::{
     channel::mpsc::{self, SendError, Sender},
     executor::ThreadPool,
@@ -11,6 +10,7 @@ use futures::{
 };
 use jsonrpc_rs::{
     channel::{RPCData, TransportChannel},
+    timer::timeout,
     Client, RPCError, RPCResult, Server,
 };
 use once_cell::sync::OnceCell;
@@ -52,9 +52,9 @@ async fn pingpong() -> RPCResult<()> {
 
     let (client_output, server_input) = mpsc::channel(20);
 
-    let server_transport = MPSCTransportChannel(server_input.map(|c| Ok(c)).boxed(), server_output);
+    let server_transport = MPSCTransportChannel(server_input.map(Ok).boxed(), server_output);
 
-    let client_transport = MPSCTransportChannel(client_input.map(|c| Ok(c)).boxed(), client_output);
+    let client_transport = MPSCTransportChannel(client_input.map(Ok).boxed(), client_output);
 
     let mut server = Server::default();
 
@@ -84,13 +84,13 @@ async fn pingpong() -> RPCResult<()> {
     assert_eq!(echo, "world");
 
     let echo: String = client
-        .call_with_timer("echo", "hello", Timeout::new(Duration::from_secs(10)))
+        .call_with_timer("echo", "hello", timeout(Duration::from_secs(10)))
         .await?;
 
     assert_eq!(echo, "hello");
 
     let echo: String = client
-        .call_with_timer("echo", "world", Timeout::new(Duration::from_secs(10)))
+        .call_with_timer("echo", "world", timeout(Duration::from_secs(10)))
         .await?;
 
     assert_eq!(echo, "world");
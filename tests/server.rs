@@ -0,0 +1,2039 @@
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{self, Poll},
+    time::{Duration, Instant},
+};
+
+use async_timer_rs::{hashed::Timeout, Timer};
+use futures::{
+    channel::mpsc, future::BoxFuture, stream::BoxStream, Future, Sink, SinkExt, StreamExt,
+};
+use jsonrpc_rs::{
+    channel::{RPCData, TransportChannel},
+    Call, CallResult, Client, ClientConfig, Codec, Context, ErrorCode, JsonCodec, Metrics,
+    Middleware, Next, RPCError, RPCResult, RegisterError, Response, SendErrorPolicy, Server,
+    ServerBuilder, Subscription, SubscriptionSink, Task, CANCEL_METHOD, HANDLER_TIMEOUT,
+    PING_METHOD, RATE_LIMITED,
+};
+use serde_json::json;
+
+mod common;
+use common::*;
+
+#[async_std::test]
+async fn handler_context() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::new("ctx");
+
+    server
+        .handle_with_ctx("login", |ctx: Context, user: String| {
+            ctx.extensions().insert(user);
+            Ok(Some(ctx.id().is_some() && ctx.method() == "login"))
+        })
+        .async_handle_with_ctx("whoami", |ctx: Context, _: ()| async move {
+            let user = ctx.extensions().get::<String>().unwrap_or_default();
+
+            Ok(Some(format!("{}@{}", user, ctx.session_id())))
+        });
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let has_id: bool = client.call("login", "alice").await?;
+
+    assert!(has_id);
+
+    let whoami: String = client.call("whoami", ()).await?;
+
+    assert!(whoami.starts_with("alice@ctx_"));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn slow_call_threshold() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::builder("Test")
+        .slow_call_threshold(Duration::from_millis(10))
+        .handle("fast", |()| Ok(Some("fast")))
+        .async_handle("slow", |()| async {
+            Timeout::new(Duration::from_millis(50)).await;
+
+            Ok(Some("slow"))
+        })
+        .build();
+
+    server.accept(server_transport);
+
+    let mut client = Client::builder("Test")
+        .slow_call_threshold(Duration::from_millis(10))
+        .build(client_transport);
+
+    // Slow calls are only logged, their results are unaffected.
+    assert_eq!(client.call::<_, String>("slow", ()).await?, "slow");
+    assert_eq!(client.call::<_, String>("fast", ()).await?, "fast");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn executor() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let spawned = Arc::new(AtomicUsize::new(0));
+
+    let executor = {
+        let spawned = spawned.clone();
+
+        move |task: Task| {
+            spawned.fetch_add(1, Ordering::SeqCst);
+
+            async_std::task::spawn(task);
+        }
+    };
+
+    let mut server = Server::builder("Test")
+        .executor(executor.clone())
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .build();
+
+    server.accept(server_transport);
+
+    // The session runs on the executor.
+    assert_eq!(spawned.load(Ordering::SeqCst), 1);
+
+    let mut client = Client::with_config(
+        "Test",
+        client_transport,
+        ClientConfig::default().executor(executor),
+    );
+
+    // So do the send and receive loops of the client.
+    assert_eq!(spawned.load(Ordering::SeqCst), 3);
+
+    let echo: String = client.call("echo", "hello").await?;
+
+    assert_eq!(echo, "hello");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn namespace() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server
+        .namespace("wallet")
+        .handle("balance", |account: String| {
+            Ok(Some(format!("{}: 1", account)))
+        })
+        .async_handle_with_ctx("method", |ctx: Context, ()| async move {
+            Ok(Some(ctx.method().to_owned()))
+        })
+        .namespace("admin")
+        .handle0("freeze", || Ok(Some("frozen")));
+
+    server.namespace("net").handle0("peers", || Ok(Some(3)));
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    assert_eq!(
+        client.call::<_, String>("wallet.balance", "alice").await?,
+        "alice: 1"
+    );
+    assert_eq!(
+        client.call::<_, String>("wallet.method", ()).await?,
+        "wallet.method"
+    );
+    assert_eq!(
+        client.call::<_, String>("wallet.admin.freeze", ()).await?,
+        "frozen"
+    );
+    assert_eq!(client.call::<_, usize>("net.peers", ()).await?, 3);
+
+    let err = client
+        .call::<_, String>("balance", "alice")
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::MethodNotFound);
+
+    Ok(())
+}
+
+/// Rejects calls of `admin.` methods whose params lack the token.
+struct Auth(Arc<Mutex<Vec<String>>>);
+
+impl Middleware<JsonCodec> for Auth {
+    fn around(
+        &self,
+        call: Call<JsonCodec>,
+        next: Next<JsonCodec>,
+    ) -> BoxFuture<'static, CallResult> {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("auth {}", call.method()));
+
+        if call.method().starts_with("admin.")
+            && call.params::<String>().ok().as_deref() != Some("secret")
+        {
+            return Box::pin(futures::future::ready(Err(RPCError::new(
+                ErrorCode::ServerError(-32040, String::new()),
+                "Unauthorized".to_owned(),
+                None,
+            ))));
+        }
+
+        next.run(call)
+    }
+}
+
+#[async_std::test]
+async fn middleware() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let calls = Arc::new(Mutex::new(vec![]));
+
+    let shout = calls.clone();
+
+    let mut server = Server::builder("Test")
+        .layer(Auth(calls.clone()))
+        .layer(move |call: Call<JsonCodec>, next: Next<JsonCodec>| {
+            shout
+                .lock()
+                .unwrap()
+                .push(format!("shout {}", call.method()));
+
+            let response = next.run(call);
+
+            Box::pin(async move {
+                let Some(frame) = response.await? else {
+                    return Ok(None);
+                };
+
+                let response: Response<String, String, serde_json::Value> =
+                    JsonCodec::decode(&frame)?;
+
+                let result = response.result.unwrap_or_default().to_uppercase();
+
+                Ok(Some(JsonCodec::encode(
+                    &Response::<String, _, ()>::success(response.id, result),
+                )?))
+            }) as BoxFuture<'static, CallResult>
+        })
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .async_handle("admin.reset", |_token: String| async { Ok(Some("reset")) })
+        .build();
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    assert_eq!(client.call::<_, String>("echo", "hello").await?, "HELLO");
+    assert_eq!(
+        client.call::<_, String>("admin.reset", "secret").await?,
+        "RESET"
+    );
+
+    // The outer middleware short-circuits, the inner one and the handler never run.
+    let err = client
+        .call::<_, String>("admin.reset", "guess")
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::ServerError(-32040, String::new()));
+    assert_eq!(err.message, "Unauthorized");
+
+    assert_eq!(
+        *calls.lock().unwrap(),
+        [
+            "auth echo",
+            "shout echo",
+            "auth admin.reset",
+            "shout admin.reset",
+            "auth admin.reset",
+        ]
+    );
+
+    // Built-in methods bypass the middlewares.
+    assert!(!client.call::<_, bool>(CANCEL_METHOD, 1000).await?);
+
+    assert_eq!(calls.lock().unwrap().len(), 5);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn case_insensitive() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let register = |builder: ServerBuilder| {
+        builder
+            .handle("getBalance", |account: String| Ok(Some(account)))
+            .async_handle_with_ctx("wallet.Method", |ctx: Context, ()| async move {
+                Ok(Some(ctx.method().to_owned()))
+            })
+            .handle_with_ctx("Colliding", |ctx: Context, ()| {
+                Ok(Some(ctx.method().to_owned()))
+            })
+            .async_handle_with_ctx("COLLIDING", |ctx: Context, ()| async move {
+                Ok(Some(ctx.method().to_owned()))
+            })
+            .handle_ping()
+    };
+
+    let (exact_transport, exact_client) = transport_pair();
+
+    register(Server::builder("Test"))
+        .build()
+        .accept(exact_transport);
+
+    let mut exact = Client::new("Test", exact_client);
+
+    // Exact matching by default.
+    let err = exact
+        .call::<_, String>("getbalance", "alice")
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::MethodNotFound);
+
+    let mut server = register(Server::builder("Test").case_insensitive(true)).build();
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    assert_eq!(
+        client.call::<_, String>("GETBALANCE", "alice").await?,
+        "alice"
+    );
+    assert_eq!(
+        client.call::<_, String>("WALLET.METHOD", ()).await?,
+        "wallet.Method"
+    );
+
+    // Names differing only in case resolve to the first one registered.
+    assert_eq!(
+        client.call::<_, String>("colliding", ()).await?,
+        "Colliding"
+    );
+
+    assert!(server.remove_method("Colliding"));
+
+    assert_eq!(
+        client.call::<_, String>("colliding", ()).await?,
+        "COLLIDING"
+    );
+
+    // Reserved methods are still matched exactly.
+    client.call::<_, serde_json::Value>("rpc.ping", ()).await?;
+
+    let err = client
+        .call::<_, serde_json::Value>("RPC.PING", ())
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::MethodNotFound);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn shared_state() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let server = Server::builder("Test")
+        .with_state(AtomicUsize::new(0))
+        .handle_stateful("incr", |counter: Arc<AtomicUsize>, step: usize| {
+            Ok(Some(counter.fetch_add(step, Ordering::SeqCst) + step))
+        })
+        .async_handle_stateful("get", |counter: Arc<AtomicUsize>, _: ()| async move {
+            Ok(Some(counter.load(Ordering::SeqCst)))
+        })
+        .build();
+
+    server.clone().accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let value: usize = client.call("incr", 2).await?;
+
+    assert_eq!(value, 2);
+
+    let value: usize = client.call("get", ()).await?;
+
+    assert_eq!(value, 2);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn shared_handlers() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    // Neither `Clone`, nor cloned per call.
+    struct Counter(AtomicUsize);
+
+    let counter = Arc::new(Counter(AtomicUsize::new(0)));
+    let async_counter = counter.clone();
+
+    let mut server = Server::default();
+
+    server
+        .handle_shared("incr", move |step: usize| {
+            Ok(Some(counter.0.fetch_add(step, Ordering::SeqCst) + step))
+        })
+        .async_handle_shared("get", move |_: ()| {
+            let value = async_counter.0.load(Ordering::SeqCst);
+
+            async move { Ok(Some(value)) }
+        });
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    for step in 1..=3 {
+        let _: usize = client.call("incr", step).await?;
+    }
+
+    let value: usize = client.call("get", ()).await?;
+
+    assert_eq!(value, 6);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn concurrent_async_handlers() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server
+        .async_handle("slow", |msg: String| async move {
+            Timeout::new(Duration::from_secs(2)).await;
+            Ok(Some(msg))
+        })
+        .async_handle("fast", |msg: String| async move { Ok(Some(msg)) });
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let now = Instant::now();
+
+    let mut slow = client.send("slow", "slow").await?;
+
+    let fast: String = client.call("fast", "fast").await?;
+
+    assert_eq!(fast, "fast");
+    assert!(now.elapsed() < Duration::from_secs(1));
+
+    let slow: String = slow.recv().await?;
+
+    assert_eq!(slow, "slow");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn ordered_responses() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::builder("Test")
+        .ordered_responses(true)
+        .async_handle("sleep", |millis: u64| async move {
+            Timeout::new(Duration::from_millis(millis)).await;
+            Ok(Some(millis))
+        })
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .build();
+
+    server.accept(server_transport);
+
+    let now = Instant::now();
+
+    for frame in [
+        r#"{"jsonrpc":"2.0","id":1,"method":"sleep","params":[500]}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"sleep","params":[400]}"#,
+        r#"{"jsonrpc":"2.0","method":"echo","params":["notified"]}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"echo","params":["hello"]}"#,
+        r#"{"jsonrpc":"2.0","id":4,"method":"missing"}"#,
+    ] {
+        output.send(frame.into()).await?;
+    }
+
+    let mut ids = vec![];
+
+    for _ in 0..4 {
+        let response: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+        ids.push(response["id"].as_u64().unwrap());
+    }
+
+    assert_eq!(ids, vec![1, 2, 3, 4]);
+
+    // Handlers still ran concurrently.
+    assert!(now.elapsed() < Duration::from_millis(850));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn coalesce_responses() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::builder("Test")
+        .coalesce_responses(2)
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .build();
+
+    // Queue the burst before the session starts, so all frames are ready at once.
+    for id in 1..=3 {
+        output
+            .send(
+                json!({"jsonrpc":"2.0","id":id,"method":"echo","params":[id.to_string()]})
+                    .to_string()
+                    .into(),
+            )
+            .await?;
+    }
+
+    server.accept(server_transport);
+
+    let batch: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(
+        batch,
+        json!([
+            {"jsonrpc":"2.0","id":1,"result":"1"},
+            {"jsonrpc":"2.0","id":2,"result":"2"},
+        ])
+    );
+
+    // The remaining response is written alone once no more requests are ready.
+    let response: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(response, json!({"jsonrpc":"2.0","id":3,"result":"3"}));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn coalesce_responses_with_notifications() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::builder("Test")
+        .coalesce_responses(4)
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .stream_handle("progress", |steps: usize| {
+            futures::stream::iter((1..=steps).map(|step| Ok(step * 10)))
+        })
+        .build();
+
+    server.accept(server_transport);
+
+    let client = Client::new("Test", client_transport);
+
+    let (mut streaming, mut first, mut second) = (client.clone(), client.clone(), client);
+
+    // Partial results are written as notifications between the coalesced responses.
+    let progress = async {
+        let progress = streaming
+            .call_stream::<_, usize>("progress", [3])
+            .await?
+            .collect::<Vec<_>>()
+            .await;
+
+        progress.into_iter().collect::<RPCResult<Vec<_>>>()
+    };
+
+    let (progress, hello, world) = async_std::future::timeout(
+        Duration::from_secs(2),
+        futures::future::try_join3(
+            progress,
+            first.call::<_, String>("echo", ["hello"]),
+            second.call::<_, String>("echo", ["world"]),
+        ),
+    )
+    .await
+    .expect("responses of the calls")?;
+
+    assert_eq!(progress, vec![10, 20, 30]);
+    assert_eq!(hello, "hello");
+    assert_eq!(world, "world");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn session_registry() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (first_transport, MPSCTransportChannel(mut first_input, mut first_output)) =
+        transport_pair();
+    let (second_transport, MPSCTransportChannel(mut second_input, _second_output)) =
+        transport_pair();
+
+    let mut server = Server::new("registry");
+
+    server.async_handle("slow", |msg: String| async move {
+        Timeout::new(Duration::from_millis(200)).await;
+        Ok(Some(msg))
+    });
+
+    server.accept(first_transport);
+    server.accept(second_transport);
+
+    assert_eq!(server.session_count(), 2);
+    assert!(server
+        .session_ids()
+        .iter()
+        .all(|id| id.starts_with("registry_")));
+    assert!(!server.close_session("registry_unknown"));
+
+    first_output
+        .send(
+            json!({"jsonrpc":"2.0","id":1,"method":"slow","params":["slow"]})
+                .to_string()
+                .into(),
+        )
+        .await?;
+
+    // Let the session read the request before it's closed.
+    Timeout::new(Duration::from_millis(50)).await;
+
+    server.close_sessions();
+
+    // Pending calls are still answered before the session stops.
+    let response: serde_json::Value = serde_json::from_slice(&first_input.next().await.unwrap()?)?;
+
+    assert_eq!(response, json!({"jsonrpc":"2.0","id":1,"result":"slow"}));
+
+    assert!(first_input.next().await.is_none());
+    assert!(second_input.next().await.is_none());
+
+    assert_eq!(server.session_count(), 0);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn abort_sessions() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::new("abort");
+
+    server.async_handle("slow", |_: ()| async {
+        Timeout::new(Duration::from_secs(5)).await;
+
+        Ok(Some(()))
+    });
+
+    server.accept(server_transport);
+
+    output
+        .send(
+            json!({"jsonrpc":"2.0","id":1,"method":"slow"})
+                .to_string()
+                .into(),
+        )
+        .await?;
+
+    // Let the session read the request before it's aborted.
+    Timeout::new(Duration::from_millis(50)).await;
+
+    let now = Instant::now();
+
+    server.abort_sessions();
+
+    assert_eq!(server.session_count(), 0);
+
+    // The pending call isn't answered, the transport is dropped at once.
+    assert!(input.next().await.is_none());
+    assert!(now.elapsed() < Duration::from_secs(1));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn idle_timeout() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::builder("idle")
+        .idle_timeout(Duration::from_millis(300))
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .build();
+
+    server.accept(server_transport);
+
+    // Every received frame restarts the timer.
+    for id in 0..4 {
+        output
+            .send(
+                json!({"jsonrpc":"2.0","id":id,"method":"echo","params":["hello"]})
+                    .to_string()
+                    .into(),
+            )
+            .await?;
+
+        input.next().await.unwrap()?;
+
+        Timeout::new(Duration::from_millis(150)).await;
+    }
+
+    assert_eq!(server.session_count(), 1);
+
+    let now = Instant::now();
+
+    // Once nothing arrives the session closes the connection.
+    assert!(input.next().await.is_none());
+    assert!(now.elapsed() < Duration::from_secs(1));
+    assert_eq!(server.session_count(), 0);
+
+    Ok(())
+}
+
+/// Sink failing its next `failures` writes.
+struct FlakySink {
+    inner: mpsc::Sender<RPCData>,
+    failures: Arc<AtomicUsize>,
+}
+
+impl Sink<RPCData> for FlakySink {
+    type Error = RPCError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<RPCResult<()>> {
+        Pin::new(&mut self.inner).poll_ready(cx).map_err(Into::into)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: RPCData) -> RPCResult<()> {
+        if self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok()
+        {
+            return Err(RPCError::new(
+                ErrorCode::InternalError,
+                "write hiccup".to_owned(),
+                None,
+            ));
+        }
+
+        Pin::new(&mut self.inner)
+            .start_send(item)
+            .map_err(Into::into)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<RPCResult<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx).map_err(Into::into)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<RPCResult<()>> {
+        Pin::new(&mut self.inner).poll_close(cx).map_err(Into::into)
+    }
+}
+
+struct FlakyTransportChannel(BoxStream<'static, RPCResult<RPCData>>, FlakySink);
+
+impl TransportChannel for FlakyTransportChannel {
+    type StreamError = RPCError;
+
+    type SinkError = RPCError;
+
+    type Input = BoxStream<'static, RPCResult<RPCData>>;
+
+    type Output = FlakySink;
+
+    fn spawn<Fut>(future: Fut)
+    where
+        Fut: Future<Output = RPCResult<()>> + Send + 'static,
+    {
+        MPSCTransportChannel::spawn(future)
+    }
+
+    fn framed(self) -> (Self::Input, Self::Output) {
+        (self.0, self.1)
+    }
+}
+
+#[async_std::test]
+async fn on_send_error() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let request = |id: usize| -> RPCData {
+        json!({"jsonrpc":"2.0","id":id,"method":"echo","params":["hello"]})
+            .to_string()
+            .into()
+    };
+
+    for (policy, failures, answered) in [
+        (None, 1, None),
+        (Some(SendErrorPolicy::Continue), 1, Some(2)),
+        (Some(SendErrorPolicy::Retry(2)), 2, Some(1)),
+        (Some(SendErrorPolicy::Retry(1)), 2, None),
+    ] {
+        let (MPSCTransportChannel(server_input, server_output), client_transport) =
+            transport_pair();
+
+        let failures = Arc::new(AtomicUsize::new(failures));
+
+        let server_transport = FlakyTransportChannel(
+            server_input,
+            FlakySink {
+                inner: server_output,
+                failures: failures.clone(),
+            },
+        );
+
+        let mut builder = Server::builder("flaky");
+
+        if let Some(policy) = policy {
+            builder = builder.on_send_error(policy);
+        }
+
+        let mut server = builder.handle("echo", |msg: String| Ok(Some(msg))).build();
+
+        server.accept(server_transport);
+
+        let MPSCTransportChannel(mut input, mut output) = client_transport;
+
+        output.send(request(1)).await?;
+        // An aborted session may have stopped reading already.
+        _ = output.send(request(2)).await;
+
+        match answered {
+            Some(id) => {
+                let response: serde_json::Value =
+                    serde_json::from_slice(&input.next().await.unwrap()?).unwrap();
+
+                assert_eq!(response["id"], json!(id), "{:?}", policy);
+                assert_eq!(server.session_count(), 1);
+            }
+            // The session ended on the write error.
+            None => assert!(input.next().await.is_none(), "{:?}", policy),
+        }
+    }
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn broadcast() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (first_transport, MPSCTransportChannel(mut first_input, _first_output)) = transport_pair();
+    let (second_transport, second_client) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.accept(first_transport);
+    server.accept(second_transport);
+
+    assert_eq!(server.broadcast("update", ["first"])?, 2);
+
+    let notification = json!({"jsonrpc":"2.0","method":"update","params":["first"]});
+
+    let MPSCTransportChannel(mut second_input, second_output) = second_client;
+
+    for input in [&mut first_input, &mut second_input] {
+        let frame: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+        assert_eq!(frame, notification);
+    }
+
+    // Closing the connection ends the second session, which leaves the registry.
+    drop(second_output);
+
+    assert!(second_input.next().await.is_none());
+
+    assert_eq!(server.broadcast("update", ["second"])?, 1);
+
+    let frame: serde_json::Value = serde_json::from_slice(&first_input.next().await.unwrap()?)?;
+
+    assert_eq!(frame["params"], json!(["second"]));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn handler_notifier() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.async_handle_with_ctx("work", |ctx: Context, steps: usize| async move {
+        let notifier = ctx.notifier();
+
+        for step in 1..=steps {
+            notifier.notify("progress", [step])?;
+        }
+
+        Ok(Some("done"))
+    });
+
+    server.accept(server_transport);
+
+    output
+        .send(
+            json!({"jsonrpc":"2.0","id":1,"method":"work","params":2})
+                .to_string()
+                .into(),
+        )
+        .await?;
+
+    let mut frames = vec![];
+
+    for _ in 0..3 {
+        frames.push(serde_json::from_slice::<serde_json::Value>(
+            &input.next().await.unwrap()?,
+        )?);
+    }
+
+    // Notifications pushed by the call are written whole, before its response.
+    assert_eq!(
+        frames,
+        [
+            json!({"jsonrpc":"2.0","method":"progress","params":[1]}),
+            json!({"jsonrpc":"2.0","method":"progress","params":[2]}),
+            json!({"jsonrpc":"2.0","id":1,"result":"done"}),
+        ]
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn sync_handler_notifier() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.handle_with_ctx("work", |ctx: Context, steps: usize| {
+        let notifier = ctx.notifier();
+
+        for step in 1..=steps {
+            notifier.notify("progress", [step])?;
+        }
+
+        Ok(Some("done"))
+    });
+
+    server.accept(server_transport);
+
+    output
+        .send(
+            json!({"jsonrpc":"2.0","id":1,"method":"work","params":2})
+                .to_string()
+                .into(),
+        )
+        .await?;
+
+    let mut frames = vec![];
+
+    for _ in 0..3 {
+        frames.push(serde_json::from_slice::<serde_json::Value>(
+            &input.next().await.unwrap()?,
+        )?);
+    }
+
+    // The response of a call completed inline follows its notifications as well.
+    assert_eq!(
+        frames,
+        [
+            json!({"jsonrpc":"2.0","method":"progress","params":[1]}),
+            json!({"jsonrpc":"2.0","method":"progress","params":[2]}),
+            json!({"jsonrpc":"2.0","id":1,"result":"done"}),
+        ]
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn subscription() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let sinks = Arc::new(Mutex::new(Vec::<SubscriptionSink>::new()));
+
+    let mut server = Server::default();
+
+    let registered = sinks.clone();
+
+    server.handle_subscription(
+        "subscribe",
+        "unsubscribe",
+        "ticks",
+        move |count: u64, sink| {
+            for tick in 0..count {
+                sink.notify(tick)?;
+            }
+
+            registered.lock().unwrap().push(sink);
+
+            Ok(())
+        },
+    );
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let ticks: Subscription<u64> = client
+        .subscribe("subscribe", "unsubscribe", "ticks", 3)
+        .await?;
+
+    let id = ticks.id();
+
+    let mut ticks = ticks.take(3);
+
+    let mut values = vec![];
+
+    while let Some(tick) = ticks.next().await {
+        values.push(tick?);
+    }
+
+    assert_eq!(values, vec![0, 1, 2]);
+
+    assert!(ticks.into_inner().unsubscribe().await?);
+
+    let sink = sinks.lock().unwrap().pop().unwrap();
+
+    assert_eq!(sink.id(), id);
+    assert!(sink.is_closed());
+    assert_eq!(
+        sink.notify(3).unwrap_err().code,
+        ErrorCode::ConnectionClosed
+    );
+
+    // Dropping the stream unsubscribes as well.
+    let ticks: Subscription<u64> = client
+        .subscribe("subscribe", "unsubscribe", "ticks", 0)
+        .await?;
+
+    drop(ticks);
+
+    // Unsubscribing is sent ahead of this call, so it was handled once the call returns.
+    assert!(!client.call::<_, bool>("unsubscribe", [id]).await?);
+
+    assert!(sinks.lock().unwrap().pop().unwrap().is_closed());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn subscription_routed_by_method() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let sinks = Arc::new(Mutex::new(Vec::<SubscriptionSink>::new()));
+
+    let mut server = Server::default();
+
+    let registered = sinks.clone();
+
+    server
+        .handle_subscription("subscribe", "unsubscribe", "ticks", move |_: (), sink| {
+            registered.lock().unwrap().push(sink);
+
+            Ok(())
+        })
+        .handle_with_ctx("noise", |ctx, id: u64| {
+            let notifier = ctx.notifier();
+
+            // Same shape, other method.
+            notifier.notify("progress", json!({ "subscription": id, "result": 99 }))?;
+            notifier.notify("progress", (id, 98))?;
+            // Same method, positional params.
+            notifier.notify("ticks", (id, 97))?;
+
+            Ok(Some(true))
+        });
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let mut ticks: Subscription<u64> = client
+        .subscribe("subscribe", "unsubscribe", "ticks", ())
+        .await?;
+
+    assert!(client.call::<_, bool>("noise", [ticks.id()]).await?);
+
+    sinks.lock().unwrap()[0].notify(7)?;
+
+    assert_eq!(ticks.next().await.unwrap()?, 7);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn server_builder() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::builder("builder")
+        .ordered_responses(true)
+        .with_state(AtomicUsize::new(0))
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .handle_stateful("count", |counter: Arc<AtomicUsize>, ()| {
+            Ok(Some(counter.fetch_add(1, Ordering::SeqCst) + 1))
+        })
+        .try_async_handle("sum", |values: Vec<u64>| async move {
+            Ok(Some(values.iter().sum::<u64>()))
+        })
+        .expect("sum registered")
+        .namespace("math", |math| {
+            math.handle("double", |value: u64| Ok(Some(value * 2)));
+        })
+        .build();
+
+    let duplicated = Server::builder("builder")
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .try_handle("echo", |_: String| Ok(Some(())));
+
+    assert_eq!(
+        duplicated.err(),
+        Some(RegisterError::AlreadyRegistered("echo".to_owned()))
+    );
+
+    let mut methods = server.methods().collect::<Vec<_>>();
+
+    methods.sort();
+
+    assert_eq!(methods, vec!["count", "echo", "math.double", "sum"]);
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    assert_eq!(client.call::<_, String>("echo", "hello").await?, "hello");
+    assert_eq!(client.call::<_, usize>("count", ()).await?, 1);
+    assert_eq!(client.call::<_, usize>("count", ()).await?, 2);
+    assert_eq!(client.call::<_, u64>("sum", [1, 2]).await?, 3);
+    assert_eq!(client.call::<_, u64>("math.double", [2]).await?, 4);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn handle_with_concurrency() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+
+    let mut server = Server::default();
+
+    let (counter, max) = (running.clone(), max_running.clone());
+
+    server.handle_with_concurrency("query", 2, move |id: usize| {
+        let (counter, max) = (counter.clone(), max.clone());
+
+        async move {
+            max.fetch_max(counter.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+
+            Timeout::new(Duration::from_millis(100)).await;
+
+            counter.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(Some(id))
+        }
+    });
+
+    server.accept(server_transport);
+
+    let client = Client::new("Test", client_transport);
+
+    let calls = (0..6).map(|id| {
+        let mut client = client.clone();
+
+        async move { client.call::<_, usize>("query", [id]).await }
+    });
+
+    let results = futures::future::try_join_all(calls).await?;
+
+    // Queued calls wait for a permit instead of failing.
+    assert_eq!(results, vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(max_running.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn rate_limit() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let mut server = Server::default();
+
+    server
+        .handle_with_rate_limit("global", 2, |()| async { Ok(Some(())) })
+        .handle_with_session_rate_limit("session", 1, |()| async { Ok(Some(())) });
+
+    let mut clients = vec![];
+
+    for _ in 0..2 {
+        let (server_transport, client_transport) = transport_pair();
+
+        server.accept(server_transport);
+
+        clients.push(Client::new("Test", client_transport));
+    }
+
+    let mut results = vec![];
+
+    for client in &mut clients {
+        for _ in 0..2 {
+            results.push(client.call::<_, ()>("global", ()).await.is_ok());
+        }
+    }
+
+    // The global rate is shared by all sessions.
+    assert_eq!(results, vec![true, true, false, false]);
+
+    let err = clients[0].call::<_, ()>("global", ()).await.unwrap_err();
+
+    assert_eq!(json!(err.code), json!(RATE_LIMITED));
+    assert_eq!(err.message, "Method global rate limited");
+
+    // Each session has its own rate.
+    for client in &mut clients {
+        client.call::<_, ()>("session", ()).await?;
+
+        assert!(client.call::<_, ()>("session", ()).await.is_err());
+    }
+
+    // Tokens refill over time.
+    Timeout::new(Duration::from_millis(1100)).await;
+
+    clients[0].call::<_, ()>("session", ()).await?;
+    clients[1].call::<_, ()>("global", ()).await?;
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn cancellation() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.async_handle_with_ctx("work", |ctx: Context, ()| async move {
+        let canceled = Box::pin(ctx.cancellation().cancelled());
+
+        let work = Box::pin(Timeout::new(Duration::from_secs(5)));
+
+        let result = match futures::future::select(canceled, work).await {
+            futures::future::Either::Left(_) => "canceled",
+            futures::future::Either::Right(_) => "done",
+        };
+
+        Ok(Some(result))
+    });
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let now = Instant::now();
+
+    let mut first = client.send("work", ()).await?;
+    let mut second = client.send("work", ()).await?;
+
+    client.cancel(first.id()).await?;
+
+    assert_eq!(first.recv::<String>().await?, "canceled");
+    assert!(now.elapsed() < Duration::from_secs(1));
+
+    // Only the canceled call observes the cancellation.
+    assert!(
+        async_std::future::timeout(Duration::from_millis(200), second.recv::<String>())
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn session_cancellation() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(_input, mut output)) = transport_pair();
+
+    let (observed, canceled) = futures::channel::oneshot::channel();
+
+    let observed = Arc::new(Mutex::new(Some(observed)));
+
+    let mut server = Server::default();
+
+    server.async_handle_with_ctx("work", move |ctx: Context, ()| {
+        let observed = observed.clone();
+
+        async move {
+            ctx.cancellation().cancelled().await;
+
+            _ = observed.lock().unwrap().take().unwrap().send(());
+
+            Ok(Some(()))
+        }
+    });
+
+    server.accept(server_transport);
+
+    output
+        .send(
+            json!({"jsonrpc":"2.0","id":1,"method":"work"})
+                .to_string()
+                .into(),
+        )
+        .await?;
+
+    // Closing the connection cancels the calls in flight.
+    drop(output);
+
+    async_std::future::timeout(Duration::from_secs(1), canceled)
+        .await
+        .expect("cancellation observed")
+        .unwrap();
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn stream_handle() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server
+        .stream_handle("progress", |steps: usize| {
+            futures::stream::iter((1..=steps).map(|step| Ok(step * 10)))
+        })
+        .stream_handle("failing", |()| {
+            futures::stream::iter(vec![
+                Ok(1),
+                Err(RPCError::server_error(-32010, "failed", json!(null))),
+                Ok(2),
+            ])
+        });
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let progress = client
+        .call_stream::<_, usize>("progress", [3])
+        .await?
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(
+        progress.into_iter().collect::<RPCResult<Vec<_>>>()?,
+        vec![10, 20, 30]
+    );
+
+    let empty = client
+        .call_stream::<_, usize>("progress", [0])
+        .await?
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(empty.into_iter().collect::<RPCResult<Vec<_>>>()?, vec![0]);
+
+    let failing = client
+        .call_stream::<_, usize>("failing", ())
+        .await?
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(failing.len(), 2);
+    assert_eq!(failing[0].as_ref().unwrap(), &1);
+    assert_eq!(failing[1].as_ref().unwrap_err().message, "failed");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn stream_routed_by_method() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.async_handle_with_ctx("stream", |ctx, ()| async move {
+        let id = ctx.id().unwrap();
+
+        let notifier = ctx.notifier();
+
+        // Same shape, other method.
+        notifier.notify("other", json!({ "id": id, "result": 99 }))?;
+        // Same method, positional params.
+        notifier.notify("stream", (id, 98))?;
+
+        notifier.notify("stream", json!({ "id": id, "result": 5 }))?;
+
+        Ok(Some(6))
+    });
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let results = client
+        .call_stream::<_, usize>("stream", ())
+        .await?
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(
+        results.into_iter().collect::<RPCResult<Vec<_>>>()?,
+        vec![5, 6]
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn panicking_handlers() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server
+        .handle("panic", |_: ()| -> RPCResult<Option<()>> { panic!("sync") })
+        .async_handle("async_panic", |_: ()| async move {
+            if true {
+                panic!("async");
+            }
+
+            Ok(Some(()))
+        })
+        .async_handle("echo", |msg: String| async { Ok(Some(msg)) });
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let err = client.call::<_, ()>("panic", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::InternalError);
+
+    let err = client.call::<_, ()>("async_panic", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::InternalError);
+
+    // The session survives panicking handlers.
+    let echo: String = client.call("echo", "hello").await?;
+
+    assert_eq!(echo, "hello");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn handler_timeout() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.handle_with_timeout(
+        "sleep",
+        Duration::from_millis(500),
+        |secs: u64| async move {
+            Timeout::new(Duration::from_secs(secs)).await;
+            Ok(Some(secs))
+        },
+    );
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let err = client.call::<_, u64>("sleep", 5).await.unwrap_err();
+
+    // A server side timeout isn't a local one.
+    assert!(matches!(
+        err.code,
+        ErrorCode::ServerError(HANDLER_TIMEOUT, _)
+    ));
+
+    let code = serde_json::to_value(&err.code)?;
+
+    assert_eq!(code, json!(HANDLER_TIMEOUT));
+    assert_ne!(code, serde_json::to_value(ErrorCode::Timeout)?);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn server_error_data() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.handle("withdraw", |amount: u64| -> RPCResult<Option<u64>> {
+        Err(RPCError::server_error(
+            -32010,
+            "Insufficient funds",
+            json!({ "requested": amount, "balance": 1 }),
+        ))
+    });
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let err = client.call::<_, u64>("withdraw", 10).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::ServerError(-32010, "".to_owned()));
+    assert_eq!(err.message, "Insufficient funds");
+    assert_eq!(err.data, Some(json!({ "requested": 10, "balance": 1 })));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn single_element_array_params() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server
+        .handle("sum", |values: Vec<i32>| {
+            Ok(Some(values.iter().sum::<i32>()))
+        })
+        .handle("count", |values: Vec<Vec<i32>>| Ok(Some(values.len())))
+        .handle("echo", |msg: String| Ok(Some(msg)));
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let sum: i32 = client.call("sum", vec![vec![1, 2, 3]]).await?;
+
+    assert_eq!(sum, 6);
+
+    let count: usize = client.call("count", vec![vec![1, 2, 3]]).await?;
+
+    assert_eq!(count, 1);
+
+    let echo: String = client.call("echo", ["hello"]).await?;
+
+    assert_eq!(echo, "hello");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn missing_params() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::default();
+
+    server
+        .handle0("version", || Ok(Some("1.0")))
+        .async_handle0("ping", || async { Ok(Some("pong")) });
+
+    server.accept(server_transport);
+
+    output
+        .send(r#"{"jsonrpc":"2.0","id":1,"method":"version"}"#.into())
+        .await?;
+
+    let response: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(response, json!({"jsonrpc":"2.0","id":1,"result":"1.0"}));
+
+    output
+        .send(r#"{"jsonrpc":"2.0","id":2,"method":"ping","params":[]}"#.into())
+        .await?;
+
+    let response: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(response, json!({"jsonrpc":"2.0","id":2,"result":"pong"}));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn lenient_version() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::builder("Test")
+        .lenient_version(true)
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .build();
+
+    server.accept(server_transport);
+
+    output
+        .send(r#"{"id":1,"method":"echo","params":["hello"]}"#.into())
+        .await?;
+
+    let response: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(response, json!({"jsonrpc":"2.0","id":1,"result":"hello"}));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn malformed_requests() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.handle("echo", |msg: String| Ok(Some(msg)));
+
+    server.accept(server_transport);
+
+    for (frame, code) in [
+        ("garbage", -32700),
+        ("42", -32600),
+        (r#""echo""#, -32600),
+        (r#"{"jsonrpc":"2.0","id":1}"#, -32600),
+    ] {
+        output.send(frame.into()).await?;
+
+        let response: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+        assert_eq!(response["jsonrpc"], "2.0");
+        assert_eq!(response["id"], serde_json::Value::Null);
+        assert_eq!(response["error"]["code"], code);
+    }
+
+    // The session is still serving.
+    output
+        .send(r#"{"jsonrpc":"2.0","id":2,"method":"echo","params":["hello"]}"#.into())
+        .await?;
+
+    let response: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(response, json!({"jsonrpc":"2.0","id":2,"result":"hello"}));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn compat_v1() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::builder("compat")
+        .compat_v1(true)
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .async_handle("sum", |values: Vec<i64>| async move {
+            Ok(Some(values.iter().sum::<i64>()))
+        })
+        .build();
+
+    server.accept(server_transport);
+
+    for (request, expected) in [
+        (
+            r#"{"id":1,"method":"echo","params":["hello"]}"#,
+            json!({"id":1,"result":"hello","error":null}),
+        ),
+        (
+            r#"{"jsonrpc":"1.0","id":2,"method":"sum","params":[1,2]}"#,
+            json!({"jsonrpc":"1.0","id":2,"result":3,"error":null}),
+        ),
+        (
+            r#"{"id":3,"method":"missing","params":[]}"#,
+            json!({"id":3,"result":null,"error":{"code":-32601,"message":"Method missing not found","data":null}}),
+        ),
+        // Version 2.0 requests are answered as before.
+        (
+            r#"{"jsonrpc":"2.0","id":4,"method":"echo","params":["hello"]}"#,
+            json!({"jsonrpc":"2.0","id":4,"result":"hello"}),
+        ),
+    ] {
+        output.send(request.into()).await?;
+
+        let response: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+        assert_eq!(response, expected);
+    }
+
+    // A 1.0 notification has a null id and isn't answered.
+    output
+        .send(r#"{"id":null,"method":"echo","params":["hello"]}"#.into())
+        .await?;
+
+    output
+        .send(r#"{"jsonrpc":"3.0","id":5,"method":"echo","params":["hello"]}"#.into())
+        .await?;
+
+    // Other versions are still rejected.
+    let response: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(response["id"], serde_json::Value::Null);
+    assert_eq!(response["error"]["code"], -32600);
+
+    Ok(())
+}
+
+#[cfg(feature = "discover")]
+#[async_std::test]
+async fn discover() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server
+        .handle_discover("echo service", "1.0.0")
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .describe::<String, String>("echo")
+        .handle_ping();
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let document: serde_json::Value = client.call("rpc.discover", ()).await?;
+
+    assert_eq!(document["openrpc"], "1.2.6");
+    assert_eq!(document["info"]["title"], "echo service");
+
+    let methods = document["methods"].as_array().unwrap();
+
+    assert_eq!(methods.len(), 2);
+    assert_eq!(methods[0]["name"], "echo");
+    assert_eq!(methods[0]["params"][0]["schema"]["type"], "string");
+    assert_eq!(methods[0]["result"]["schema"]["type"], "string");
+    assert_eq!(methods[1]["name"], "rpc.ping");
+    assert_eq!(methods[1]["params"][0]["schema"], json!({}));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn max_frame_size() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, MPSCTransportChannel(mut input, mut output)) = transport_pair();
+
+    let mut server = Server::builder("Test")
+        .max_frame_size(64)
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .build();
+
+    server.accept(server_transport);
+
+    let oversized = json!({"jsonrpc":"2.0","id":1,"method":"echo","params":["x".repeat(64)]});
+
+    output.send(oversized.to_string().into()).await?;
+
+    let response: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(response["id"], json!(null));
+    assert_eq!(response["error"]["code"], json!(-32600));
+
+    // The session keeps serving frames within the limit.
+    output
+        .send(r#"{"jsonrpc":"2.0","id":2,"method":"echo","params":["hi"]}"#.into())
+        .await?;
+
+    let response: serde_json::Value = serde_json::from_slice(&input.next().await.unwrap()?)?;
+
+    assert_eq!(response, json!({"jsonrpc":"2.0","id":2,"result":"hi"}));
+
+    Ok(())
+}
+
+#[cfg(feature = "traceparent")]
+#[async_std::test]
+async fn traceparent() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    let traced = Arc::new(std::sync::atomic::AtomicBool::new(true));
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.handle_with_ctx("trace", |ctx: Context, _: ()| {
+        Ok(Some(ctx.traceparent().map(str::to_owned)))
+    });
+
+    server.accept(server_transport);
+
+    let context = traced.clone();
+
+    let mut client = Client::with_config(
+        "Test",
+        client_transport,
+        ClientConfig::default().trace_context(move || {
+            context
+                .load(Ordering::SeqCst)
+                .then(|| TRACEPARENT.to_owned())
+        }),
+    );
+
+    let traceparent: Option<String> = client.call("trace", ()).await?;
+
+    assert_eq!(traceparent.as_deref(), Some(TRACEPARENT));
+
+    // Outside of a trace no context is propagated.
+    traced.store(false, Ordering::SeqCst);
+
+    let traceparent: Option<String> = client.call("trace", ()).await?;
+
+    assert_eq!(traceparent, None);
+
+    Ok(())
+}
+
+#[test]
+fn duplicated_registration() {
+    let mut server = Server::default();
+
+    server
+        .try_handle("echo", |msg: String| Ok(Some(msg)))
+        .unwrap()
+        .try_async_handle("ping", |_: ()| async { Ok(Some("pong")) })
+        .unwrap();
+
+    assert_eq!(
+        server
+            .try_async_handle("echo", |msg: String| async { Ok(Some(msg)) })
+            .err(),
+        Some(RegisterError::AlreadyRegistered("echo".to_owned()))
+    );
+
+    assert_eq!(
+        server.try_handle("ping", |_: ()| Ok(Some("pong"))).err(),
+        Some(RegisterError::AlreadyRegistered("ping".to_owned()))
+    );
+
+    assert_eq!(server.methods().collect::<Vec<_>>(), vec!["echo", "ping"]);
+
+    // Removal is visible through every clone sharing the register.
+    let shared = server.clone();
+
+    assert!(shared.has_method("echo"));
+    assert!(server.remove_method("echo"));
+    assert!(!shared.has_method("echo"));
+    assert!(!server.remove_method("echo"));
+
+    assert_eq!(shared.methods().collect::<Vec<_>>(), vec!["ping"]);
+}
+
+#[test]
+fn reserved_methods() {
+    let mut server = Server::default();
+
+    // Built-in methods may still be replaced.
+    server.handle(PING_METHOD, |_: ()| Ok(Some("pong")));
+
+    // Reserved methods are skipped.
+    server
+        .handle("rpc.custom", |_: ()| Ok(Some(())))
+        .async_handle("rpc.other", |_: ()| async { Ok(Some(())) })
+        .handle_shared("rpc.shared", |_: ()| Ok(Some(())));
+
+    assert_eq!(server.methods().collect::<Vec<_>>(), vec![PING_METHOD]);
+
+    assert_eq!(
+        server.try_handle("rpc.custom", |_: ()| Ok(Some(()))).err(),
+        Some(RegisterError::Reserved("rpc.custom".to_owned()))
+    );
+    assert_eq!(
+        RegisterError::Reserved("rpc.custom".to_owned()).to_string(),
+        "Method rpc.custom rejected, the rpc. prefix is reserved"
+    );
+
+    let server = Server::builder("Test")
+        .allow_reserved(true)
+        .handle("rpc.custom", |_: ()| Ok(Some(())))
+        .build();
+
+    assert!(server.has_method("rpc.custom"));
+}
+
+#[async_std::test]
+async fn default_handler() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.default_handler(|method, _, params| match method {
+        "forward" => Ok(Some(json!({ "method": method, "params": params }))),
+        _ => Err(RPCError::server_error(
+            -32050,
+            "Upstream unavailable",
+            json!(method),
+        )),
+    });
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let value: serde_json::Value = client.call("forward", [1, 2]).await?;
+
+    assert_eq!(value, json!({ "method": "forward", "params": [1, 2] }));
+
+    let err = client.call::<_, ()>("other", ()).await.unwrap_err();
+
+    assert_eq!(err.data, Some(json!("other")));
+
+    Ok(())
+}
+
+/// Records (method, is_error) of completed calls.
+#[derive(Clone, Default)]
+struct RecordingMetrics {
+    requests: Arc<AtomicUsize>,
+    responses: Arc<std::sync::Mutex<Vec<(String, bool)>>>,
+}
+
+impl Metrics for RecordingMetrics {
+    fn on_request(&self, _method: &str) {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn on_response(&self, method: &str, _elapsed: Duration, is_error: bool) {
+        self.responses
+            .lock()
+            .unwrap()
+            .push((method.to_owned(), is_error));
+    }
+}
+
+#[async_std::test]
+async fn metrics() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let server_metrics = RecordingMetrics::default();
+    let client_metrics = RecordingMetrics::default();
+
+    let mut server = Server::builder("Test")
+        .metrics(server_metrics.clone())
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .async_handle("fail", |_: ()| async {
+            Err::<Option<()>, _>(RPCError::server_error(-32010, "Failed", json!(null)))
+        })
+        .build();
+
+    server.accept(server_transport);
+
+    let mut client = Client::with_config(
+        "Test",
+        client_transport,
+        ClientConfig::default().metrics(client_metrics.clone()),
+    );
+
+    let echo: String = client.call("echo", "hello").await?;
+
+    assert_eq!(echo, "hello");
+
+    client.call::<_, ()>("fail", ()).await.unwrap_err();
+
+    let expected = vec![("echo".to_owned(), false), ("fail".to_owned(), true)];
+
+    for metrics in [server_metrics, client_metrics] {
+        assert_eq!(metrics.requests.load(Ordering::SeqCst), 2);
+        assert_eq!(*metrics.responses.lock().unwrap(), expected);
+    }
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn method_not_found() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    Server::default().accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let err = client.call::<_, ()>("missing", ()).await.unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::MethodNotFound);
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "tower")]
+
+use futures::future::poll_fn;
+use jsonrpc_rs::{Client, ErrorCode, RPCResult, Server};
+use serde_json::json;
+use tower_service::Service;
+
+mod common;
+use common::*;
+
+#[async_std::test]
+async fn tower_service() -> RPCResult<()> {
+    _ = pretty_env_logger::try_init();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server.handle("add", |(a, b): (i64, i64)| Ok(Some(a + b)));
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    poll_fn(|cx| client.poll_ready(cx)).await?;
+
+    let sum = Service::call(&mut client, ("add".to_owned(), json!([1, 2]))).await?;
+
+    assert_eq!(sum, json!(3));
+
+    poll_fn(|cx| client.poll_ready(cx)).await?;
+
+    let err = Service::call(&mut client, ("sub".to_owned(), json!([1, 2])))
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.code, ErrorCode::MethodNotFound);
+
+    Ok(())
+}
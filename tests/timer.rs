@@ -0,0 +1,29 @@
+use std::time::{Duration, Instant};
+
+use jsonrpc_rs::timer;
+
+#[async_std::test]
+async fn configure() {
+    _ = pretty_env_logger::try_init();
+
+    assert!(timer::configure(Duration::from_millis(5), 8));
+
+    // Too late, the wheel is running.
+    assert!(!timer::configure(Duration::from_secs(1), 3600));
+
+    let now = Instant::now();
+
+    timer::timeout(Duration::from_millis(12)).await;
+
+    // Rounded up to whole ticks, far below the default tick of 100ms.
+    assert!(now.elapsed() >= Duration::from_millis(12));
+    assert!(now.elapsed() < Duration::from_millis(80));
+
+    // Longer than a round of the wheel.
+    let now = Instant::now();
+
+    timer::timeout(Duration::from_millis(100)).await;
+
+    assert!(now.elapsed() >= Duration::from_millis(100));
+    assert!(now.elapsed() < Duration::from_millis(180));
+}
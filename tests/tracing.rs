@@ -0,0 +1,111 @@
+#![cfg(feature = "tracing")]
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+use jsonrpc_rs::{Client, RPCResult, Server};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+
+mod common;
+use common::*;
+
+/// Records the name and `method` field of every created span.
+#[derive(Default)]
+struct SpanRecorder {
+    next_id: AtomicU64,
+    spans: Mutex<Vec<(String, String)>>,
+}
+
+struct MethodVisitor(String);
+
+impl Visit for MethodVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "method" {
+            self.0 = value.to_owned();
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+impl Subscriber for &'static SpanRecorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+        let mut visitor = MethodVisitor(String::new());
+
+        span.record(&mut visitor);
+
+        self.spans
+            .lock()
+            .unwrap()
+            .push((span.metadata().name().to_owned(), visitor.0));
+
+        span::Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+#[async_std::test]
+async fn call_spans() -> RPCResult<()> {
+    let recorder: &'static SpanRecorder = Box::leak(Box::default());
+
+    tracing::subscriber::set_global_default(recorder).unwrap();
+
+    let (server_transport, client_transport) = transport_pair();
+
+    let mut server = Server::default();
+
+    server
+        .handle("echo", |msg: String| Ok(Some(msg)))
+        .async_handle("async_echo", |msg: String| async move { Ok(Some(msg)) });
+
+    server.accept(server_transport);
+
+    let mut client = Client::new("Test", client_transport);
+
+    let echo: String = client.call("echo", "hello").await?;
+
+    assert_eq!(echo, "hello");
+
+    let echo: String = client.call("async_echo", "hello").await?;
+
+    assert_eq!(echo, "hello");
+
+    let spans = recorder.spans.lock().unwrap().clone();
+
+    for expected in [
+        ("rpc.session", ""),
+        ("rpc.call", "echo"),
+        ("rpc.handle", "echo"),
+        ("rpc.call", "async_echo"),
+        ("rpc.handle", "async_echo"),
+    ] {
+        assert!(
+            spans
+                .iter()
+                .any(|(name, method)| (name.as_str(), method.as_str()) == expected),
+            "span {:?} not found in {:?}",
+            expected,
+            spans
+        );
+    }
+
+    Ok(())
+}